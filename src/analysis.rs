@@ -0,0 +1,1954 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::entity::{Entity, EntityType, ImportInfo, ReExport, Usage};
+use crate::graph::DependencyGraph;
+use crate::parser::{EXTERNAL_PREFIX, UNRESOLVED_PREFIX};
+use crate::scanner::ScanError;
+
+/// File names that are loaded by the build tooling rather than imported,
+/// so they must never be reported as deletable.
+const ENTRY_POINT_FILES: &[&str] = &["main.ts", "index.ts", "polyfills.ts", "test-setup.ts"];
+
+/// Checks whether a file is one of the built-in entry point files or matches one of
+/// the configured `entry_points` patterns.
+pub(crate) fn is_entry_point_file(path: &str, entry_points: &[String]) -> bool {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| ENTRY_POINT_FILES.contains(&name))
+        || entry_points
+            .iter()
+            .any(|pattern| matches_entry_point(path, pattern))
+}
+
+/// Checks whether a file matches an entry point pattern. Patterns containing `*`
+/// or `?` are globs matched against the whole path (or just the file name when the
+/// pattern has no `/`); other patterns match a file name or a trailing path.
+pub(crate) fn matches_entry_point(path: &str, pattern: &str) -> bool {
+    let path = Path::new(path);
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    let path_str = path.to_string_lossy();
+
+    if pattern.contains(['*', '?']) {
+        if pattern.contains('/') {
+            wildcard_match(pattern, &path_str)
+                || wildcard_match(&format!("*/{}", pattern), &path_str)
+        } else {
+            wildcard_match(pattern, file_name)
+        }
+    } else {
+        path.ends_with(pattern)
+    }
+}
+
+/// Matches `text` against a pattern where `*` matches any run of characters
+/// and `?` matches exactly one.
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Computes the ids of every entity reachable from the entry files, following
+/// imports. Entities defined in an entry file are reachable, as is everything the
+/// entry files import (even when they export nothing themselves, like `main.ts`).
+/// Reaching an entity also reaches the entities its file uses locally, and importing
+/// a name from a barrel reaches the declarations it re-exports rather than the barrel.
+///
+/// With `max_depth`, only entities within that many imports of the entry files are
+/// reached: those defined in or imported by an entry file are at depth 0, and
+/// locally used entities share the depth of the entity that reached their file.
+pub(crate) fn reachable(
+    entities: &HashMap<String, Entity>,
+    file_imports: &HashMap<String, Vec<ImportInfo>>,
+    file_re_exports: &HashMap<String, Vec<ReExport>>,
+    entry_files: &[String],
+    max_depth: Option<usize>,
+) -> HashSet<String> {
+    let mut entities_by_file: HashMap<&str, Vec<&Entity>> = HashMap::new();
+    for entity in entities.values() {
+        entities_by_file
+            .entry(&entity.file_path)
+            .or_default()
+            .push(entity);
+    }
+
+    let mut reached: HashSet<String> = HashSet::new();
+    // Breadth-first, so every entity is reached at its smallest depth
+    let mut queue: VecDeque<(&Entity, usize)> = VecDeque::new();
+    let mut visited_files: HashSet<&str> = HashSet::new();
+
+    fn visit<'a>(
+        entity: Option<&'a Entity>,
+        depth: usize,
+        reached: &mut HashSet<String>,
+        queue: &mut VecDeque<(&'a Entity, usize)>,
+    ) {
+        if let Some(entity) = entity
+            && reached.insert(entity.id.clone())
+        {
+            queue.push_back((entity, depth));
+        }
+    }
+
+    // The entities an import refers to: the imported declaration, or what a barrel re-exports
+    let imported = |import: &ImportInfo| -> Vec<&Entity> {
+        match entities.get(&import.id) {
+            Some(entity) if !entity.is_placeholder() => vec![entity],
+            _ if file_re_exports.contains_key(&import.path) => {
+                re_export_origins(entities, file_re_exports, &import.path, &import.name)
+                    .iter()
+                    .filter_map(|id| entities.get(id))
+                    .collect()
+            }
+            placeholder => placeholder.into_iter().collect(),
+        }
+    };
+
+    for entry_file in entry_files {
+        for entity in entities_by_file
+            .get(entry_file.as_str())
+            .into_iter()
+            .flatten()
+        {
+            visit(Some(entity), 0, &mut reached, &mut queue);
+        }
+        for import in file_imports.get(entry_file).into_iter().flatten() {
+            for entity in imported(import) {
+                visit(Some(entity), 0, &mut reached, &mut queue);
+            }
+        }
+    }
+
+    while let Some((entity, depth)) = queue.pop_front() {
+        if max_depth.is_none_or(|max_depth| depth < max_depth) {
+            for import in entity.deps.iter() {
+                for imported in imported(import) {
+                    visit(Some(imported), depth + 1, &mut reached, &mut queue);
+                }
+            }
+        }
+
+        if visited_files.insert(&entity.file_path) {
+            for sibling in entities_by_file
+                .get(entity.file_path.as_str())
+                .into_iter()
+                .flatten()
+            {
+                if sibling.used_locally {
+                    visit(Some(sibling), depth, &mut reached, &mut queue);
+                }
+            }
+        }
+    }
+
+    reached
+}
+
+/// Finds files that can be deleted as a whole: every entity defined in the
+/// file is unused and no other file imports anything from it. Entry point files,
+/// built-in or matching `entry_points`, are never deletable.
+/// Returns the sorted list of file paths.
+pub(crate) fn find_deletable_files(
+    entities: &HashMap<String, Entity>,
+    entry_points: &[String],
+) -> Vec<String> {
+    let mut candidate_files: HashSet<&str> = HashSet::new();
+    let mut kept_files: HashSet<&str> = HashSet::new();
+
+    for entity in entities.values() {
+        // Placeholders and used entities both mean something imports the file,
+        // and files whose entities are used by tests are kept as well
+        if entity.used || entity.used_in_tests || matches!(entity.entity_type, EntityType::Unknown)
+        {
+            kept_files.insert(&entity.file_path);
+        } else {
+            candidate_files.insert(&entity.file_path);
+        }
+
+        for import in entity.deps.iter() {
+            if import.path != entity.file_path {
+                kept_files.insert(&import.path);
+            }
+        }
+    }
+
+    let mut deletable: Vec<String> = candidate_files
+        .into_iter()
+        .filter(|path| !kept_files.contains(path) && !is_entry_point_file(path, entry_points))
+        .map(|path| path.to_string())
+        .collect();
+    deletable.sort();
+    deletable
+}
+
+/// Finds orphan files: parsed files that declare no entities and that no import or
+/// re-export resolves to, such as leftover side-effect scripts. Test files and entry
+/// point files (built-in or matching `entry_points`) are run by the tooling rather
+/// than imported and are never reported. Returns the sorted list of file paths.
+pub(crate) fn orphan_files(
+    entities: &HashMap<String, Entity>,
+    file_imports: &HashMap<String, Vec<ImportInfo>>,
+    file_re_exports: &HashMap<String, Vec<ReExport>>,
+    entry_points: &[String],
+) -> Vec<String> {
+    let declaring_files: HashSet<&str> = entities
+        .values()
+        .filter(|entity| !entity.is_placeholder())
+        .map(|entity| entity.file_path.as_str())
+        .collect();
+
+    let imported_files: HashSet<&str> = file_imports
+        .iter()
+        .flat_map(|(file, imports)| {
+            imports
+                .iter()
+                .filter(move |import| import.path != *file)
+                .map(|import| import.path.as_str())
+        })
+        .chain(
+            file_re_exports
+                .values()
+                .flatten()
+                .map(|re_export| re_export.origin.path.as_str()),
+        )
+        .collect();
+
+    let mut orphans: Vec<String> = file_imports
+        .keys()
+        .filter(|file| {
+            !declaring_files.contains(file.as_str())
+                && !imported_files.contains(file.as_str())
+                && !crate::is_test_file(file)
+                && !is_entry_point_file(file, entry_points)
+        })
+        .cloned()
+        .collect();
+    orphans.sort();
+    orphans
+}
+
+/// Finds barrel files: files that re-export other modules (`export ... from`) but
+/// declare no entities of their own. Returns the sorted list of file paths.
+pub(crate) fn barrel_files(
+    entities: &HashMap<String, Entity>,
+    file_re_exports: &HashMap<String, Vec<ReExport>>,
+) -> Vec<String> {
+    // Placeholders only mean that something imports from the file
+    let declaring_files: HashSet<&str> = entities
+        .values()
+        .filter(|entity| !matches!(entity.entity_type, EntityType::Unknown))
+        .map(|entity| entity.file_path.as_str())
+        .collect();
+
+    let mut barrels: Vec<String> = file_re_exports
+        .iter()
+        .filter(|(file, re_exports)| {
+            !re_exports.is_empty() && !declaring_files.contains(file.as_str())
+        })
+        .map(|(file, _)| file.clone())
+        .collect();
+    barrels.sort();
+    barrels
+}
+
+/// Follows re-exports so that importing a name from a barrel marks the entity
+/// where it is declared as used, rather than only the barrel's placeholder. Usage
+/// by test files is forwarded the same way into `used_in_tests`. Chains of barrels
+/// are followed until a declaration is found; namespace re-exports
+/// (`export * as ns`) are not followed.
+pub(crate) fn propagate_re_export_usage(
+    entities: &mut HashMap<String, Entity>,
+    file_re_exports: &HashMap<String, Vec<ReExport>>,
+) {
+    for from_test in [false, true] {
+        let origins: Vec<(String, Usage)> = entities
+            .values()
+            .filter(|entity| {
+                matches!(entity.entity_type, EntityType::Unknown)
+                    && file_re_exports.contains_key(&entity.file_path)
+                    && if from_test {
+                        entity.used_in_tests
+                    } else {
+                        entity.used
+                    }
+            })
+            .flat_map(|entity| {
+                let usage = Usage::ReExport {
+                    barrel: entity.file_path.clone(),
+                    via: entity.used_by.clone().map(Box::new),
+                };
+                re_export_origins(entities, file_re_exports, &entity.file_path, &entity.name)
+                    .into_iter()
+                    .map(move |id| (id, usage.clone()))
+            })
+            .collect();
+
+        for (id, usage) in origins {
+            if let Some(entity) = entities.get_mut(&id) {
+                if from_test {
+                    entity.used_in_tests = true;
+                } else {
+                    entity.mark_used(usage);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the ids of the declarations that `name`, as exported by the barrel
+/// `file`, stands for. Chains of barrels are followed until a declaration is found;
+/// namespace re-exports (`export * as ns`) are not followed.
+fn re_export_origins(
+    entities: &HashMap<String, Entity>,
+    file_re_exports: &HashMap<String, Vec<ReExport>>,
+    file: &str,
+    name: &str,
+) -> Vec<String> {
+    let mut origin_ids = Vec::new();
+    let mut queue: VecDeque<(String, String)> =
+        VecDeque::from([(file.to_string(), name.to_string())]);
+    let mut visited: HashSet<(String, String)> = queue.iter().cloned().collect();
+
+    while let Some((file, name)) = queue.pop_front() {
+        for re_export in file_re_exports.get(&file).into_iter().flatten() {
+            let origin = &re_export.origin;
+            let target = if re_export.is_wildcard() {
+                ImportInfo::new(name.clone(), origin.path.clone())
+            } else if re_export.exported_name == name && origin.name != "*" {
+                origin.clone()
+            } else {
+                continue;
+            };
+
+            if !visited.insert((target.path.clone(), target.name.clone())) {
+                continue;
+            }
+
+            match entities.get(&target.id) {
+                Some(entity) if !matches!(entity.entity_type, EntityType::Unknown) => {
+                    origin_ids.push(target.id);
+                }
+                // Not declared there, so the target may be a barrel itself
+                _ => queue.push_back((target.path, target.name)),
+            }
+        }
+    }
+
+    origin_ids
+}
+
+/// Builds a file-to-file coupling matrix. Rows are importing files and columns are
+/// imported files, both in the order of the returned (sorted) file list. Each cell
+/// counts the distinct entities the row file imports from the column file.
+pub(crate) fn coupling_matrix(
+    entities: &HashMap<String, Entity>,
+) -> (Vec<String>, Vec<Vec<usize>>) {
+    let mut files: Vec<String> = entities
+        .values()
+        .map(|entity| entity.file_path.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    files.sort();
+
+    let file_index: HashMap<&str, usize> = files
+        .iter()
+        .enumerate()
+        .map(|(i, file)| (file.as_str(), i))
+        .collect();
+
+    let mut matrix = vec![vec![0; files.len()]; files.len()];
+    let mut counted: HashSet<(&str, &str)> = HashSet::new();
+
+    for entity in entities.values() {
+        for import in entity.deps.iter() {
+            // Imports are shared by every entity in a file, so count each one once
+            if !entities.contains_key(&import.id)
+                || !counted.insert((entity.file_path.as_str(), import.id.as_str()))
+            {
+                continue;
+            }
+
+            if let (Some(&row), Some(&col)) = (
+                file_index.get(entity.file_path.as_str()),
+                file_index.get(import.path.as_str()),
+            ) {
+                matrix[row][col] += 1;
+            }
+        }
+    }
+
+    (files, matrix)
+}
+
+/// Graph degree metrics for a single entity.
+#[derive(Debug)]
+pub(crate) struct EntityMetrics<'a> {
+    pub entity: &'a Entity,
+    /// Number of entities that depend on this entity
+    pub in_degree: usize,
+    /// Number of resolved dependencies of this entity
+    pub out_degree: usize,
+}
+
+/// Computes in- and out-degree for every entity, based on the resolved edges of
+/// the dependency graph. Sorted by in-degree, then out-degree (both descending),
+/// then by name and id so the order is stable.
+pub(crate) fn entity_metrics(entities: &HashMap<String, Entity>) -> Vec<EntityMetrics<'_>> {
+    let graph = DependencyGraph::from_entities(entities);
+
+    let mut in_degrees: HashMap<&str, usize> = HashMap::new();
+    let mut out_degrees: HashMap<&str, usize> = HashMap::new();
+    for edge in &graph.edges {
+        *in_degrees.entry(&edge.target).or_default() += 1;
+        *out_degrees.entry(&edge.source).or_default() += 1;
+    }
+
+    let mut metrics: Vec<EntityMetrics> = entities
+        .values()
+        .map(|entity| EntityMetrics {
+            entity,
+            in_degree: in_degrees.get(entity.id.as_str()).copied().unwrap_or(0),
+            out_degree: out_degrees.get(entity.id.as_str()).copied().unwrap_or(0),
+        })
+        .collect();
+
+    metrics.sort_by(|a, b| {
+        b.in_degree
+            .cmp(&a.in_degree)
+            .then(b.out_degree.cmp(&a.out_degree))
+            .then(a.entity.name.cmp(&b.entity.name))
+            .then(a.entity.id.cmp(&b.entity.id))
+    });
+
+    metrics
+}
+
+/// Returns the top `n` entities by number of dependents.
+pub(crate) fn most_depended_upon(
+    entities: &HashMap<String, Entity>,
+    n: usize,
+) -> Vec<EntityMetrics<'_>> {
+    let mut metrics = entity_metrics(entities);
+    metrics.truncate(n);
+    metrics
+}
+
+/// Returns the metrics of the declared entities with more dependencies than
+/// `max_dependencies` or more dependents than `max_dependents`, in the order of
+/// [`entity_metrics`]. A `None` limit is not checked.
+pub(crate) fn threshold_violations(
+    entities: &HashMap<String, Entity>,
+    max_dependencies: Option<usize>,
+    max_dependents: Option<usize>,
+) -> Vec<EntityMetrics<'_>> {
+    let exceeds = |count: usize, max: Option<usize>| max.is_some_and(|max| count > max);
+
+    entity_metrics(entities)
+        .into_iter()
+        .filter(|metric| !metric.entity.is_placeholder())
+        .filter(|metric| {
+            exceeds(metric.out_degree, max_dependencies)
+                || exceeds(metric.in_degree, max_dependents)
+        })
+        .collect()
+}
+
+/// Returns the declared entities called `name`, sorted by file path and line.
+/// Placeholders for unresolved imports are left out.
+pub(crate) fn entities_named<'a>(
+    entities: &'a HashMap<String, Entity>,
+    name: &str,
+) -> Vec<&'a Entity> {
+    let mut named: Vec<&Entity> = entities
+        .values()
+        .filter(|entity| entity.name == name && !entity.is_placeholder())
+        .collect();
+    named.sort_by(|a, b| (&a.file_path, a.line).cmp(&(&b.file_path, b.line)));
+    named
+}
+
+/// Groups the files importing external packages by package name. Only imports
+/// recorded with an `external:<package>` path are considered.
+pub(crate) fn external_packages(
+    file_imports: &HashMap<String, Vec<ImportInfo>>,
+) -> BTreeMap<&str, Vec<&str>> {
+    let mut packages: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+
+    for (file, imports) in file_imports {
+        for import in imports {
+            if let Some(package) = import.path.strip_prefix(EXTERNAL_PREFIX) {
+                packages.entry(package).or_default().push(file);
+            }
+        }
+    }
+
+    for files in packages.values_mut() {
+        files.sort();
+        files.dedup();
+    }
+
+    packages
+}
+
+/// Collects, for each declared entity, the external packages it depends on directly
+/// or through the entities it imports. An entity's imports are those of its file, so
+/// the entities of one file share their packages. Entities without external
+/// dependencies are left out; the rest are sorted by file and line.
+pub(crate) fn transitive_external_packages(
+    entities: &HashMap<String, Entity>,
+) -> Vec<(&Entity, BTreeSet<&str>)> {
+    let declared: Vec<&Entity> = entities.values().filter(|e| !e.is_placeholder()).collect();
+    let file_deps: HashMap<&str, &[ImportInfo]> = declared
+        .iter()
+        .map(|entity| (entity.file_path.as_str(), entity.deps.as_slice()))
+        .collect();
+
+    let mut packages_by_file: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+    for &file in file_deps.keys() {
+        let mut packages = BTreeSet::new();
+        let mut visited: HashSet<&str> = HashSet::from([file]);
+        let mut queue = VecDeque::from([file]);
+
+        while let Some(current) = queue.pop_front() {
+            for import in file_deps.get(current).copied().unwrap_or_default() {
+                if let Some(package) = import.path.strip_prefix(EXTERNAL_PREFIX) {
+                    packages.insert(package);
+                } else if let Some(imported) = entities.get(&import.id)
+                    && !imported.is_placeholder()
+                    && visited.insert(&imported.file_path)
+                {
+                    queue.push_back(&imported.file_path);
+                }
+            }
+        }
+        packages_by_file.insert(file, packages);
+    }
+
+    let mut found: Vec<(&Entity, BTreeSet<&str>)> = declared
+        .into_iter()
+        .filter_map(|entity| {
+            let packages = packages_by_file.get(entity.file_path.as_str())?;
+            (!packages.is_empty()).then(|| (entity, packages.clone()))
+        })
+        .collect();
+    found.sort_by(|(a, _), (b, _)| {
+        (&a.file_path, a.line, &a.name).cmp(&(&b.file_path, b.line, &b.name))
+    });
+    found
+}
+
+/// Finds imports whose resolved path is the importing file itself, which usually
+/// points at a bug or a misconfigured alias. Returns `(file, import)` pairs sorted
+/// by file and imported name.
+pub(crate) fn self_imports(
+    file_imports: &HashMap<String, Vec<ImportInfo>>,
+) -> Vec<(&str, &ImportInfo)> {
+    let mut found: Vec<(&str, &ImportInfo)> = file_imports
+        .iter()
+        .flat_map(|(file, imports)| {
+            imports
+                .iter()
+                .filter(move |import| import.path == *file)
+                .map(move |import| (file.as_str(), import))
+        })
+        .collect();
+    found.sort_by(|a, b| (a.0, &a.1.name).cmp(&(b.0, &b.1.name)));
+    found
+}
+
+/// An import of a file that exists on disk but lies outside the scanned directories,
+/// so the imported entities are only known as placeholders.
+#[derive(Debug)]
+pub(crate) struct OutOfScopeImport<'a> {
+    pub file: &'a str,
+    pub import: &'a ImportInfo,
+    /// Directory to scan as well: relative to the project root, or absolute when
+    /// the imported file is outside the project
+    pub suggested_dir: String,
+}
+
+/// Finds imports resolving to an existing file that is not below any of `scan_dirs`
+/// (relative to `root`). Returns them sorted by file and imported name.
+pub(crate) fn out_of_scope_imports<'a>(
+    file_imports: &'a HashMap<String, Vec<ImportInfo>>,
+    root: &str,
+    scan_dirs: &[&str],
+) -> Vec<OutOfScopeImport<'a>> {
+    let root = root.trim_end_matches('/');
+    let scan_dirs: Vec<&str> = scan_dirs
+        .iter()
+        .map(|dir| dir.trim_start_matches("./").trim_end_matches('/'))
+        .collect();
+
+    let mut found = Vec::new();
+    for (file, imports) in file_imports {
+        for import in imports {
+            // Placeholder paths such as `external:rxjs` are never files
+            if !Path::new(&import.path).is_file() {
+                continue;
+            }
+
+            let suggested_dir = match import
+                .path
+                .strip_prefix(root)
+                .and_then(|path| path.strip_prefix('/'))
+            {
+                Some(relative) if scan_dirs.iter().any(|dir| is_below(relative, dir)) => continue,
+                Some(relative) => suggested_scan_dir(relative, &scan_dirs),
+                None => import
+                    .path
+                    .rsplit_once('/')
+                    .map_or_else(|| import.path.clone(), |(dir, _)| dir.to_string()),
+            };
+            found.push(OutOfScopeImport {
+                file,
+                import,
+                suggested_dir,
+            });
+        }
+    }
+
+    found.sort_by(|a, b| (a.file, &a.import.name).cmp(&(b.file, &b.import.name)));
+    found
+}
+
+/// An import from a file in one layer of a file in a layer above it, such as a
+/// library importing from an application.
+#[derive(Debug)]
+pub(crate) struct LayerViolation<'a> {
+    pub file: &'a str,
+    pub import: &'a ImportInfo,
+    pub from_layer: &'a str,
+    pub to_layer: &'a str,
+}
+
+/// Finds imports that go against the layering. `layers` are directories relative to
+/// `root`, from the top (e.g. `apps`) to the bottom (e.g. `libs`); a file may import
+/// from its own layer and the layers below it. Files outside every layer are not
+/// checked. Returns the violations sorted by file and imported name.
+pub(crate) fn layer_violations<'a>(
+    file_imports: &'a HashMap<String, Vec<ImportInfo>>,
+    root: &str,
+    layers: &'a [String],
+) -> Vec<LayerViolation<'a>> {
+    let root = root.trim_end_matches('/');
+    let layer_of = |path: &str| {
+        let relative = path.strip_prefix(root)?.strip_prefix('/')?;
+        layers.iter().position(|layer| {
+            is_below(
+                relative,
+                layer.trim_start_matches("./").trim_end_matches('/'),
+            )
+        })
+    };
+
+    let mut found = Vec::new();
+    for (file, imports) in file_imports {
+        let Some(from) = layer_of(file) else {
+            continue;
+        };
+
+        for import in imports {
+            // A missing file still shows which layer the import reaches into
+            let path = import
+                .path
+                .strip_prefix(UNRESOLVED_PREFIX)
+                .unwrap_or(&import.path);
+            if let Some(to) = layer_of(path)
+                && to < from
+            {
+                found.push(LayerViolation {
+                    file,
+                    import,
+                    from_layer: &layers[from],
+                    to_layer: &layers[to],
+                });
+            }
+        }
+    }
+
+    found.sort_by(|a, b| (a.file, &a.import.name).cmp(&(b.file, &b.import.name)));
+    found
+}
+
+/// Whether the relative `path` lies below the relative directory `dir`.
+fn is_below(path: &str, dir: &str) -> bool {
+    dir.is_empty()
+        || dir == "."
+        || path
+            .strip_prefix(dir)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// The outermost directory of `relative_path` that contains none of the scanned
+/// directories, e.g. `apps/admin` for `apps/admin/src/a.ts` when `apps/web` is scanned.
+fn suggested_scan_dir(relative_path: &str, scan_dirs: &[&str]) -> String {
+    relative_path
+        .match_indices('/')
+        .map(|(index, _)| &relative_path[..index])
+        .find(|dir| !scan_dirs.iter().any(|scanned| is_below(scanned, dir)))
+        .unwrap_or(".")
+        .to_string()
+}
+
+/// Project-wide summary counts. Import placeholders (`Unknown` entities) are not counted.
+#[derive(Debug, Serialize)]
+pub(crate) struct Stats {
+    pub total_entities: usize,
+    pub unused_entities: usize,
+    /// Number of parsed files, including files that export nothing
+    pub files: usize,
+    pub average_dependencies: f64,
+    /// Entity counts keyed by type name, in alphabetical order
+    pub by_type: BTreeMap<String, usize>,
+    /// Directories and files skipped because they could not be read or parsed
+    pub errors: Vec<ScanError>,
+}
+
+/// Aggregates the entity map into summary counts.
+pub(crate) fn stats(
+    entities: &HashMap<String, Entity>,
+    file_imports: &HashMap<String, Vec<ImportInfo>>,
+    errors: &[ScanError],
+) -> Stats {
+    let mut by_type: BTreeMap<String, usize> = BTreeMap::new();
+    let mut total_entities = 0;
+    let mut unused_entities = 0;
+    let mut total_dependencies = 0;
+
+    for entity in entities.values() {
+        if matches!(entity.entity_type, EntityType::Unknown) {
+            continue;
+        }
+        total_entities += 1;
+        total_dependencies += entity.deps.len();
+        if !entity.used {
+            unused_entities += 1;
+        }
+        *by_type.entry(entity.entity_type.to_string()).or_default() += 1;
+    }
+
+    let average_dependencies = if total_entities == 0 {
+        0.0
+    } else {
+        total_dependencies as f64 / total_entities as f64
+    };
+
+    Stats {
+        total_entities,
+        unused_entities,
+        files: file_imports.len(),
+        average_dependencies,
+        by_type,
+        errors: errors.to_vec(),
+    }
+}
+
+/// Declared and unused entities of one directory, see [`distribution`].
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub(crate) struct DirectoryCount {
+    pub directory: String,
+    pub entities: usize,
+    pub unused: usize,
+}
+
+/// Counts declared entities and unused ones per directory relative to `root`. With
+/// `depth`, an entity counts toward the first `depth` directories of its file path
+/// (`libs/shared` for depth 2); otherwise toward the scanned directory containing it,
+/// or its top-level directory. Import placeholders are not counted. Sorted by directory.
+pub(crate) fn distribution(
+    entities: &HashMap<String, Entity>,
+    root: &str,
+    scan_dirs: &[&str],
+    depth: Option<usize>,
+) -> Vec<DirectoryCount> {
+    let root = root.trim_end_matches('/');
+    let scan_dirs: Vec<&str> = scan_dirs
+        .iter()
+        .map(|dir| dir.trim_start_matches("./").trim_end_matches('/'))
+        .collect();
+
+    let mut counts: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    for entity in entities.values() {
+        if entity.is_placeholder() {
+            continue;
+        }
+
+        let path = entity.file_path.as_str();
+        let relative = path
+            .strip_prefix(root)
+            .and_then(|path| path.strip_prefix('/'))
+            .unwrap_or(path);
+        let directory = directory_bucket(relative, &scan_dirs, depth);
+
+        let (total, unused) = counts.entry(directory).or_default();
+        *total += 1;
+        if !entity.used {
+            *unused += 1;
+        }
+    }
+
+    counts
+        .into_iter()
+        .map(|(directory, (entities, unused))| DirectoryCount {
+            directory,
+            entities,
+            unused,
+        })
+        .collect()
+}
+
+/// The directory `relative_path` is counted toward by [`distribution`].
+fn directory_bucket(relative_path: &str, scan_dirs: &[&str], depth: Option<usize>) -> String {
+    let Some((dir, _)) = relative_path.rsplit_once('/') else {
+        return ".".to_string();
+    };
+
+    match depth {
+        Some(depth) => dir
+            .split('/')
+            .take(depth.max(1))
+            .collect::<Vec<_>>()
+            .join("/"),
+        None => scan_dirs
+            .iter()
+            .filter(|scanned| !scanned.is_empty() && **scanned != ".")
+            .filter(|scanned| is_below(relative_path, scanned))
+            .max_by_key(|scanned| scanned.len())
+            .map_or_else(
+                || dir.split('/').next().unwrap_or(dir).to_string(),
+                |scanned| scanned.to_string(),
+            ),
+    }
+}
+
+/// Size of the entity map, split into declarations found in scanned files and
+/// placeholders created for imports that did not resolve to one.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct EntityCounts {
+    pub defined: usize,
+    pub placeholders: usize,
+}
+
+impl EntityCounts {
+    pub fn total(&self) -> usize {
+        self.defined + self.placeholders
+    }
+}
+
+pub(crate) fn entity_counts(entities: &HashMap<String, Entity>) -> EntityCounts {
+    let mut counts = EntityCounts::default();
+    for entity in entities.values() {
+        if entity.is_placeholder() {
+            counts.placeholders += 1;
+        } else {
+            counts.defined += 1;
+        }
+    }
+    counts
+}
+
+/// Finds all entities that become removable once the unused ones are deleted.
+///
+/// Starts from the unused entities and repeatedly removes them, dropping the
+/// edges they contribute. An entity whose every dependent has been removed is
+/// dead as well, and the process continues until a fixed point is reached.
+/// Entities that are marked used without any dependent in the map (used locally
+/// or by a file with no exports) and entities in entry point files (built-in or
+/// matching `entry_points`) are kept alive. With `reachable_ids`, as computed by
+/// `reachable` from the entry points, the unused entities are the ones not
+/// reached rather than the ones not marked used.
+/// Returns the dead entities sorted by file path and name.
+pub(crate) fn dead_code<'a>(
+    entities: &'a HashMap<String, Entity>,
+    reachable_ids: Option<&HashSet<String>>,
+    entry_points: &[String],
+) -> Vec<&'a Entity> {
+    let graph = DependencyGraph::from_entities(entities);
+
+    let mut dependents: HashMap<&str, usize> = HashMap::new();
+    let mut dependencies: HashMap<&str, Vec<&str>> = HashMap::new();
+    for edge in &graph.edges {
+        if edge.source == edge.target {
+            continue;
+        }
+        *dependents.entry(&edge.target).or_default() += 1;
+        dependencies
+            .entry(&edge.source)
+            .or_default()
+            .push(&edge.target);
+    }
+
+    let is_removable = |entity: &Entity| {
+        !matches!(entity.entity_type, EntityType::Unknown)
+            && !is_entry_point_file(&entity.file_path, entry_points)
+    };
+    let is_unused = |entity: &Entity| match reachable_ids {
+        Some(ids) => !ids.contains(&entity.id),
+        None => !entity.used && !entity.used_in_tests,
+    };
+
+    let mut dead: HashSet<&str> = HashSet::new();
+    let mut queue: VecDeque<&str> = VecDeque::new();
+    for entity in entities.values() {
+        if is_unused(entity) && is_removable(entity) {
+            dead.insert(&entity.id);
+            queue.push_back(&entity.id);
+        }
+    }
+
+    while let Some(current) = queue.pop_front() {
+        let Some(targets) = dependencies.get(current) else {
+            continue;
+        };
+
+        for &target in targets {
+            let Some(count) = dependents.get_mut(target) else {
+                continue;
+            };
+            *count -= 1;
+
+            if *count == 0
+                && !dead.contains(target)
+                && entities.get(target).is_some_and(is_removable)
+            {
+                dead.insert(target);
+                queue.push_back(target);
+            }
+        }
+    }
+
+    let mut dead_entities: Vec<&Entity> = dead.iter().filter_map(|id| entities.get(*id)).collect();
+    dead_entities.sort_by(|a, b| (&a.file_path, &a.name).cmp(&(&b.file_path, &b.name)));
+    dead_entities
+}
+
+/// Changes between two snapshots of the entities of a project.
+#[derive(Debug, Default, Serialize)]
+pub struct SnapshotDiff<'a> {
+    /// Entities only in the newer snapshot
+    pub added: Vec<&'a Entity>,
+    /// Entities only in the older snapshot
+    pub removed: Vec<&'a Entity>,
+    /// Entities in both snapshots that were used before and are not anymore
+    pub newly_unused: Vec<&'a Entity>,
+    /// Entities in both snapshots that were unused before and are used now
+    pub newly_used: Vec<&'a Entity>,
+    /// Entities in both snapshots whose declaration changed
+    pub modified: Vec<&'a Entity>,
+}
+
+/// The directory shared by the files declaring a snapshot's entities, ending in `/`.
+fn snapshot_root(entities: &[Entity]) -> &str {
+    let mut paths = entities
+        .iter()
+        .filter(|entity| !entity.is_placeholder())
+        .map(|entity| entity.file_path.as_str());
+    let Some(first) = paths.next() else {
+        return "";
+    };
+    let mut root = first.rfind('/').map_or("", |end| &first[..=end]);
+    for path in paths {
+        while !path.starts_with(root) {
+            root = root[..root.len() - 1]
+                .rfind('/')
+                .map_or("", |end| &root[..=end]);
+        }
+    }
+    root
+}
+
+/// Compares two snapshots, matching entities by name and file path relative to the
+/// directory shared by each snapshot's files, so snapshots taken in checkouts at
+/// different paths compare. When one of these directories contains the other, as
+/// for two snapshots of one checkout, both use the outer one. An entity is modified
+/// when both snapshots have a content hash for it and they differ, so snapshots
+/// written without hashes report no modifications. Placeholders are left out;
+/// the entities listed in each category are taken from the snapshot they are in
+/// (the newer one unless removed) and sorted by location.
+pub fn diff_snapshots<'a>(before: &'a [Entity], after: &'a [Entity]) -> SnapshotDiff<'a> {
+    let mut before_root = snapshot_root(before);
+    let mut after_root = snapshot_root(after);
+    if before_root.starts_with(after_root) {
+        before_root = after_root;
+    } else if after_root.starts_with(before_root) {
+        after_root = before_root;
+    }
+
+    let declared =
+        |entities: &'a [Entity], root: &str| -> HashMap<(&'a str, &'a str), &'a Entity> {
+            entities
+                .iter()
+                .filter(|entity| !entity.is_placeholder())
+                .map(|entity| {
+                    let relative_path = entity
+                        .file_path
+                        .strip_prefix(root)
+                        .unwrap_or(&entity.file_path);
+                    ((relative_path, entity.name.as_str()), entity)
+                })
+                .collect()
+        };
+    let before = declared(before, before_root);
+    let after = declared(after, after_root);
+
+    let mut diff = SnapshotDiff::default();
+    for (key, &entity) in &after {
+        let Some(previous) = before.get(key) else {
+            diff.added.push(entity);
+            continue;
+        };
+        if previous.used && !entity.used {
+            diff.newly_unused.push(entity);
+        } else if !previous.used && entity.used {
+            diff.newly_used.push(entity);
+        }
+        if let (Some(old), Some(new)) = (&previous.content_hash, &entity.content_hash)
+            && old != new
+        {
+            diff.modified.push(entity);
+        }
+    }
+    diff.removed = before
+        .iter()
+        .filter(|(key, _)| !after.contains_key(*key))
+        .map(|(_, &entity)| entity)
+        .collect();
+
+    for entities in [
+        &mut diff.added,
+        &mut diff.removed,
+        &mut diff.newly_unused,
+        &mut diff.newly_used,
+        &mut diff.modified,
+    ] {
+        entities.sort_by(|a, b| (&a.file_path, a.line, &a.id).cmp(&(&b.file_path, b.line, &b.id)));
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::ImportInfo;
+    use std::rc::Rc;
+
+    fn create_entity(
+        name: &str,
+        entity_type: EntityType,
+        file_path: &str,
+        deps: Vec<ImportInfo>,
+    ) -> Entity {
+        Entity::new(
+            name.to_string(),
+            entity_type,
+            file_path.to_string(),
+            Rc::new(deps),
+        )
+    }
+
+    fn insert(entities: &mut HashMap<String, Entity>, entity: Entity) {
+        entities.insert(entity.id.clone(), entity);
+    }
+
+    /// `Hub` is used by `A`, `B` and `C`; `C` also depends on `A` and `B`.
+    fn lint_fixture() -> HashMap<String, Entity> {
+        let import = |name: &str| ImportInfo::new(name.to_string(), format!("/src/{}.ts", name));
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+        insert(
+            &mut entities,
+            create_entity("Hub", EntityType::Class, "/src/Hub.ts", vec![]),
+        );
+        for name in ["A", "B"] {
+            insert(
+                &mut entities,
+                create_entity(
+                    name,
+                    EntityType::Class,
+                    &format!("/src/{}.ts", name),
+                    vec![import("Hub")],
+                ),
+            );
+        }
+        insert(
+            &mut entities,
+            create_entity(
+                "C",
+                EntityType::Class,
+                "/src/C.ts",
+                vec![import("Hub"), import("A"), import("B")],
+            ),
+        );
+        entities
+    }
+
+    #[test]
+    fn test_entities_named_finds_every_file() {
+        let mut entities = HashMap::new();
+        insert(
+            &mut entities,
+            create_entity("UserService", EntityType::Class, "/src/web/user.ts", vec![]),
+        );
+        insert(
+            &mut entities,
+            create_entity(
+                "UserService",
+                EntityType::Class,
+                "/src/admin/user.ts",
+                vec![],
+            ),
+        );
+        insert(
+            &mut entities,
+            create_entity("UserStore", EntityType::Class, "/src/web/store.ts", vec![]),
+        );
+        insert(
+            &mut entities,
+            create_entity(
+                "UserService",
+                EntityType::Unknown,
+                "/lib/missing.ts",
+                vec![],
+            ),
+        );
+
+        let files: Vec<&str> = entities_named(&entities, "UserService")
+            .iter()
+            .map(|entity| entity.file_path.as_str())
+            .collect();
+        assert_eq!(files, ["/src/admin/user.ts", "/src/web/user.ts"]);
+        assert!(entities_named(&entities, "User").is_empty());
+    }
+
+    #[test]
+    fn test_threshold_violations_for_dependencies() {
+        let entities = lint_fixture();
+
+        let violations = threshold_violations(&entities, Some(2), None);
+        let names: Vec<(&str, usize)> = violations
+            .iter()
+            .map(|metric| (metric.entity.name.as_str(), metric.out_degree))
+            .collect();
+        assert_eq!(names, [("C", 3)]);
+
+        assert!(threshold_violations(&entities, Some(3), None).is_empty());
+    }
+
+    #[test]
+    fn test_threshold_violations_for_dependents() {
+        let entities = lint_fixture();
+
+        let violations = threshold_violations(&entities, None, Some(0));
+        let names: Vec<(&str, usize)> = violations
+            .iter()
+            .map(|metric| (metric.entity.name.as_str(), metric.in_degree))
+            .collect();
+        assert_eq!(names, [("Hub", 3), ("A", 1), ("B", 1)]);
+
+        let violations = threshold_violations(&entities, Some(2), Some(2));
+        let names: Vec<&str> = violations.iter().map(|m| m.entity.name.as_str()).collect();
+        assert_eq!(names, ["Hub", "C"]);
+    }
+
+    #[test]
+    fn test_entity_counts_separates_placeholders() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+        insert(
+            &mut entities,
+            create_entity("Service", EntityType::Class, "/src/service.ts", vec![]),
+        );
+        insert(
+            &mut entities,
+            create_entity("helper", EntityType::Function, "/src/util.ts", vec![]),
+        );
+        insert(
+            &mut entities,
+            create_entity("External", EntityType::Unknown, "/lib/x.ts", vec![]),
+        );
+
+        let counts = entity_counts(&entities);
+
+        assert_eq!(
+            counts,
+            EntityCounts {
+                defined: 2,
+                placeholders: 1
+            }
+        );
+        assert_eq!(counts.total(), 3);
+    }
+
+    #[test]
+    fn test_layer_violations_flag_imports_into_upper_layers() {
+        let import = |name: &str, path: &str| ImportInfo::new(name.to_string(), path.to_string());
+        let mut file_imports: HashMap<String, Vec<ImportInfo>> = HashMap::new();
+        file_imports.insert(
+            "/repo/libs/ui/button.ts".to_string(),
+            vec![
+                import("AppConfig", "/repo/apps/web/config.ts"),
+                import("Theme", "/repo/libs/ui/theme.ts"),
+                import("Component", "external:@angular/core"),
+            ],
+        );
+        file_imports.insert(
+            "/repo/apps/web/main.ts".to_string(),
+            vec![import("Button", "/repo/libs/ui/button.ts")],
+        );
+        file_imports.insert(
+            "/repo/tools/script.ts".to_string(),
+            vec![import("AppConfig", "/repo/apps/web/config.ts")],
+        );
+        let layers = ["apps".to_string(), "libs/".to_string()];
+
+        let found = layer_violations(&file_imports, "/repo/", &layers);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].file, "/repo/libs/ui/button.ts");
+        assert_eq!(found[0].import.name, "AppConfig");
+        assert_eq!((found[0].from_layer, found[0].to_layer), ("libs/", "apps"));
+    }
+
+    #[test]
+    fn test_distribution_buckets_entities_by_directory() {
+        let entity = |name: &str, file: &str, used: bool| {
+            let mut entity = Entity::new(
+                name.to_string(),
+                EntityType::Class,
+                file.to_string(),
+                Rc::new(vec![]),
+            );
+            entity.used = used;
+            entity
+        };
+        let entities: HashMap<String, Entity> = [
+            entity("A", "/repo/apps/web/src/a.ts", true),
+            entity("B", "/repo/apps/web/src/b/b.ts", false),
+            entity("C", "/repo/libs/shared/src/c.ts", true),
+            entity("D", "/repo/libs/ui/d.ts", false),
+            entity("E", "/repo/libs/ui/e.ts", false),
+            Entity::new(
+                "Missing".to_string(),
+                EntityType::Unknown,
+                "/repo/libs/missing.ts".to_string(),
+                Rc::new(vec![]),
+            ),
+        ]
+        .into_iter()
+        .map(|entity| (entity.id.clone(), entity))
+        .collect();
+
+        let count = |directory: &str, entities: usize, unused: usize| DirectoryCount {
+            directory: directory.to_string(),
+            entities,
+            unused,
+        };
+
+        let by_scan_dir = distribution(&entities, "/repo", &["apps/web", "libs"], None);
+        assert_eq!(by_scan_dir, [count("apps/web", 2, 1), count("libs", 3, 2)]);
+
+        let by_depth = distribution(&entities, "/repo", &["apps/web", "libs"], Some(2));
+        assert_eq!(
+            by_depth,
+            [
+                count("apps/web", 2, 1),
+                count("libs/shared", 1, 0),
+                count("libs/ui", 2, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transitive_external_packages_follow_imported_entities() {
+        let external = |name: &str, package: &str| {
+            ImportInfo::new(name.to_string(), format!("{}{}", EXTERNAL_PREFIX, package))
+        };
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+        insert(
+            &mut entities,
+            create_entity(
+                "Store",
+                EntityType::Class,
+                "/src/store.ts",
+                vec![external("map", "rxjs"), external("produce", "immer")],
+            ),
+        );
+        insert(
+            &mut entities,
+            create_entity(
+                "Page",
+                EntityType::Class,
+                "/src/page.ts",
+                vec![
+                    ImportInfo::new("Store".to_string(), "/src/store.ts".to_string()),
+                    external("Component", "@angular/core"),
+                ],
+            ),
+        );
+        insert(
+            &mut entities,
+            create_entity("plain", EntityType::Const, "/src/plain.ts", vec![]),
+        );
+        insert(
+            &mut entities,
+            create_entity("map", EntityType::Unknown, "external:rxjs", vec![]),
+        );
+
+        let found = transitive_external_packages(&entities);
+
+        let names: Vec<(&str, Vec<&str>)> = found
+            .iter()
+            .map(|(entity, packages)| (entity.name.as_str(), packages.iter().copied().collect()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("Page", vec!["@angular/core", "immer", "rxjs"]),
+                ("Store", vec!["immer", "rxjs"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_external_packages_groups_files_by_package() {
+        let external = |name: &str, package: &str| {
+            ImportInfo::new(name.to_string(), format!("{}{}", EXTERNAL_PREFIX, package))
+        };
+        let mut file_imports: HashMap<String, Vec<ImportInfo>> = HashMap::new();
+        file_imports.insert(
+            "/src/b.ts".to_string(),
+            vec![
+                external("Component", "@angular/core"),
+                external("Injectable", "@angular/core"),
+                ImportInfo::new("Local".to_string(), "/src/local.ts".to_string()),
+            ],
+        );
+        file_imports.insert(
+            "/src/a.ts".to_string(),
+            vec![
+                external("map", "rxjs"),
+                external("Component", "@angular/core"),
+            ],
+        );
+
+        let packages = external_packages(&file_imports);
+
+        let expected: BTreeMap<&str, Vec<&str>> = BTreeMap::from([
+            ("@angular/core", vec!["/src/a.ts", "/src/b.ts"]),
+            ("rxjs", vec!["/src/a.ts"]),
+        ]);
+        assert_eq!(packages, expected);
+    }
+
+    #[test]
+    fn test_stats_counts_per_type_and_unused() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+        let helper_import = ImportInfo::new("helper".to_string(), "/src/util.ts".to_string());
+
+        let mut service = create_entity(
+            "Service",
+            EntityType::Class,
+            "/src/service.ts",
+            vec![helper_import.clone()],
+        );
+        service.used = true;
+        insert(&mut entities, service);
+        insert(
+            &mut entities,
+            create_entity(
+                "Config",
+                EntityType::Interface,
+                "/src/service.ts",
+                vec![helper_import],
+            ),
+        );
+        let mut helper = create_entity("helper", EntityType::Function, "/src/util.ts", vec![]);
+        helper.used = true;
+        insert(&mut entities, helper);
+        insert(
+            &mut entities,
+            create_entity("other", EntityType::Function, "/src/util.ts", vec![]),
+        );
+        let mut placeholder = create_entity("External", EntityType::Unknown, "/lib/x.ts", vec![]);
+        placeholder.used = true;
+        insert(&mut entities, placeholder);
+
+        let mut file_imports: HashMap<String, Vec<ImportInfo>> = HashMap::new();
+        for file in ["/src/service.ts", "/src/util.ts", "/src/main.ts"] {
+            file_imports.insert(file.to_string(), vec![]);
+        }
+
+        let stats = stats(&entities, &file_imports, &[]);
+
+        assert_eq!(stats.total_entities, 4);
+        assert_eq!(stats.unused_entities, 2);
+        assert_eq!(stats.files, 3);
+        assert!((stats.average_dependencies - 0.5).abs() < f64::EPSILON);
+        let by_type: Vec<(&str, usize)> = stats
+            .by_type
+            .iter()
+            .map(|(t, n)| (t.as_str(), *n))
+            .collect();
+        assert_eq!(
+            by_type,
+            vec![("class", 1), ("function", 2), ("interface", 1)]
+        );
+    }
+
+    #[test]
+    fn test_find_deletable_files_lists_unused_unimported_file() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+
+        insert(
+            &mut entities,
+            create_entity("DeadA", EntityType::Class, "/src/dead.ts", vec![]),
+        );
+        insert(
+            &mut entities,
+            create_entity("DeadB", EntityType::Const, "/src/dead.ts", vec![]),
+        );
+
+        let deletable = find_deletable_files(&entities, &[]);
+
+        assert_eq!(deletable, vec!["/src/dead.ts".to_string()]);
+    }
+
+    #[test]
+    fn test_find_deletable_files_skips_file_with_external_consumer() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+
+        let mut helper = create_entity("Helper", EntityType::Function, "/src/helper.ts", vec![]);
+        helper.used = true;
+        insert(&mut entities, helper);
+        insert(
+            &mut entities,
+            create_entity("Unused", EntityType::Const, "/src/helper.ts", vec![]),
+        );
+
+        let import = ImportInfo::new("Helper".to_string(), "/src/helper.ts".to_string());
+        insert(
+            &mut entities,
+            create_entity(
+                "Consumer",
+                EntityType::Class,
+                "/src/consumer.ts",
+                vec![import],
+            ),
+        );
+
+        let deletable = find_deletable_files(&entities, &[]);
+
+        assert!(!deletable.contains(&"/src/helper.ts".to_string()));
+        assert_eq!(deletable, vec!["/src/consumer.ts".to_string()]);
+    }
+
+    #[test]
+    fn test_barrel_files_excludes_files_with_declarations() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+        // A placeholder for an import of the barrel does not make it a declaring file
+        insert(
+            &mut entities,
+            create_entity("Button", EntityType::Unknown, "/src/index.ts", vec![]),
+        );
+        insert(
+            &mut entities,
+            create_entity("Mixed", EntityType::Class, "/src/mixed.ts", vec![]),
+        );
+
+        let re_export = |name: &str| ReExport::new(name, name, "/src/button.ts".to_string());
+        let file_re_exports: HashMap<String, Vec<ReExport>> = HashMap::from([
+            ("/src/index.ts".to_string(), vec![re_export("Button")]),
+            ("/src/mixed.ts".to_string(), vec![re_export("*")]),
+        ]);
+
+        assert_eq!(
+            barrel_files(&entities, &file_re_exports),
+            vec!["/src/index.ts".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_re_export_usage_reaches_declaration_through_barrels() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+        let foo = create_entity("Foo", EntityType::Class, "/src/a.ts", vec![]);
+        let foo_id = foo.id.clone();
+        insert(&mut entities, foo);
+        let bar = create_entity("Bar", EntityType::Class, "/src/a.ts", vec![]);
+        let bar_id = bar.id.clone();
+        insert(&mut entities, bar);
+
+        // `/src/c.ts` imports `Foo` from the outer barrel and `Baz` (aliased `Bar`)
+        // from the inner one in a test
+        let mut foo_import = create_entity("Foo", EntityType::Unknown, "/src/index.ts", vec![]);
+        foo_import.used = true;
+        insert(&mut entities, foo_import);
+        let mut baz_import = create_entity("Baz", EntityType::Unknown, "/src/b.ts", vec![]);
+        baz_import.used_in_tests = true;
+        insert(&mut entities, baz_import);
+
+        let file_re_exports: HashMap<String, Vec<ReExport>> = HashMap::from([
+            (
+                "/src/index.ts".to_string(),
+                vec![ReExport::new("*", "*", "/src/b.ts".to_string())],
+            ),
+            (
+                "/src/b.ts".to_string(),
+                vec![
+                    ReExport::new("Foo", "Foo", "/src/a.ts".to_string()),
+                    ReExport::new("Baz", "Bar", "/src/a.ts".to_string()),
+                ],
+            ),
+        ]);
+
+        propagate_re_export_usage(&mut entities, &file_re_exports);
+
+        assert!(entities[&foo_id].used);
+        assert!(!entities[&bar_id].used);
+        assert!(entities[&bar_id].used_in_tests);
+    }
+
+    #[test]
+    fn test_coupling_matrix_counts_file_edges() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+
+        insert(
+            &mut entities,
+            create_entity("Helper", EntityType::Function, "/src/b.ts", vec![]),
+        );
+
+        let import = ImportInfo::new("Helper".to_string(), "/src/b.ts".to_string());
+        insert(
+            &mut entities,
+            create_entity(
+                "First",
+                EntityType::Class,
+                "/src/a.ts",
+                vec![import.clone()],
+            ),
+        );
+        insert(
+            &mut entities,
+            create_entity("Second", EntityType::Class, "/src/a.ts", vec![import]),
+        );
+
+        let (files, matrix) = coupling_matrix(&entities);
+
+        assert_eq!(
+            files,
+            vec!["/src/a.ts".to_string(), "/src/b.ts".to_string()]
+        );
+        assert_eq!(matrix[0][1], 1);
+        assert_eq!(matrix[1][0], 0);
+        assert_eq!(matrix[0][0], 0);
+        assert_eq!(matrix[1][1], 0);
+    }
+
+    #[test]
+    fn test_most_depended_upon_ranks_hub_first() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+
+        let hub = create_entity("Hub", EntityType::Class, "/src/hub.ts", vec![]);
+        let hub_id = hub.id.clone();
+        insert(&mut entities, hub);
+        insert(
+            &mut entities,
+            create_entity("Leaf", EntityType::Const, "/src/leaf.ts", vec![]),
+        );
+
+        let hub_import = ImportInfo::new("Hub".to_string(), "/src/hub.ts".to_string());
+        let leaf_import = ImportInfo::new("Leaf".to_string(), "/src/leaf.ts".to_string());
+        insert(
+            &mut entities,
+            create_entity(
+                "A",
+                EntityType::Class,
+                "/src/a.ts",
+                vec![hub_import.clone(), leaf_import],
+            ),
+        );
+        insert(
+            &mut entities,
+            create_entity(
+                "B",
+                EntityType::Class,
+                "/src/b.ts",
+                vec![hub_import.clone()],
+            ),
+        );
+        insert(
+            &mut entities,
+            create_entity("C", EntityType::Class, "/src/c.ts", vec![hub_import]),
+        );
+
+        let top = most_depended_upon(&entities, 2);
+
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].entity.id, hub_id);
+        assert_eq!(top[0].in_degree, 3);
+        assert_eq!(top[0].out_degree, 0);
+        assert_eq!(top[1].entity.name, "Leaf");
+        assert_eq!(top[1].in_degree, 1);
+    }
+
+    #[test]
+    fn test_entity_metrics_handles_isolated_entities() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+        insert(
+            &mut entities,
+            create_entity("Alone", EntityType::Enum, "/src/alone.ts", vec![]),
+        );
+
+        let metrics = entity_metrics(&entities);
+
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].in_degree, 0);
+        assert_eq!(metrics[0].out_degree, 0);
+        assert_eq!(most_depended_upon(&entities, 5).len(), 1);
+    }
+
+    #[test]
+    fn test_dead_code_cascades_through_private_dependencies() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+
+        // Top (unused) -> Middle -> Bottom, plus Shared used by both Top and Alive
+        let mut bottom = create_entity("Bottom", EntityType::Function, "/src/bottom.ts", vec![]);
+        bottom.used = true;
+        insert(&mut entities, bottom);
+
+        let mut middle = create_entity(
+            "Middle",
+            EntityType::Function,
+            "/src/middle.ts",
+            vec![ImportInfo::new(
+                "Bottom".to_string(),
+                "/src/bottom.ts".to_string(),
+            )],
+        );
+        middle.used = true;
+        insert(&mut entities, middle);
+
+        let mut shared = create_entity("Shared", EntityType::Const, "/src/shared.ts", vec![]);
+        shared.used = true;
+        insert(&mut entities, shared);
+
+        let shared_import = ImportInfo::new("Shared".to_string(), "/src/shared.ts".to_string());
+        insert(
+            &mut entities,
+            create_entity(
+                "Top",
+                EntityType::Class,
+                "/src/top.ts",
+                vec![
+                    ImportInfo::new("Middle".to_string(), "/src/middle.ts".to_string()),
+                    shared_import.clone(),
+                ],
+            ),
+        );
+
+        // Alive is used from outside the map (e.g. locally), so it keeps Shared alive
+        let mut alive = create_entity(
+            "Alive",
+            EntityType::Class,
+            "/src/alive.ts",
+            vec![shared_import],
+        );
+        alive.used = true;
+        insert(&mut entities, alive);
+
+        let dead: Vec<&str> = dead_code(&entities, None, &[])
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
+
+        assert_eq!(dead, vec!["Bottom", "Middle", "Top"]);
+
+        // From entry points that reach nothing, being marked used keeps nothing alive
+        let dead: Vec<&str> = dead_code(&entities, Some(&HashSet::new()), &[])
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
+
+        assert_eq!(dead, vec!["Alive", "Bottom", "Middle", "Shared", "Top"]);
+    }
+
+    #[test]
+    fn test_diff_snapshots_matches_entities_across_checkouts() {
+        let snapshot = |root: &str, files: &[(&str, &str)]| -> Vec<Entity> {
+            files
+                .iter()
+                .map(|(name, file)| {
+                    create_entity(
+                        name,
+                        EntityType::Class,
+                        &format!("{}{}", root, file),
+                        vec![],
+                    )
+                })
+                .collect()
+        };
+        let names = |entities: &[&Entity]| -> Vec<String> {
+            entities.iter().map(|entity| entity.name.clone()).collect()
+        };
+        let shared = ("Shared", "/libs/shared/shared.ts");
+        let main = ("Main", "/apps/web/main.ts");
+
+        let before = snapshot("/a/repo", &[shared, main]);
+        let after = snapshot("/b/repo", &[shared, main, ("Added", "/libs/added.ts")]);
+        let diff = diff_snapshots(&before, &after);
+        assert_eq!(names(&diff.added), ["Added"]);
+        assert!(diff.removed.is_empty());
+
+        // Files of the older snapshot share a deeper directory than the newer one's
+        let before = snapshot("/a/repo", &[shared]);
+        let after = snapshot("/a/repo", &[shared, main]);
+        let diff = diff_snapshots(&before, &after);
+        assert_eq!(names(&diff.added), ["Main"]);
+        assert!(diff.removed.is_empty());
+    }
+
+    #[test]
+    fn test_dead_code_keeps_entry_point_entities() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+        insert(
+            &mut entities,
+            create_entity(
+                "bootstrap",
+                EntityType::Function,
+                "/apps/web/src/main.ts",
+                vec![],
+            ),
+        );
+
+        insert(
+            &mut entities,
+            create_entity(
+                "routes",
+                EntityType::Const,
+                "/apps/web/src/app.routes.ts",
+                vec![],
+            ),
+        );
+
+        let dead: Vec<&str> = dead_code(&entities, None, &[])
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(dead, ["routes"]);
+        assert!(dead_code(&entities, None, &["*.routes.ts".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn test_matches_entry_point() {
+        assert!(matches_entry_point("/repo/apps/web/src/main.ts", "main.ts"));
+        assert!(matches_entry_point(
+            "/repo/apps/web/src/main.ts",
+            "src/main.ts"
+        ));
+        assert!(!matches_entry_point(
+            "/repo/apps/web/src/domain.ts",
+            "main.ts"
+        ));
+        assert!(matches_entry_point(
+            "/repo/apps/web/src/app-routing.module.ts",
+            "*-routing.module.ts"
+        ));
+        assert!(matches_entry_point(
+            "/repo/apps/web/src/main.ts",
+            "apps/*/src/main.ts"
+        ));
+        assert!(!matches_entry_point(
+            "/repo/libs/ui/src/main.ts",
+            "apps/*/src/main.ts"
+        ));
+    }
+
+    #[test]
+    fn test_reachable_from_entry_points() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+
+        // main.ts (no exports) -> AppModule -> Service; Orphan -> Helper from a non-entry file
+        let mut service = create_entity("Service", EntityType::Class, "/src/service.ts", vec![]);
+        service.used = true;
+        insert(&mut entities, service);
+
+        let mut app_module = create_entity(
+            "AppModule",
+            EntityType::Class,
+            "/src/app.module.ts",
+            vec![ImportInfo::new(
+                "Service".to_string(),
+                "/src/service.ts".to_string(),
+            )],
+        );
+        app_module.used = true;
+        insert(&mut entities, app_module);
+
+        let mut helper = create_entity("Helper", EntityType::Function, "/src/helper.ts", vec![]);
+        helper.used = true;
+        insert(&mut entities, helper);
+
+        insert(
+            &mut entities,
+            create_entity(
+                "Orphan",
+                EntityType::Class,
+                "/src/orphan.ts",
+                vec![ImportInfo::new(
+                    "Helper".to_string(),
+                    "/src/helper.ts".to_string(),
+                )],
+            ),
+        );
+
+        let mut file_imports: HashMap<String, Vec<ImportInfo>> = HashMap::new();
+        file_imports.insert(
+            "/src/main.ts".to_string(),
+            vec![ImportInfo::new(
+                "AppModule".to_string(),
+                "/src/app.module.ts".to_string(),
+            )],
+        );
+
+        let reached = reachable(
+            &entities,
+            &file_imports,
+            &HashMap::new(),
+            &["/src/main.ts".to_string()],
+            None,
+        );
+        let mut reached_names: Vec<&str> = reached
+            .iter()
+            .map(|id| entities[id].name.as_str())
+            .collect();
+        reached_names.sort();
+
+        // Helper is marked used, but only the non-entry Orphan imports it
+        assert_eq!(reached_names, vec!["AppModule", "Service"]);
+    }
+
+    #[test]
+    fn test_reachable_includes_locally_used_siblings() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+
+        insert(
+            &mut entities,
+            create_entity("Entry", EntityType::Function, "/src/entry.ts", vec![]),
+        );
+
+        let mut internal = create_entity("internal", EntityType::Function, "/src/entry.ts", vec![]);
+        internal.used = true;
+        internal.used_locally = true;
+        insert(&mut entities, internal);
+
+        let reached = reachable(
+            &entities,
+            &HashMap::new(),
+            &HashMap::new(),
+            &["/src/entry.ts".to_string()],
+            None,
+        );
+
+        assert_eq!(reached.len(), 2);
+    }
+
+    #[test]
+    fn test_reachable_stops_at_max_depth() {
+        let import = |name: &str| ImportInfo::new(name.to_string(), format!("/src/{}.ts", name));
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+        insert(
+            &mut entities,
+            create_entity("a", EntityType::Function, "/src/a.ts", vec![import("b")]),
+        );
+        insert(
+            &mut entities,
+            create_entity("b", EntityType::Function, "/src/b.ts", vec![import("c")]),
+        );
+        insert(
+            &mut entities,
+            create_entity("c", EntityType::Function, "/src/c.ts", vec![import("d")]),
+        );
+        insert(
+            &mut entities,
+            create_entity("d", EntityType::Function, "/src/d.ts", vec![]),
+        );
+
+        let mut file_imports: HashMap<String, Vec<ImportInfo>> = HashMap::new();
+        file_imports.insert("/src/main.ts".to_string(), vec![import("a")]);
+        let reached_names = |max_depth: Option<usize>| {
+            let reached = reachable(
+                &entities,
+                &file_imports,
+                &HashMap::new(),
+                &["/src/main.ts".to_string()],
+                max_depth,
+            );
+            let mut names: Vec<&str> = reached
+                .iter()
+                .map(|id| entities[id].name.as_str())
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(reached_names(Some(0)), ["a"]);
+        assert_eq!(reached_names(Some(2)), ["a", "b", "c"]);
+        assert_eq!(reached_names(None), ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_find_deletable_files_skips_entry_points() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+
+        insert(
+            &mut entities,
+            create_entity(
+                "bootstrap",
+                EntityType::Function,
+                "/apps/web/src/main.ts",
+                vec![],
+            ),
+        );
+        insert(
+            &mut entities,
+            create_entity(
+                "routes",
+                EntityType::Const,
+                "/apps/web/src/app.routes.ts",
+                vec![],
+            ),
+        );
+
+        assert_eq!(
+            find_deletable_files(&entities, &[]),
+            ["/apps/web/src/app.routes.ts"]
+        );
+        assert!(find_deletable_files(&entities, &["*.routes.ts".to_string()]).is_empty());
+    }
+}
@@ -1,10 +1,80 @@
+use std::path::PathBuf;
+
 use clap::{Args, Parser, Subcommand};
+use sting::{ColorChoice, EntityType, OutputFormat, PathAlias, SortKey};
 
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct StingArgs {
     #[command(subcommand)]
     pub command: Commands,
+    /// Import path alias mapping a prefix to a directory relative to the project root
+    /// (e.g. "@core/=libs/core/src"). Can be repeated; aliases are tried in order
+    #[arg(long = "alias", value_name = "PREFIX=PATH", global = true)]
+    pub aliases: Vec<PathAlias>,
+    /// Package scope belonging to the project (e.g. "@myorg"). Its imports are never
+    /// treated as external packages, unlike similarly named scopes. Can be repeated
+    #[arg(long = "internal-scope", value_name = "SCOPE", global = true)]
+    pub internal_scopes: Vec<String>,
+    /// Record imports of external packages (e.g. rxjs, @angular/core) as dependencies
+    #[arg(long, global = true)]
+    pub include_external: bool,
+    /// Also analyze top-level declarations that are not exported, so unused internal
+    /// helpers are reported
+    #[arg(long, global = true)]
+    pub include_internal: bool,
+    /// Analyze only these files (comma-separated or repeated, relative to the project
+    /// root) instead of scanning the project directories
+    #[arg(
+        long = "file",
+        value_name = "FILE",
+        value_delimiter = ',',
+        global = true
+    )]
+    pub files: Vec<String>,
+    /// Directory to scan instead of apps/web, apps/mobile and libs (relative to the
+    /// project root). Can be repeated; overrides `dirs` in .nxalyzer.toml
+    #[arg(long = "dir", value_name = "DIR", global = true)]
+    pub dirs: Vec<String>,
+    /// Leave out files and directories matching these glob patterns (comma-separated or
+    /// repeated). Patterns with a `/` match the path relative to the project root,
+    /// others the file or directory name
+    #[arg(
+        long = "exclude",
+        value_name = "GLOB",
+        value_delimiter = ',',
+        global = true
+    )]
+    pub excludes: Vec<String>,
+    /// Limit how many directory levels are scanned below apps/web, apps/mobile and libs
+    /// (1 only includes the files directly inside them). Unlimited by default
+    #[arg(long, value_name = "DEPTH", global = true)]
+    pub max_depth: Option<usize>,
+    /// Descend into symlinked directories (skipped by default); each directory is
+    /// scanned once, so symlink loops are safe
+    #[arg(long, global = true)]
+    pub follow_symlinks: bool,
+    /// Number of threads walking the scanned directories, which also bounds how many
+    /// directories are open at once. One per core (at most eight) by default; scans
+    /// following symlinks and the commands reading one file at a time (`unused-imports`,
+    /// `validate`) always walk on a single thread
+    #[arg(long, value_name = "N", global = true)]
+    pub walk_threads: Option<usize>,
+    /// Also scan test helpers and doubles (mocks directories, mock.ts and -stub.ts files,
+    /// ...), which are skipped by default
+    #[arg(long, global = true)]
+    pub include_tests: bool,
+    /// Count Angular components used by their selector in another component's
+    /// template (templateUrl or inline template) as used
+    #[arg(long, global = true)]
+    pub scan_templates: bool,
+    /// Only print results and warnings, without progress messages
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+    /// Colorize the text output: auto (when stdout is a terminal and NO_COLOR is
+    /// unset), always or never
+    #[arg(long, value_enum, default_value = "auto", global = true)]
+    pub color: ColorChoice,
 }
 
 #[derive(Subcommand, Debug)]
@@ -15,45 +85,382 @@ pub enum Commands {
     Query(QueryArgs),
     /// Lists all unused entities in the nx project
     Unused(UnusedArgs),
+    /// Lists imports that are never referenced in the importing file
+    UnusedImports(UnusedImportsArgs),
+    /// Lists unused entities plus everything that only they depend on
+    DeadCode(DeadCodeArgs),
+    /// Lists files whose entities are all unused and that nobody imports
+    Deletable(DeletableArgs),
+    /// Lists barrel files that only re-export other modules
+    Barrels(BarrelsArgs),
+    /// Lists files that declare no entities and that nothing imports
+    Orphans(OrphansArgs),
+    /// Lists the files the scan finds, without parsing them, to check the scan configuration
+    ListFiles(ListFilesArgs),
+    /// Prints dependency counts (in/out degree) for each entity
+    Metrics(MetricsArgs),
+    /// Reports entities with too many dependencies or dependents; exits with 1 if any
+    Lint(LintArgs),
+    /// Prints summary counts: entities per type, unused, files and average dependencies
+    Stats(StatsArgs),
+    /// Prints the number of entities and unused entities per directory
+    Distribution(DistributionArgs),
+    /// Lists imports that resolve to the importing file itself
+    SelfImports(SelfImportsArgs),
+    /// Lists imports whose resolved file does not exist; exits with 1 if any
+    Validate(ValidateArgs),
+    /// Lists imports of files outside the scanned directories and the directories to add
+    OutOfScope(OutOfScopeArgs),
+    /// Lists the external packages imported by the project and the files using them
+    Externals(ExternalsArgs),
+    /// Lists the external packages each entity depends on, directly or transitively
+    EntityExternals(EntityExternalsArgs),
+    /// Reports imports from a lower layer into a higher one (e.g. libs into apps);
+    /// exits with 1 if any
+    ArchCheck(ArchCheckArgs),
     /// Outputs the dependency graph as JSON (D3.js compatible)
     Graph(GraphArgs),
+    /// Outputs the dependency graph as a Mermaid diagram
+    Mermaid(MermaidArgs),
+    /// Prints an entity's transitive dependencies as an indented tree
+    Tree(TreeArgs),
+    /// Explains why an entity is marked used (the file importing it, the barrel re-exporting it, ...)
+    WhyUsed(WhyUsedArgs),
+    /// Outputs the file-to-file coupling matrix as CSV
+    Coupling(CouplingArgs),
     /// Lists all entities affected by git changes compared to a base reference
     Affected(AffectedArgs),
+    /// Compares two `query-all --format json` snapshots: entities added, removed,
+    /// newly unused and newly used
+    Diff(DiffArgs),
 }
 
 #[derive(Args, Debug)]
 pub struct QueryArgs {
     /// Path to the root of the nx project
     pub path: String,
-    /// Query string to filter entities by
-    pub query: String,
+    /// Id of the entity to show
+    #[arg(required_unless_present = "name")]
+    pub query: Option<String>,
+    /// Show the entities with this name instead, in every file that declares one
+    #[arg(long, conflicts_with = "query")]
+    pub name: Option<String>,
 }
 
 #[derive(Args, Debug)]
 pub struct QueryAllArgs {
-    /// Path to the root of the nx project
-    pub path: String,
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+    /// Output format
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: OutputFormat,
+    /// Only show entities of these types (comma-separated: class,enum,type,interface,function,const,namespace)
+    #[arg(long = "type", value_delimiter = ',')]
+    pub types: Vec<EntityType>,
+    /// Leave out placeholders for imports that do not resolve to a scanned declaration
+    #[arg(long)]
+    pub exclude_placeholders: bool,
+    /// Order of the listed entities (text and JSON output)
+    #[arg(long, value_enum, default_value = "id")]
+    pub sort_by: SortKey,
+    /// Reverse the order given by --sort-by
+    #[arg(long)]
+    pub reverse: bool,
+    /// Only list entities declared in files changed since --since. The whole project
+    /// is still scanned, so their dependencies resolve
+    #[arg(long)]
+    pub changed_only: bool,
+    /// Git reference the changes of --changed-only are taken from (merge-base with HEAD)
+    #[arg(long, value_name = "REF", default_value = "main")]
+    pub since: String,
+    /// Only list entities of this Nx project (from `project.json` or `nx.json`)
+    #[arg(long, value_name = "NAME")]
+    pub project: Option<String>,
+    /// Write the output to this file instead of stdout, creating its parent directories
+    #[arg(long, short = 'o', value_name = "PATH")]
+    pub output: Option<PathBuf>,
 }
 
 #[derive(Args, Debug)]
 pub struct UnusedArgs {
-    /// Path to the root of the nx project
-    pub path: String,
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+    /// Entry point file name, path suffix or glob (e.g. main.ts, "*-routing.module.ts").
+    /// When given, entities not reachable from an entry point are reported as unused
+    #[arg(long = "entry-point")]
+    pub entry_points: Vec<String>,
+    /// Only show entities of these types (comma-separated: class,enum,type,interface,function,const,namespace)
+    #[arg(long = "type", value_delimiter = ',')]
+    pub types: Vec<EntityType>,
+    /// Output format
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: OutputFormat,
+    /// Leave out entities that are only used by test files instead of labeling them
+    #[arg(long)]
+    pub exclude_test_only: bool,
+    /// Order of the listed entities (text and JSON output)
+    #[arg(long, value_enum, default_value = "file")]
+    pub sort_by: SortKey,
+    /// Reverse the order given by --sort-by
+    #[arg(long)]
+    pub reverse: bool,
+    /// List the entities one by one instead of grouping them by file (text output)
+    #[arg(long)]
+    pub flat: bool,
+    /// Exit with status 1 when any unused entity is found, e.g. to gate CI
+    #[arg(long)]
+    pub fail_on_unused: bool,
+    /// Exit with status 1 when more than N unused entities are found
+    #[arg(long, value_name = "N")]
+    pub max_unused: Option<usize>,
+    /// Only follow imports N levels deep from the entry points; entities beyond are
+    /// reported as unused. Needs entry points (--entry-point or the config file)
+    #[arg(long, value_name = "N")]
+    pub max_propagation_depth: Option<usize>,
+    /// Only report entities of this Nx project (from `project.json` or `nx.json`)
+    #[arg(long, value_name = "NAME")]
+    pub project: Option<String>,
+    /// Write the output to this file instead of stdout, creating its parent directories
+    #[arg(long, short = 'o', value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct UnusedImportsArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct DeadCodeArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+    /// Entry point file name, path suffix or glob (e.g. main.ts, "*-routing.module.ts").
+    /// When given, entities not reachable from an entry point are dead along with
+    /// what only they use
+    #[arg(long = "entry-point")]
+    pub entry_points: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct DeletableArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+    /// Do not report test files (.spec.ts, .test.ts)
+    #[arg(long, default_value = "false")]
+    pub exclude_tests: bool,
+    /// Entry point file name, path suffix or glob whose files are never deletable,
+    /// in addition to main.ts, index.ts, polyfills.ts and test-setup.ts
+    #[arg(long = "entry-point")]
+    pub entry_points: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct BarrelsArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct OrphansArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ListFilesArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct MetricsArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+    /// Only show the N most depended-upon entities
+    #[arg(long)]
+    pub top: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+pub struct LintArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+    /// Report entities with more dependencies than this
+    #[arg(
+        long = "max-deps",
+        value_name = "N",
+        required_unless_present = "max_dependents"
+    )]
+    pub max_dependencies: Option<usize>,
+    /// Report entities with more dependents (fan-in) than this
+    #[arg(long, value_name = "N")]
+    pub max_dependents: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+pub struct SelfImportsArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ValidateArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct OutOfScopeArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ExternalsArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct EntityExternalsArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+    /// Output format (text, or json mapping entity ids to package names)
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: OutputFormat,
+}
+
+#[derive(Args, Debug)]
+pub struct DistributionArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+    /// Group by the first N directories of each file path instead of by scanned directory
+    #[arg(long, value_name = "N")]
+    pub depth: Option<usize>,
+    /// Output format (text or json)
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: OutputFormat,
+}
+
+#[derive(Args, Debug)]
+pub struct ArchCheckArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+    /// Layer directories relative to the root, from the top layer to the bottom one
+    /// (comma-separated). Files may only import from their layer and the ones below
+    #[arg(long, value_delimiter = ',', default_values = ["apps", "libs"])]
+    pub layers: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct StatsArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+    /// Output format (text or json)
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: OutputFormat,
 }
 
 #[derive(Args, Debug)]
 pub struct GraphArgs {
-    /// Path to the root of the nx project
-    pub path: String,
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+    /// Output `nodes` and `edges` (`from_id`/`to_id`) instead of d3-style `links`
+    #[arg(long)]
+    pub adjacency: bool,
+    /// Only include this entity id and the entities within --depth hops of it
+    #[arg(long)]
+    pub entity: Option<String>,
+    /// Number of hops around --entity to include, following dependencies and dependents
+    #[arg(long, default_value = "1", requires = "entity")]
+    pub depth: usize,
+    /// Write the output to this file instead of stdout, creating its parent directories
+    #[arg(long, short = 'o', value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct MermaidArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+    /// Only include this entity id and the entities within --depth hops of it
+    #[arg(long)]
+    pub entity: Option<String>,
+    /// Number of hops around --entity to include, following dependencies and dependents
+    #[arg(long, default_value = "1", requires = "entity")]
+    pub depth: usize,
+    /// Write the output to this file instead of stdout, creating its parent directories
+    #[arg(long, short = 'o', value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct TreeArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+    /// Name of the entity; every entity with that name gets a tree
+    #[arg(long, required_unless_present = "id", conflicts_with = "id")]
+    pub name: Option<String>,
+    /// Id of the entity
+    #[arg(long)]
+    pub id: Option<String>,
+    /// Write the output to this file instead of stdout, creating its parent directories
+    #[arg(long, short = 'o', value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct WhyUsedArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+    /// Name of the entity; every entity with that name is explained
+    #[arg(long, required_unless_present = "id", conflicts_with = "id")]
+    pub name: Option<String>,
+    /// Id of the entity
+    #[arg(long)]
+    pub id: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct CouplingArgs {
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
+    /// Write the output to this file instead of stdout, creating its parent directories
+    #[arg(long, short = 'o', value_name = "PATH")]
+    pub output: Option<PathBuf>,
 }
 
 #[derive(Args, Debug)]
 pub struct AffectedArgs {
-    /// Path to the root of the nx project
-    pub path: String,
+    /// Path to the root of the nx project (detected from the current directory when omitted)
+    pub path: Option<String>,
     /// Git reference to compare against (branch, tag, or commit SHA)
-    #[arg(long)]
-    pub base: String,
+    #[arg(long, required_unless_present = "since_tag")]
+    pub base: Option<String>,
+    /// Compare against the latest tag matching this pattern on the history of HEAD,
+    /// by version or else by date, instead of --base
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        num_args = 0..=1,
+        default_missing_value = "v*",
+        conflicts_with = "base"
+    )]
+    pub since_tag: Option<String>,
+    /// Git reference to compare with the base (defaults to HEAD)
+    #[arg(long, conflicts_with = "working_tree")]
+    pub head: Option<String>,
+    /// Diff the base tree directly instead of starting from the merge-base
+    #[arg(long, default_value = "false")]
+    pub no_merge_base: bool,
+    /// When the base and head share no history (e.g. in a shallow clone), diff the
+    /// base tree directly with a warning instead of failing
+    #[arg(long, default_value = "false")]
+    pub merge_base_fallback: bool,
+    /// Include uncommitted changes (staged, unstaged and untracked files)
+    #[arg(long, default_value = "false")]
+    pub working_tree: bool,
     /// Include transitive consumers (multi-hop dependency traversal)
     #[arg(long, default_value = "false")]
     pub transitive: bool,
@@ -64,3 +471,17 @@ pub struct AffectedArgs {
     #[arg(long, default_value = "false", conflicts_with = "paths")]
     pub tests: bool,
 }
+
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    /// Snapshot of the older run
+    pub before: PathBuf,
+    /// Snapshot of the newer run
+    pub after: PathBuf,
+    /// Output format (text or json)
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: OutputFormat,
+    /// Write the output to this file instead of stdout, creating its parent directories
+    #[arg(long, short = 'o', value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
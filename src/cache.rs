@@ -0,0 +1,377 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::entity::{Entity, ImportInfo, ReExport, Usage};
+use crate::filesystem::FileSystem;
+use crate::parser::{self, FileParseResult, Parser};
+
+/// Name of the cache file written to the project root.
+pub(crate) const CACHE_FILE_NAME: &str = ".sting-cache.json";
+
+/// Bumped whenever the cached data layout or the parser output changes,
+/// so that caches written by older versions are discarded.
+const CACHE_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "+29");
+
+/// Modification time and size of a file, used to detect changes without reading it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FileStamp {
+    modified_nanos: u128,
+    size: u64,
+}
+
+impl FileStamp {
+    pub fn of(file_system: &dyn FileSystem, path: &Path) -> Option<Self> {
+        let metadata = file_system.metadata(path).ok()?;
+        let modified = metadata.modified.duration_since(UNIX_EPOCH).ok()?;
+        Some(FileStamp {
+            modified_nanos: modified.as_nanos(),
+            size: metadata.len,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedFile {
+    stamp: FileStamp,
+    entities: Vec<Entity>,
+    imports: Vec<ImportInfo>,
+    re_exports: Vec<ReExport>,
+    /// Every import source of the file with what it resolved to when cached
+    resolutions: Vec<(String, Option<String>)>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheData {
+    version: String,
+    /// Parser settings the results were produced with, see `Parser::settings_fingerprint`
+    #[serde(default)]
+    settings: String,
+    files: HashMap<String, CachedFile>,
+}
+
+/// Persistent cache of parse results keyed by file path, invalidated when a
+/// file's modification time or size changes or when one of its import sources
+/// resolves differently, e.g. because the file it names was added or removed.
+pub(crate) struct ParseCache<'a> {
+    file_system: &'a dyn FileSystem,
+    path: PathBuf,
+    data: CacheData,
+    dirty: bool,
+}
+
+impl<'a> ParseCache<'a> {
+    /// Loads the cache from the project root. A missing, unreadable or outdated
+    /// cache file, or one written with different parser `settings`, results in an empty cache.
+    pub fn load(file_system: &'a dyn FileSystem, root_path: &Path, settings: &str) -> Self {
+        let path = root_path.join(CACHE_FILE_NAME);
+
+        let data = file_system
+            .read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<CacheData>(&content).ok())
+            .filter(|data| data.version == CACHE_VERSION && data.settings == settings)
+            .unwrap_or_else(|| CacheData {
+                version: CACHE_VERSION.to_string(),
+                settings: settings.to_string(),
+                files: HashMap::new(),
+            });
+
+        ParseCache {
+            file_system,
+            path,
+            data,
+            dirty: false,
+        }
+    }
+
+    /// Returns the cached result for the file if it has not changed since it was cached.
+    pub fn get(&self, file_path: &str, stamp: FileStamp) -> Option<FileParseResult> {
+        let cached = self.data.files.get(file_path)?;
+        if cached.stamp != stamp {
+            return None;
+        }
+
+        let deps = Rc::new(cached.imports.clone());
+        let entities = cached
+            .entities
+            .iter()
+            .map(|entity| {
+                let mut restored = Entity {
+                    deps: Rc::clone(&deps),
+                    bases: Vec::new(),
+                    // Local usage is the only reason the parser records
+                    used_by: entity.used_locally.then_some(Usage::Local),
+                    ..entity.clone()
+                };
+                restored.set_bases(entity.bases.clone());
+                restored
+            })
+            .collect();
+
+        Some(FileParseResult {
+            entities,
+            imports: cached.imports.clone(),
+            re_exports: cached.re_exports.clone(),
+        })
+    }
+
+    pub fn insert(
+        &mut self,
+        file_path: &str,
+        stamp: FileStamp,
+        result: &FileParseResult,
+        resolutions: Vec<(String, Option<String>)>,
+    ) {
+        self.data.files.insert(
+            file_path.to_string(),
+            CachedFile {
+                stamp,
+                entities: result.entities.clone(),
+                imports: result.imports.clone(),
+                re_exports: result.re_exports.clone(),
+                resolutions,
+            },
+        );
+        self.dirty = true;
+    }
+
+    /// Whether the import sources of the cached file still resolve as they did.
+    fn resolves_as_cached(&self, parser: &Parser, file_path: &str) -> bool {
+        self.data.files.get(file_path).is_some_and(|cached| {
+            cached.resolutions.iter().all(|(source, resolved)| {
+                parser.resolve_import_path(file_path, source) == *resolved
+            })
+        })
+    }
+
+    /// Parses the file, reusing the cached result when the file is unchanged and its
+    /// imports resolve as before.
+    pub fn parse(&mut self, parser: &Parser, file_path: &str) -> Result<FileParseResult> {
+        let stamp = FileStamp::of(self.file_system, Path::new(file_path));
+
+        if let Some(stamp) = stamp
+            && self.resolves_as_cached(parser, file_path)
+            && let Some(result) = self.get(file_path, stamp)
+        {
+            return Ok(result);
+        }
+
+        let content = parser::read_source(self.file_system, file_path)?;
+        let result = parser.parse_content(&content, file_path);
+        if let Some(stamp) = stamp {
+            let resolutions = parser.import_resolutions(&content, file_path);
+            self.insert(file_path, stamp, &result, resolutions);
+        }
+        Ok(result)
+    }
+
+    /// Drops entries for files that are no longer part of the scan.
+    pub fn retain_files(&mut self, files: &[String]) {
+        let keep: HashSet<&str> = files.iter().map(|file| file.as_str()).collect();
+        let before = self.data.files.len();
+        self.data
+            .files
+            .retain(|path, _| keep.contains(path.as_str()));
+        if self.data.files.len() != before {
+            self.dirty = true;
+        }
+    }
+
+    /// Writes the cache back to disk if anything changed.
+    pub fn save(&self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let json = serde_json::to_string(&self.data)?;
+        self.file_system
+            .write(&self.path, json.as_bytes())
+            .with_context(|| format!("Failed to write cache file {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::RealFileSystem;
+    use crate::parser::UNRESOLVED_PREFIX;
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+    use tempfile::tempdir;
+
+    fn set_modified(path: &Path, time: SystemTime) {
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(time).unwrap();
+    }
+
+    fn entity_names(result: &FileParseResult) -> Vec<String> {
+        result.entities.iter().map(|e| e.name.clone()).collect()
+    }
+
+    #[test]
+    fn test_cache_hit_skips_reparsing() {
+        let temp = tempdir().unwrap();
+        let file = temp.path().join("service.ts");
+        let file_str = file.to_string_lossy().to_string();
+        let original_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        fs::write(&file, "export class Foo {}").unwrap();
+        set_modified(&file, original_time);
+
+        let parser = Parser::new(temp.path());
+        let mut cache = ParseCache::load(&RealFileSystem, temp.path(), "");
+        assert_eq!(
+            entity_names(&cache.parse(&parser, &file_str).unwrap()),
+            vec!["Foo"]
+        );
+        cache.save().unwrap();
+
+        // Same size and modification time: the stale cached entry must be returned
+        fs::write(&file, "export class Bar {}").unwrap();
+        set_modified(&file, original_time);
+
+        let mut cache = ParseCache::load(&RealFileSystem, temp.path(), "");
+        assert_eq!(
+            entity_names(&cache.parse(&parser, &file_str).unwrap()),
+            vec!["Foo"]
+        );
+    }
+
+    #[test]
+    fn test_modified_file_invalidates_cache() {
+        let temp = tempdir().unwrap();
+        let file = temp.path().join("service.ts");
+        let file_str = file.to_string_lossy().to_string();
+
+        fs::write(&file, "export class Foo {}").unwrap();
+        set_modified(
+            &file,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        );
+
+        let parser = Parser::new(temp.path());
+        let mut cache = ParseCache::load(&RealFileSystem, temp.path(), "");
+        cache.parse(&parser, &file_str).unwrap();
+        cache.save().unwrap();
+
+        fs::write(&file, "export class Bar {}").unwrap();
+        set_modified(
+            &file,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_100),
+        );
+
+        let mut cache = ParseCache::load(&RealFileSystem, temp.path(), "");
+        assert_eq!(
+            entity_names(&cache.parse(&parser, &file_str).unwrap()),
+            vec!["Bar"]
+        );
+    }
+
+    #[test]
+    fn test_added_import_target_invalidates_importing_file() {
+        let temp = tempdir().unwrap();
+        let bar = temp.path().join("bar.ts");
+        let bar_str = bar.to_string_lossy().to_string();
+        fs::write(&bar, "import { Foo } from './foo';\nexport class Bar {}").unwrap();
+
+        let parser = Parser::new(temp.path());
+        let mut cache = ParseCache::load(&RealFileSystem, temp.path(), "");
+        let before = cache.parse(&parser, &bar_str).unwrap();
+        assert!(before.imports[0].path.starts_with(UNRESOLVED_PREFIX));
+        cache.save().unwrap();
+
+        // bar.ts is untouched, but its import now resolves to the new file
+        fs::write(temp.path().join("foo.ts"), "export class Foo {}").unwrap();
+
+        let mut cache = ParseCache::load(&RealFileSystem, temp.path(), "");
+        let after = cache.parse(&parser, &bar_str).unwrap();
+        assert!(after.imports[0].path.ends_with("/foo.ts"));
+    }
+
+    #[test]
+    fn test_cached_entities_share_deps() {
+        let temp = tempdir().unwrap();
+        let file = temp.path().join("service.ts");
+        let file_str = file.to_string_lossy().to_string();
+
+        fs::write(
+            &file,
+            "import { Helper } from './helper';\nexport class Foo {}\nexport class Bar {}",
+        )
+        .unwrap();
+
+        let parser = Parser::new(temp.path());
+        let mut cache = ParseCache::load(&RealFileSystem, temp.path(), "");
+        cache.parse(&parser, &file_str).unwrap();
+        cache.save().unwrap();
+
+        let cache = ParseCache::load(&RealFileSystem, temp.path(), "");
+        let result = cache
+            .get(&file_str, FileStamp::of(&RealFileSystem, &file).unwrap())
+            .unwrap();
+
+        assert_eq!(result.imports.len(), 1);
+        assert_eq!(result.entities.len(), 2);
+        for entity in &result.entities {
+            assert_eq!(entity.deps.len(), 1);
+            assert_eq!(entity.deps[0].name, "Helper");
+        }
+    }
+
+    #[test]
+    fn test_retain_files_drops_deleted_entries() {
+        let temp = tempdir().unwrap();
+        let file = temp.path().join("gone.ts");
+        let file_str = file.to_string_lossy().to_string();
+        fs::write(&file, "export const A = 1;").unwrap();
+
+        let parser = Parser::new(temp.path());
+        let mut cache = ParseCache::load(&RealFileSystem, temp.path(), "");
+        cache.parse(&parser, &file_str).unwrap();
+        cache.save().unwrap();
+
+        let mut cache = ParseCache::load(&RealFileSystem, temp.path(), "");
+        cache.retain_files(&[]);
+        cache.save().unwrap();
+
+        let cache = ParseCache::load(&RealFileSystem, temp.path(), "");
+        assert!(
+            cache
+                .get(&file_str, FileStamp::of(&RealFileSystem, &file).unwrap())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_cache_with_different_settings_is_ignored() {
+        let temp = tempdir().unwrap();
+        let file = temp.path().join("service.ts");
+        fs::write(&file, "export class Service {}").unwrap();
+        let file_str = file.to_string_lossy().to_string();
+
+        let parser = Parser::new(temp.path());
+        let mut cache = ParseCache::load(&RealFileSystem, temp.path(), "@core/=libs/core");
+        cache.parse(&parser, &file_str).unwrap();
+        cache.save().unwrap();
+
+        let stamp = FileStamp::of(&RealFileSystem, &file).unwrap();
+        let same = ParseCache::load(&RealFileSystem, temp.path(), "@core/=libs/core");
+        assert!(same.get(&file_str, stamp).is_some());
+        let changed = ParseCache::load(&RealFileSystem, temp.path(), "@core/=libs/core-v2");
+        assert!(changed.get(&file_str, stamp).is_none());
+    }
+
+    #[test]
+    fn test_corrupt_cache_is_ignored() {
+        let temp = tempdir().unwrap();
+        fs::write(temp.path().join(CACHE_FILE_NAME), "not json").unwrap();
+
+        let cache = ParseCache::load(&RealFileSystem, temp.path(), "");
+        assert!(cache.data.files.is_empty());
+    }
+}
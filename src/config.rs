@@ -0,0 +1,214 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::filesystem::{FileSystem, RealFileSystem};
+use crate::parser::{EntityTypeRule, PathAlias};
+
+/// Name of the per-repository configuration file, read from the project root.
+pub const CONFIG_FILE: &str = ".nxalyzer.toml";
+
+/// Files marking the root of a monorepo, strongest first.
+const ROOT_MARKERS: [&str; 3] = ["nx.json", "tsconfig.base.json", "package.json"];
+
+/// Finds the monorepo root by walking up from `start`, like `git` finds its
+/// repository. The nearest directory with `nx.json` wins, then the nearest with
+/// `tsconfig.base.json`, then the nearest with `package.json` (libraries often
+/// carry their own `package.json`, so it is only a last resort). Falls back to
+/// `start` when none of the ancestors has a marker.
+pub fn find_project_root(start: &Path) -> PathBuf {
+    ROOT_MARKERS
+        .iter()
+        .find_map(|marker| start.ancestors().find(|dir| dir.join(marker).is_file()))
+        .unwrap_or(start)
+        .to_path_buf()
+}
+
+/// Settings read from `.nxalyzer.toml`. Every field is optional; an empty list
+/// leaves the built-in behavior (or the matching CLI option) in place.
+///
+/// Every key is an array of strings. Aliases are written as `PREFIX=PATH`, like
+/// the `--alias` option, and entity type rules as `LABEL=REGEX`:
+///
+/// ```toml
+/// dirs = ["apps", "libs"]
+/// aliases = ["@core/=libs/core/src"]
+/// skip_dirs = ["generated"]
+/// skip_file_suffixes = [".gen.ts"]
+/// entry_points = ["main.ts"]
+/// extensions = ["ts", "tsx"]
+/// entity_types = ["store=createStore\\("]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawConfig")]
+pub struct Config {
+    /// Directories to scan, relative to the project root
+    pub dirs: Vec<String>,
+    pub aliases: Vec<PathAlias>,
+    /// Directory names skipped in addition to the built-in ones
+    pub skip_dirs: Vec<String>,
+    /// File name suffixes skipped in addition to the built-in ones
+    pub skip_file_suffixes: Vec<String>,
+    /// Entry points for the `unused` command
+    pub entry_points: Vec<String>,
+    /// File extensions (without the dot) that are scanned and tried when resolving imports
+    pub extensions: Vec<String>,
+    /// Rules classifying matching declaration lines before the built-in types
+    pub entity_types: Vec<EntityTypeRule>,
+}
+
+impl Config {
+    /// Reads `.nxalyzer.toml` from `root_path`, returning the default config when
+    /// there is no such file.
+    pub fn load(root_path: &Path) -> Result<Config> {
+        Config::load_from(&RealFileSystem, root_path)
+    }
+
+    /// Like `load`, reading the file from `file_system`.
+    pub(crate) fn load_from(file_system: &dyn FileSystem, root_path: &Path) -> Result<Config> {
+        let path = root_path.join(CONFIG_FILE);
+        match file_system.read_to_string(&path) {
+            Ok(content) => {
+                Config::parse(&content).with_context(|| format!("Invalid {}", path.display()))
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e).with_context(|| format!("Unable to read {}", path.display())),
+        }
+    }
+
+    /// Parses the content of a `.nxalyzer.toml` file.
+    pub fn parse(content: &str) -> Result<Config> {
+        Ok(toml::from_str(content)?)
+    }
+}
+
+/// `.nxalyzer.toml` as written, with aliases and entity type rules still strings.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawConfig {
+    dirs: Vec<String>,
+    aliases: Vec<String>,
+    skip_dirs: Vec<String>,
+    skip_file_suffixes: Vec<String>,
+    entry_points: Vec<String>,
+    extensions: Vec<String>,
+    entity_types: Vec<String>,
+}
+
+impl TryFrom<RawConfig> for Config {
+    type Error = String;
+
+    fn try_from(raw: RawConfig) -> Result<Config, String> {
+        Ok(Config {
+            dirs: raw.dirs,
+            aliases: raw
+                .aliases
+                .iter()
+                .map(|alias| alias.parse())
+                .collect::<Result<_, _>>()?,
+            skip_dirs: raw.skip_dirs,
+            skip_file_suffixes: raw.skip_file_suffixes,
+            entry_points: raw.entry_points,
+            extensions: raw
+                .extensions
+                .into_iter()
+                .map(|extension| extension.trim_start_matches('.').to_string())
+                .collect(),
+            entity_types: raw
+                .entity_types
+                .iter()
+                .map(|rule| rule.parse())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_parse_reads_all_fields() {
+        let config = Config::parse(
+            r#"
+# Scanned folders
+dirs = ["apps", "libs"] # trailing comment
+aliases = ["@core/=libs/core/src"]
+skip_dirs = [
+    "generated",
+    "fixtures#1",
+]
+skip_file_suffixes = [".gen.ts"]
+entry_points = ["main.ts"]
+extensions = [".ts", "tsx"]
+entity_types = ["store=createStore\\("]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.dirs, ["apps", "libs"]);
+        assert_eq!(config.aliases, ["@core/=libs/core/src".parse().unwrap()]);
+        assert_eq!(config.skip_dirs, ["generated", "fixtures#1"]);
+        assert_eq!(config.skip_file_suffixes, [".gen.ts"]);
+        assert_eq!(config.entry_points, ["main.ts"]);
+        assert_eq!(config.extensions, ["ts", "tsx"]);
+        assert_eq!(
+            config.entity_types,
+            ["store=createStore\\(".parse().unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_parse_accepts_any_toml_string() {
+        let config = Config::parse(
+            r#"
+entity_types = ['store=createStore\(']
+dirs = ["apps", """libs""", "tools\u002fscripts"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.entity_types,
+            ["store=createStore\\(".parse().unwrap()]
+        );
+        assert_eq!(config.dirs, ["apps", "libs", "tools/scripts"]);
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_keys_and_bad_values() {
+        assert!(Config::parse("dir = [\"apps\"]").is_err());
+        assert!(Config::parse("dirs = \"apps\"").is_err());
+        assert!(Config::parse("dirs = [\"apps\"").is_err());
+        assert!(Config::parse("aliases = [\"@core/\"]").is_err());
+        assert!(Config::parse("entity_types = [\"store=(\"]").is_err());
+    }
+
+    #[test]
+    fn test_load_without_file_is_default() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert_eq!(Config::load(temp_dir.path()).unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_find_project_root_walks_up_to_the_strongest_marker() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let nested = root.join("libs/shared/src/lib");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join("nx.json"), "{}").unwrap();
+        fs::write(root.join("package.json"), "{}").unwrap();
+        fs::write(root.join("libs/shared/package.json"), "{}").unwrap();
+
+        assert_eq!(find_project_root(&nested), root);
+
+        fs::remove_file(root.join("nx.json")).unwrap();
+        assert_eq!(find_project_root(&nested), root.join("libs/shared"));
+
+        let empty = tempfile::tempdir().unwrap();
+        assert_eq!(find_project_root(empty.path()), empty.path());
+    }
+}
@@ -1,11 +1,10 @@
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+use std::path::Path;
 use std::rc::Rc;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize)]
-pub(crate) enum EntityType {
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EntityType {
     Unknown,
     Class,
     Enum,
@@ -13,6 +12,10 @@ pub(crate) enum EntityType {
     Interface,
     Function,
     Const,
+    /// `namespace Foo {}` or the older `module Foo {}`
+    Namespace,
+    /// A type assigned by an `entity_types` rule of the config file, e.g. `store`
+    Custom(String),
 }
 
 impl std::fmt::Display for EntityType {
@@ -25,33 +28,161 @@ impl std::fmt::Display for EntityType {
             EntityType::Interface => write!(f, "interface"),
             EntityType::Function => write!(f, "function"),
             EntityType::Const => write!(f, "const"),
+            EntityType::Namespace => write!(f, "namespace"),
+            EntityType::Custom(label) => write!(f, "{}", label),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub(crate) struct ImportInfo {
+impl std::str::FromStr for EntityType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "unknown" => Ok(EntityType::Unknown),
+            "class" => Ok(EntityType::Class),
+            "enum" => Ok(EntityType::Enum),
+            "type" => Ok(EntityType::Type),
+            "interface" => Ok(EntityType::Interface),
+            "function" => Ok(EntityType::Function),
+            "const" => Ok(EntityType::Const),
+            "namespace" => Ok(EntityType::Namespace),
+            other => Err(format!(
+                "invalid entity type '{}' (expected class, enum, type, interface, function, const, namespace or unknown)",
+                other
+            )),
+        }
+    }
+}
+
+/// A name imported from the file at `path`, identified like the entity it refers to.
+/// `name` is the exported name; `alias` the local name it is bound to, when the
+/// import renames it (`import { Foo as Bar }`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportInfo {
     pub id: String,
     pub name: String,
     pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
 }
 
 impl ImportInfo {
     pub fn new(name: String, path: String) -> Self {
         let id = generate_entity_id(&path, &name);
-        ImportInfo { id, name, path }
+        ImportInfo {
+            id,
+            name,
+            path,
+            alias: None,
+        }
+    }
+
+    /// Binds the import to `alias` in the importing file.
+    pub fn with_alias(mut self, alias: String) -> Self {
+        self.alias = Some(alias);
+        self
+    }
+
+    /// The name the import is referenced by in the importing file.
+    pub fn local_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub(crate) struct Entity {
+/// An `export ... from` statement entry: `origin` is the entity in the module it
+/// comes from and `exported_name` the name the re-exporting file gives it. Both
+/// names are `*` for `export *`, and only the origin for `export * as ns`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReExport {
+    pub exported_name: String,
+    pub origin: ImportInfo,
+}
+
+impl ReExport {
+    pub fn new(exported_name: &str, origin_name: &str, origin_path: String) -> Self {
+        ReExport {
+            exported_name: exported_name.to_string(),
+            origin: ImportInfo::new(origin_name.to_string(), origin_path),
+        }
+    }
+
+    /// Whether this is a plain `export * from`, which forwards every name unchanged.
+    pub fn is_wildcard(&self) -> bool {
+        self.exported_name == "*"
+    }
+}
+
+/// What made an entity used: the first usage found for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Usage {
+    /// Referenced by other code in its own file
+    Local,
+    /// Imported by this file
+    Import(String),
+    /// Imported through this barrel file, which re-exports it, for the barrel's usage
+    ReExport {
+        barrel: String,
+        via: Option<Box<Usage>>,
+    },
+    /// Its selector appears in the template of this component, declared in `file`
+    Template { component: String, file: String },
+}
+
+impl std::fmt::Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Usage::Local => write!(f, "used locally in its own file"),
+            Usage::Import(file) => write!(f, "imported by {}", file),
+            Usage::ReExport { barrel, via } => {
+                write!(f, "re-exported by barrel {}", barrel)?;
+                match via {
+                    Some(via) => write!(f, ", which is {}", via),
+                    None => Ok(()),
+                }
+            }
+            Usage::Template { component, file } => {
+                write!(f, "used in the template of {} in {}", component, file)
+            }
+        }
+    }
+}
+
+/// An exported declaration, or a placeholder of type `Unknown` for a name imported
+/// from a file that does not declare it (yet).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entity {
     pub id: String,
     pub name: String,
     pub entity_type: EntityType,
     pub file_path: String,
+    /// 1-based line of the declaration, 0 when unknown (e.g. for imported placeholders)
+    pub line: usize,
     #[serde(skip)]
     pub deps: Rc<Vec<ImportInfo>>,
     pub used: bool,
+    /// Why `used` is set, see [`Entity::mark_used`]
+    #[serde(skip)]
+    pub used_by: Option<Usage>,
+    /// Whether the entity is referenced by other code in its own file
+    pub used_locally: bool,
+    /// Whether the entity is imported by test files (`.spec.ts`/`.test.ts`), which
+    /// does not count towards `used`
+    pub used_in_tests: bool,
+    /// Whether the declaration is exported; internal top-level declarations are
+    /// only recorded when the parser is asked to
+    pub exported: bool,
+    /// Classes and interfaces a class or interface extends or implements, when they
+    /// are imported or declared in the same file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bases: Vec<ImportInfo>,
+    /// Hash of the declaration's text, ignoring comments and indentation, to tell
+    /// modified entities apart when comparing snapshots
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// The Nx project owning the file, from the nearest `project.json` (or `nx.json` entry)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
 }
 
 impl Entity {
@@ -67,15 +198,174 @@ impl Entity {
             name,
             entity_type,
             file_path,
+            line: 0,
             deps,
             used: false,
+            used_by: None,
+            used_locally: false,
+            used_in_tests: false,
+            exported: true,
+            bases: Vec::new(),
+            content_hash: None,
+            project: None,
+        }
+    }
+
+    /// Records the bases of a class or interface. The ones declared in the same
+    /// file are not among the file's imports, so they are added to `deps`.
+    pub(crate) fn set_bases(&mut self, bases: Vec<ImportInfo>) {
+        let local: Vec<&ImportInfo> = bases
+            .iter()
+            .filter(|base| !self.deps.iter().any(|dep| dep.id == base.id))
+            .collect();
+        if !local.is_empty() {
+            self.deps = Rc::new(self.deps.iter().chain(local).cloned().collect());
         }
+        self.bases = bases;
+    }
+
+    /// Marks the entity as used, keeping the first usage as the reason.
+    pub(crate) fn mark_used(&mut self, usage: Usage) {
+        self.used = true;
+        self.used_by.get_or_insert(usage);
     }
+
+    /// Whether the entity is a placeholder for an import that did not resolve to a
+    /// scanned declaration (e.g. an external package or an out-of-scope file).
+    pub fn is_placeholder(&self) -> bool {
+        matches!(self.entity_type, EntityType::Unknown)
+    }
+
+    /// Whether the entity is only referenced from test files.
+    pub fn is_test_only(&self) -> bool {
+        !self.used && self.used_in_tests
+    }
+
+    /// Returns `path:line` when the declaration line is known, otherwise just the path.
+    pub fn location(&self) -> String {
+        if self.line > 0 {
+            format!("{}:{}", self.file_path, self.line)
+        } else {
+            self.file_path.clone()
+        }
+    }
+}
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher`, its output is fixed, so ids and content
+/// hashes written to snapshots and caches do not change with the toolchain.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+/// Id of the entity `name` declared in `file_path`, hashed from the path as given
+/// (absolute in a scan), so checkouts in different directories have different ids.
+/// Backslashes count as path separators, so Windows and forward-slash spellings of
+/// a path share ids.
+pub fn generate_entity_id(file_path: &str, name: &str) -> String {
+    let mut hasher = Fnv1a::new();
+    hasher.write(format!("{}:{}", file_path.replace('\\', "/"), name).as_bytes());
+    hasher.finish()
 }
 
-pub(crate) fn generate_entity_id(file_path: &str, name: &str) -> String {
-    let mut hasher = DefaultHasher::new();
-    let key = format!("{}:{}", file_path, name);
-    key.hash(&mut hasher);
-    format!("{:016x}", hasher.finish())
+/// Hashes the text of a declaration. Lines are trimmed and blank lines skipped, so
+/// re-indenting or moving a declaration keeps its hash.
+pub(crate) fn content_hash(text: &str) -> String {
+    let mut hasher = Fnv1a::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        hasher.write(line.as_bytes());
+        hasher.write(b"\n");
+    }
+    hasher.finish()
+}
+
+/// Converts a path to the string form used for file paths throughout the analysis,
+/// with forward slashes as separators on every platform.
+pub(crate) fn normalize_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if std::path::MAIN_SEPARATOR == '\\' {
+        path.replace('\\', "/")
+    } else {
+        path.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entity_type_from_str() {
+        assert_eq!("class".parse::<EntityType>(), Ok(EntityType::Class));
+        assert_eq!("enum".parse::<EntityType>(), Ok(EntityType::Enum));
+        assert_eq!("type".parse::<EntityType>(), Ok(EntityType::Type));
+        assert_eq!("interface".parse::<EntityType>(), Ok(EntityType::Interface));
+        assert_eq!("function".parse::<EntityType>(), Ok(EntityType::Function));
+        assert_eq!("const".parse::<EntityType>(), Ok(EntityType::Const));
+        assert_eq!("namespace".parse::<EntityType>(), Ok(EntityType::Namespace));
+        assert_eq!("unknown".parse::<EntityType>(), Ok(EntityType::Unknown));
+        assert_eq!(
+            " Interface ".parse::<EntityType>(),
+            Ok(EntityType::Interface)
+        );
+    }
+
+    #[test]
+    fn test_entity_id_ignores_separator_style() {
+        assert_eq!(
+            generate_entity_id("C:\\project\\libs\\foo.ts", "Foo"),
+            generate_entity_id("C:/project/libs/foo.ts", "Foo")
+        );
+        assert_eq!(
+            ImportInfo::new("Foo".to_string(), "libs\\foo.ts".to_string()).id,
+            generate_entity_id("libs/foo.ts", "Foo")
+        );
+    }
+
+    #[test]
+    fn test_hashes_are_fixed_across_builds() {
+        assert_eq!(
+            generate_entity_id("/project/libs/foo.ts", "Foo"),
+            "403b642b01004874"
+        );
+        assert_eq!(content_hash("  class Foo {\n\n  }\n"), "3e380c439efa8d1f");
+    }
+
+    #[test]
+    fn test_entity_type_from_str_rejects_invalid() {
+        assert!("module".parse::<EntityType>().is_err());
+        assert!("".parse::<EntityType>().is_err());
+    }
+
+    #[test]
+    fn test_entity_type_round_trips_through_display() {
+        for entity_type in [
+            EntityType::Class,
+            EntityType::Enum,
+            EntityType::Type,
+            EntityType::Interface,
+            EntityType::Function,
+            EntityType::Const,
+            EntityType::Namespace,
+        ] {
+            assert_eq!(
+                entity_type.to_string().parse::<EntityType>(),
+                Ok(entity_type)
+            );
+        }
+    }
 }
@@ -0,0 +1,521 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::{Value, json};
+
+use crate::entity::Entity;
+use crate::graph::{DependencyGraph, Neighborhood};
+
+/// Output formats supported by the listing commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text
+    Text,
+    /// Comma-separated values (RFC 4180)
+    Csv,
+    /// JSON
+    Json,
+    /// Newline-delimited JSON, one entity per line, written as it is produced
+    JsonLines,
+    /// SARIF 2.1.0 for code scanning tools (unused entities only)
+    Sarif,
+}
+
+/// Keys the listing commands can order their output by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortKey {
+    /// Entity id
+    Id,
+    /// Entity name
+    Name,
+    /// Entity type name
+    Type,
+    /// File path and declaration line
+    File,
+    /// Number of dependencies
+    Deps,
+}
+
+/// Order of the entities printed by a listing command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortOrder {
+    pub key: SortKey,
+    /// Sort descending instead of ascending
+    pub reverse: bool,
+}
+
+/// Orders the entities by the sort key, breaking ties by id so the output is stable.
+pub(crate) fn sort_entities(entities: &mut [&Entity], order: SortOrder) {
+    entities.sort_by(|a, b| {
+        let ordering = match order.key {
+            SortKey::Id => a.id.cmp(&b.id),
+            SortKey::Name => a.name.cmp(&b.name),
+            SortKey::Type => a.entity_type.to_string().cmp(&b.entity_type.to_string()),
+            SortKey::File => (&a.file_path, a.line).cmp(&(&b.file_path, b.line)),
+            SortKey::Deps => a.deps.len().cmp(&b.deps.len()),
+        }
+        .then_with(|| a.id.cmp(&b.id));
+
+        if order.reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
+const CSV_HEADER: &str = "id,name,type,file_path,used,dep_count";
+
+/// Renders the entities as CSV with one row per entity, in the given order.
+pub(crate) fn export_csv(entities: &[&Entity]) -> String {
+    let mut csv = String::new();
+    csv.push_str(CSV_HEADER);
+    csv.push_str("\r\n");
+
+    for entity in entities {
+        let row = [
+            csv_field(&entity.id),
+            csv_field(&entity.name),
+            csv_field(&entity.entity_type.to_string()),
+            csv_field(&entity.file_path),
+            entity.used.to_string(),
+            entity.deps.len().to_string(),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+
+    csv
+}
+
+/// Writes each entity as a JSON object on its own line (JSON Lines), so the output
+/// can be streamed without building the whole document first.
+pub(crate) fn write_json_lines(
+    writer: &mut (impl Write + ?Sized),
+    entities: &[&Entity],
+) -> io::Result<()> {
+    for entity in entities {
+        serde_json::to_writer(&mut *writer, entity)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+/// Renders a file coupling matrix as CSV. The header row lists the imported files
+/// and each following row starts with the importing file.
+pub(crate) fn export_coupling_csv(files: &[String], matrix: &[Vec<usize>]) -> String {
+    let mut csv = String::new();
+
+    let header: Vec<String> = std::iter::once("file".to_string())
+        .chain(files.iter().map(|file| csv_field(file)))
+        .collect();
+    csv.push_str(&header.join(","));
+    csv.push_str("\r\n");
+
+    for (file, row) in files.iter().zip(matrix) {
+        let cells: Vec<String> = std::iter::once(csv_field(file))
+            .chain(row.iter().map(|count| count.to_string()))
+            .collect();
+        csv.push_str(&cells.join(","));
+        csv.push_str("\r\n");
+    }
+
+    csv
+}
+
+/// Renders the dependency graph as a Mermaid `graph TD` diagram, limited to the
+/// `focus` neighborhood when given.
+pub(crate) fn export_mermaid(
+    entities: &HashMap<String, Entity>,
+    focus: Option<Neighborhood>,
+) -> String {
+    let mut graph = DependencyGraph::from_entities(entities);
+    if let Some(focus) = focus {
+        graph.retain_neighborhood(focus);
+    }
+
+    let mut nodes: Vec<_> = graph.nodes.iter().collect();
+    nodes.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut edges: Vec<_> = graph.edges.iter().collect();
+    edges.sort_by(|a, b| (&a.source, &a.target).cmp(&(&b.source, &b.target)));
+
+    let mut mermaid = String::from("graph TD\n");
+
+    for node in nodes {
+        mermaid.push_str(&format!(
+            "    {}[\"{}\"]\n",
+            mermaid_id(&node.id),
+            mermaid_label(&node.name)
+        ));
+    }
+
+    for edge in edges {
+        mermaid.push_str(&format!(
+            "    {} --> {}\n",
+            mermaid_id(&edge.source),
+            mermaid_id(&edge.target)
+        ));
+    }
+
+    mermaid
+}
+
+/// Mermaid node ids may only contain alphanumerics and underscores and should not
+/// collide with keywords such as `end`, so every id gets an `n` prefix.
+fn mermaid_id(id: &str) -> String {
+    let sanitized: String = id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("n{}", sanitized)
+}
+
+fn mermaid_label(name: &str) -> String {
+    name.replace('"', "#quot;")
+}
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const UNUSED_ENTITY_RULE: &str = "unused-entity";
+
+/// Renders unused entities as a SARIF 2.1.0 log with one `unused-entity` result per
+/// entity. Locations are relative to `root_path` (the `%SRCROOT%` base) when possible.
+pub(crate) fn export_sarif(unused: &[&Entity], root_path: &Path) -> String {
+    let results: Vec<Value> = unused
+        .iter()
+        .map(|entity| {
+            let uri = Path::new(&entity.file_path)
+                .strip_prefix(root_path)
+                .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_else(|_| entity.file_path.clone());
+
+            let mut physical_location = json!({
+                "artifactLocation": { "uri": uri, "uriBaseId": "%SRCROOT%" }
+            });
+            if entity.line > 0 {
+                physical_location["region"] = json!({ "startLine": entity.line });
+            }
+
+            json!({
+                "ruleId": UNUSED_ENTITY_RULE,
+                "level": "warning",
+                "message": { "text": sarif_message(entity) },
+                "locations": [{ "physicalLocation": physical_location }]
+            })
+        })
+        .collect();
+
+    let log = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": [{
+                        "id": UNUSED_ENTITY_RULE,
+                        "shortDescription": { "text": "Exported entity is never used" },
+                        "defaultConfiguration": { "level": "warning" }
+                    }]
+                }
+            },
+            "results": results
+        }]
+    });
+
+    serde_json::to_string_pretty(&log).unwrap_or_default()
+}
+
+fn sarif_message(entity: &Entity) -> String {
+    let message = format!("Unused {} '{}'", entity.entity_type, entity.name);
+    if entity.is_test_only() {
+        format!("{} (only used by tests)", message)
+    } else {
+        message
+    }
+}
+
+/// Quotes a CSV field when it contains a delimiter, a quote or a line break,
+/// doubling any embedded quotes as required by RFC 4180.
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::{EntityType, ImportInfo};
+    use std::rc::Rc;
+
+    /// Minimal RFC 4180 reader used to verify the exported output.
+    fn parse_csv(input: &str) -> Vec<Vec<String>> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        let mut field = String::new();
+        let mut in_quotes = false;
+        let mut chars = input.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if in_quotes {
+                if c == '"' {
+                    if chars.peek() == Some(&'"') {
+                        field.push('"');
+                        chars.next();
+                    } else {
+                        in_quotes = false;
+                    }
+                } else {
+                    field.push(c);
+                }
+                continue;
+            }
+
+            match c {
+                '"' => in_quotes = true,
+                ',' => row.push(std::mem::take(&mut field)),
+                '\r' => {}
+                '\n' => {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                _ => field.push(c),
+            }
+        }
+
+        rows
+    }
+
+    #[test]
+    fn test_csv_field_escaping() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_export_coupling_csv() {
+        let files = vec!["/src/a.ts".to_string(), "/src/b,c.ts".to_string()];
+        let matrix = vec![vec![0, 2], vec![0, 0]];
+
+        let rows = parse_csv(&export_coupling_csv(&files, &matrix));
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0], vec!["file", "/src/a.ts", "/src/b,c.ts"]);
+        assert_eq!(rows[1], vec!["/src/a.ts", "0", "2"]);
+        assert_eq!(rows[2], vec!["/src/b,c.ts", "0", "0"]);
+    }
+
+    fn create_entity(name: &str, file_path: &str, deps: Vec<ImportInfo>) -> Entity {
+        Entity::new(
+            name.to_string(),
+            EntityType::Class,
+            file_path.to_string(),
+            Rc::new(deps),
+        )
+    }
+
+    #[test]
+    fn test_sort_entities_by_each_key() {
+        let import = ImportInfo::new("Helper".to_string(), "/src/helper.ts".to_string());
+        let mut zeta = create_entity("Zeta", "/src/a.ts", vec![]);
+        zeta.entity_type = EntityType::Function;
+        let mut alpha = create_entity("Alpha", "/src/c.ts", vec![import.clone(), import.clone()]);
+        alpha.entity_type = EntityType::Const;
+        let beta = create_entity("Beta", "/src/b.ts", vec![import]);
+
+        let names = |key: SortKey, reverse: bool| {
+            let mut sorted = vec![&zeta, &alpha, &beta];
+            sort_entities(&mut sorted, SortOrder { key, reverse });
+            sorted.iter().map(|e| e.name.as_str()).collect::<Vec<_>>()
+        };
+
+        let mut by_id = [&zeta, &alpha, &beta];
+        by_id.sort_by(|a, b| a.id.cmp(&b.id));
+        let by_id: Vec<_> = by_id.iter().map(|e| e.name.as_str()).collect();
+
+        assert_eq!(names(SortKey::Id, false), by_id);
+        assert_eq!(names(SortKey::Name, false), ["Alpha", "Beta", "Zeta"]);
+        assert_eq!(names(SortKey::Type, false), ["Beta", "Alpha", "Zeta"]);
+        assert_eq!(names(SortKey::File, false), ["Zeta", "Beta", "Alpha"]);
+        assert_eq!(names(SortKey::Deps, false), ["Zeta", "Beta", "Alpha"]);
+        assert_eq!(names(SortKey::Name, true), ["Zeta", "Beta", "Alpha"]);
+
+        // CSV rows keep the order they are given in
+        let mut sorted = vec![&zeta, &alpha, &beta];
+        sort_entities(
+            &mut sorted,
+            SortOrder {
+                key: SortKey::File,
+                reverse: true,
+            },
+        );
+        let csv_names: Vec<String> = parse_csv(&export_csv(&sorted))
+            .into_iter()
+            .skip(1)
+            .map(|row| row[1].clone())
+            .collect();
+        assert_eq!(csv_names, ["Alpha", "Beta", "Zeta"]);
+    }
+
+    #[test]
+    fn test_export_mermaid_contains_header_and_edges() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+
+        let helper = create_entity("Helper", "/src/helper.ts", vec![]);
+        let helper_id = helper.id.clone();
+        entities.insert(helper.id.clone(), helper);
+
+        let import = ImportInfo::new("Helper".to_string(), "/src/helper.ts".to_string());
+        let service = create_entity("Service", "/src/service.ts", vec![import]);
+        let service_id = service.id.clone();
+        entities.insert(service.id.clone(), service);
+
+        let mermaid = export_mermaid(&entities, None);
+
+        assert!(mermaid.starts_with("graph TD\n"));
+        assert!(mermaid.contains(&format!("n{}[\"Helper\"]", helper_id)));
+        assert!(mermaid.contains(&format!("n{}[\"Service\"]", service_id)));
+        assert!(mermaid.contains(&format!("n{} --> n{}", service_id, helper_id)));
+    }
+
+    #[test]
+    fn test_export_mermaid_focus_limits_to_neighborhood() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+
+        // A -> B -> C, focusing on C must leave A out
+        let c = create_entity("C", "/src/c.ts", vec![]);
+        let c_id = c.id.clone();
+        entities.insert(c.id.clone(), c);
+
+        let b = create_entity(
+            "B",
+            "/src/b.ts",
+            vec![ImportInfo::new("C".to_string(), "/src/c.ts".to_string())],
+        );
+        entities.insert(b.id.clone(), b);
+
+        let a = create_entity(
+            "A",
+            "/src/a.ts",
+            vec![ImportInfo::new("B".to_string(), "/src/b.ts".to_string())],
+        );
+        entities.insert(a.id.clone(), a);
+
+        let mermaid = export_mermaid(
+            &entities,
+            Some(Neighborhood {
+                entity_id: &c_id,
+                depth: 1,
+            }),
+        );
+
+        assert!(mermaid.contains("[\"B\"]"));
+        assert!(mermaid.contains("[\"C\"]"));
+        assert!(!mermaid.contains("[\"A\"]"));
+        assert_eq!(mermaid.matches("-->").count(), 1);
+    }
+
+    #[test]
+    fn test_mermaid_id_is_sanitized() {
+        assert_eq!(mermaid_id("abc-123:x"), "nabc_123_x");
+        assert_eq!(mermaid_label("say \"hi\""), "say #quot;hi#quot;");
+    }
+
+    #[test]
+    fn test_export_sarif_structure() {
+        let mut service = create_entity("Service", "/project/apps/web/src/service.ts", vec![]);
+        service.line = 12;
+        let helper = create_entity("Helper", "/elsewhere/helper.ts", vec![]);
+
+        let sarif: Value =
+            serde_json::from_str(&export_sarif(&[&service, &helper], Path::new("/project")))
+                .unwrap();
+
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(sarif["$schema"], SARIF_SCHEMA);
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "sting");
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], UNUSED_ENTITY_RULE);
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["ruleId"], UNUSED_ENTITY_RULE);
+        assert_eq!(results[0]["message"]["text"], "Unused class 'Service'");
+        let location = &results[0]["locations"][0]["physicalLocation"];
+        assert_eq!(
+            location["artifactLocation"]["uri"],
+            "apps/web/src/service.ts"
+        );
+        assert_eq!(location["region"]["startLine"], 12);
+
+        let location = &results[1]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "/elsewhere/helper.ts");
+        assert!(location.get("region").is_none());
+    }
+
+    #[test]
+    fn test_json_lines_are_independent_entities() {
+        let mut service = create_entity("Service", "/src/service.ts", vec![]);
+        service.line = 3;
+        let helper = create_entity("Helper", "/src/multi\nline.ts", vec![]);
+
+        let mut output = Vec::new();
+        write_json_lines(&mut output, &[&service, &helper]).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let parsed: Vec<Entity> = lines
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(parsed[0].id, service.id);
+        assert_eq!(parsed[0].line, 3);
+        assert_eq!(parsed[1].file_path, "/src/multi\nline.ts");
+    }
+
+    #[test]
+    fn test_export_csv_round_trip() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+
+        let import = ImportInfo::new("Helper".to_string(), "/src/helper.ts".to_string());
+        let mut service = Entity::new(
+            "Service".to_string(),
+            EntityType::Class,
+            "/src/odd,name \"v2\".ts".to_string(),
+            Rc::new(vec![import]),
+        );
+        service.used = true;
+        entities.insert(service.id.clone(), service.clone());
+
+        let helper = Entity::new(
+            "Helper".to_string(),
+            EntityType::Function,
+            "/src/helper.ts".to_string(),
+            Rc::new(vec![]),
+        );
+        entities.insert(helper.id.clone(), helper);
+
+        let rows = parse_csv(&export_csv(&entities.values().collect::<Vec<_>>()));
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].join(","), CSV_HEADER);
+
+        let service_row = rows.iter().find(|row| row[1] == "Service").unwrap();
+        assert_eq!(service_row[0], service.id);
+        assert_eq!(service_row[2], "class");
+        assert_eq!(service_row[3], "/src/odd,name \"v2\".ts");
+        assert_eq!(service_row[4], "true");
+        assert_eq!(service_row[5], "1");
+
+        let helper_row = rows.iter().find(|row| row[1] == "Helper").unwrap();
+        assert_eq!(helper_row[4], "false");
+        assert_eq!(helper_row[5], "0");
+    }
+}
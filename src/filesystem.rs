@@ -0,0 +1,280 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[cfg(test)]
+pub(crate) use memory::MemoryFileSystem;
+
+/// What the scan needs to know about a file or directory.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Metadata {
+    pub is_dir: bool,
+    pub len: u64,
+    pub modified: SystemTime,
+}
+
+/// An entry of a directory listing.
+#[derive(Debug, Clone)]
+pub(crate) struct DirEntry {
+    pub path: PathBuf,
+    /// Whether the entry itself is a symlink, whatever it points to
+    pub is_symlink: bool,
+}
+
+/// The file system the scan reads the project from. Real runs use
+/// `RealFileSystem`; tests can run the whole scan against an in-memory tree.
+/// Paths are followed through symlinks, like `std::fs::metadata` does.
+pub(crate) trait FileSystem: fmt::Debug + Sync {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>>;
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok_and(|metadata| metadata.is_dir)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok_and(|metadata| !metadata.is_dir)
+    }
+}
+
+/// The file system of the machine, through `std::fs`.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct RealFileSystem;
+
+impl FileSystem for RealFileSystem {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        fs::write(path, contents)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        fs::read_dir(path)?
+            .map(|entry| {
+                let entry = entry?;
+                Ok(DirEntry {
+                    path: entry.path(),
+                    is_symlink: entry.file_type()?.is_symlink(),
+                })
+            })
+            .collect()
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let metadata = fs::metadata(path)?;
+        Ok(Metadata {
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+            modified: metadata.modified()?,
+        })
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+}
+
+#[cfg(test)]
+mod memory {
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::io;
+    use std::path::{Component, Path, PathBuf};
+    use std::sync::Mutex;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use super::{DirEntry, FileSystem, Metadata};
+
+    /// Files held in memory by absolute path. Directories exist implicitly as the
+    /// ancestors of the files, and there are no symlinks. Every write counts as one
+    /// tick of the modification clock, so rewritten files look changed.
+    #[derive(Debug, Default)]
+    pub(crate) struct MemoryFileSystem {
+        files: Mutex<MemoryFiles>,
+    }
+
+    #[derive(Debug, Default)]
+    struct MemoryFiles {
+        contents: BTreeMap<PathBuf, (Vec<u8>, SystemTime)>,
+        writes: u64,
+    }
+
+    impl MemoryFileSystem {
+        pub fn new() -> Self {
+            MemoryFileSystem::default()
+        }
+
+        /// Adds a file, creating its directories.
+        pub fn with_file(self, path: impl AsRef<Path>, contents: &str) -> Self {
+            self.write(path.as_ref(), contents.as_bytes()).unwrap();
+            self
+        }
+
+        fn not_found(path: &Path) -> io::Error {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} not found", path.display()),
+            )
+        }
+    }
+
+    impl FileSystem for MemoryFileSystem {
+        fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+            let path = self.canonicalize(path)?;
+            let files = self.files.lock().unwrap();
+            files
+                .contents
+                .get(&path)
+                .map(|(contents, _)| contents.clone())
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::IsADirectory, path.display().to_string())
+                })
+        }
+
+        fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+            let path = lexical_normalize(path);
+            let mut files = self.files.lock().unwrap();
+            files.writes += 1;
+            let modified = UNIX_EPOCH + Duration::from_secs(files.writes);
+            files.contents.insert(path, (contents.to_vec(), modified));
+            Ok(())
+        }
+
+        fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+            let path = self.canonicalize(path)?;
+            let files = self.files.lock().unwrap();
+            let children: BTreeSet<PathBuf> = files
+                .contents
+                .keys()
+                .filter_map(|file| file.strip_prefix(&path).ok()?.components().next())
+                .map(|child| path.join(child))
+                .collect();
+            if children.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotADirectory,
+                    path.display().to_string(),
+                ));
+            }
+
+            Ok(children
+                .into_iter()
+                .map(|path| DirEntry {
+                    path,
+                    is_symlink: false,
+                })
+                .collect())
+        }
+
+        fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+            let path = lexical_normalize(path);
+            let files = self.files.lock().unwrap();
+            if let Some((contents, modified)) = files.contents.get(&path) {
+                return Ok(Metadata {
+                    is_dir: false,
+                    len: contents.len() as u64,
+                    modified: *modified,
+                });
+            }
+
+            if files.contents.keys().any(|file| file.starts_with(&path)) {
+                Ok(Metadata {
+                    is_dir: true,
+                    len: 0,
+                    modified: UNIX_EPOCH,
+                })
+            } else {
+                Err(MemoryFileSystem::not_found(&path))
+            }
+        }
+
+        fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+            self.metadata(path)?;
+            Ok(lexical_normalize(path))
+        }
+    }
+
+    /// Resolves `.` and `..` in `path` without looking at any file system.
+    fn lexical_normalize(path: &Path) -> PathBuf {
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    normalized.pop();
+                }
+                component => normalized.push(component),
+            }
+        }
+        normalized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_file_system_implies_directories() {
+        let file_system = MemoryFileSystem::new()
+            .with_file("/project/libs/a.ts", "export const a = 1;")
+            .with_file("/project/libs/nested/b.ts", "");
+
+        assert!(file_system.is_dir(Path::new("/project/libs")));
+        assert!(file_system.is_file(Path::new("/project/libs/a.ts")));
+        assert!(!file_system.exists(Path::new("/project/apps")));
+        assert_eq!(
+            file_system
+                .read_to_string(Path::new("/project/libs/nested/../a.ts"))
+                .unwrap(),
+            "export const a = 1;"
+        );
+        assert_eq!(
+            file_system
+                .canonicalize(Path::new("/project/./libs/nested/.."))
+                .unwrap(),
+            Path::new("/project/libs")
+        );
+
+        let entries: Vec<PathBuf> = file_system
+            .read_dir(Path::new("/project/libs"))
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                PathBuf::from("/project/libs/a.ts"),
+                PathBuf::from("/project/libs/nested")
+            ]
+        );
+        assert!(
+            file_system
+                .read_dir(Path::new("/project/libs/a.ts"))
+                .is_err()
+        );
+    }
+}
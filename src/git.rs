@@ -1,8 +1,11 @@
 use std::fmt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
-use git2::{Delta, DiffOptions, Repository};
+use git2::{Delta, DiffFindOptions, DiffOptions, Repository};
+
+use crate::entity::normalize_path;
+use crate::report::Reporter;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ChangeType {
@@ -27,82 +30,195 @@ impl fmt::Display for ChangeType {
 pub struct ChangedFile {
     pub path: String,
     pub change_type: ChangeType,
+    /// Previous path of a renamed or copied file
+    pub old_path: Option<String>,
 }
 
 impl ChangedFile {
     pub fn new(path: String, change_type: ChangeType) -> Self {
-        Self { path, change_type }
+        Self {
+            path,
+            change_type,
+            old_path: None,
+        }
+    }
+}
+
+/// Describes which two snapshots of the repository to compare.
+#[derive(Debug, Clone)]
+pub struct DiffRange<'a> {
+    /// Git reference to compare against (branch, tag, or commit SHA)
+    pub base_ref: &'a str,
+    /// Git reference of the newer side, HEAD when `None`
+    pub head_ref: Option<&'a str>,
+    /// Start the diff at the merge-base of both refs (`git diff base...head`)
+    /// instead of comparing the two trees directly (`git diff base..head`)
+    pub use_merge_base: bool,
+    /// Compare against the working directory (staged, unstaged and untracked
+    /// changes) instead of the head commit
+    pub include_working_tree: bool,
+    /// When the refs share no history (e.g. in a shallow clone), compare the two
+    /// trees directly with a warning instead of failing
+    pub merge_base_fallback: bool,
+}
+
+impl<'a> DiffRange<'a> {
+    pub fn new(base_ref: &'a str) -> Self {
+        Self {
+            base_ref,
+            head_ref: None,
+            use_merge_base: true,
+            include_working_tree: false,
+            merge_base_fallback: false,
+        }
     }
 }
 
-pub fn get_changed_files(repo_path: &Path, base_ref: &str) -> Result<Vec<ChangedFile>> {
-    let repo = Repository::discover(repo_path).with_context(|| {
+/// Lists files changed in the given range.
+///
+/// With `use_merge_base`, the diff starts at the merge-base of both refs, so only
+/// changes made on the head side are reported. Otherwise, or when there is no
+/// merge-base and `merge_base_fallback` is set, the two trees are compared
+/// directly. With `include_working_tree`, uncommitted changes on top of HEAD are
+/// reported as well. The warning about a missing merge-base goes to `reporter`.
+pub fn get_changed_files(
+    repo_path: &Path,
+    range: &DiffRange,
+    reporter: &Reporter,
+) -> Result<Vec<ChangedFile>> {
+    let (repo, repo_root) = discover_repository(repo_path)?;
+    changed_files_in(&repo, &repo_root, range, reporter)
+}
+
+/// Finds the git repository at or above `path` and the canonical path of its
+/// working directory. Changed files are reported below that path, so they match
+/// the paths of the scanned files even when the repository is reached through a
+/// symlink.
+pub(crate) fn discover_repository(path: &Path) -> Result<(Repository, PathBuf)> {
+    let repo = Repository::discover(path).with_context(|| {
         format!(
             "Failed to find git repository at or above '{}'",
-            repo_path.display()
+            path.display()
         )
     })?;
 
-    let repo_root = repo
+    let workdir = repo
         .workdir()
         .ok_or_else(|| anyhow::anyhow!("Repository has no working directory (bare repository)"))?;
+    let repo_root = workdir
+        .canonicalize()
+        .unwrap_or_else(|_| workdir.to_path_buf());
 
-    // Resolve the base reference to a commit
-    let base_obj = repo.revparse_single(base_ref).with_context(|| {
-        format!(
-            "Could not resolve git reference '{}'. Ensure it exists.",
-            base_ref
-        )
-    })?;
+    Ok((repo, repo_root))
+}
 
-    let base_commit = base_obj
-        .peel_to_commit()
-        .with_context(|| format!("Reference '{}' does not point to a commit", base_ref))?;
+/// Lists files changed in the given range of `repo`, with paths below `repo_root`.
+pub(crate) fn changed_files_in(
+    repo: &Repository,
+    repo_root: &Path,
+    range: &DiffRange,
+    reporter: &Reporter,
+) -> Result<Vec<ChangedFile>> {
+    let base_ref = range.base_ref;
+    let head_ref = range.head_ref;
+
+    if range.include_working_tree && head_ref.is_some() {
+        anyhow::bail!("Working tree changes can only be compared against HEAD");
+    }
 
-    let head_ref = repo
-        .head()
-        .with_context(|| "Failed to get HEAD reference")?;
-    let head_commit = head_ref
-        .peel_to_commit()
-        .with_context(|| "HEAD does not point to a commit")?;
+    // Resolve the base reference to a commit
+    let base_commit = resolve_commit(repo, base_ref)?;
+
+    let head_commit = match head_ref {
+        Some(head_ref) => resolve_commit(repo, head_ref)?,
+        None => repo
+            .head()
+            .with_context(|| "Failed to get HEAD reference")?
+            .peel_to_commit()
+            .with_context(|| "HEAD does not point to a commit")?,
+    };
+    let head_name = head_ref.unwrap_or("HEAD");
 
     let head_tree = head_commit
         .tree()
-        .with_context(|| "Failed to get tree from HEAD commit")?;
-
-    // Find the merge-base (common ancestor) between HEAD and base
-    // This ensures we only get files changed in the current branch,
-    // regardless of whether the local base branch is up-to-date
-    let merge_base_oid = repo
-        .merge_base(head_commit.id(), base_commit.id())
-        .with_context(|| {
-            format!(
-                "Could not find merge-base between HEAD and '{}'. Ensure the branches share common history.",
-                base_ref
-            )
-        })?;
-
-    let merge_base_commit = repo
-        .find_commit(merge_base_oid)
-        .with_context(|| "Failed to find merge-base commit")?;
-
-    let merge_base_tree = merge_base_commit
-        .tree()
-        .with_context(|| "Failed to get tree from merge-base commit")?;
+        .with_context(|| format!("Failed to get tree from '{}' commit", head_name))?;
+
+    let base_tree = if range.use_merge_base {
+        // Find the merge-base (common ancestor) between head and base
+        // This ensures we only get files changed in the current branch,
+        // regardless of whether the local base branch is up-to-date
+        match repo.merge_base(head_commit.id(), base_commit.id()) {
+            Ok(merge_base_oid) => {
+                let merge_base_commit = repo
+                    .find_commit(merge_base_oid)
+                    .with_context(|| "Failed to find merge-base commit")?;
+
+                merge_base_commit
+                    .tree()
+                    .with_context(|| "Failed to get tree from merge-base commit")?
+            }
+            Err(_) if range.merge_base_fallback => {
+                reporter.warn(format!(
+                    "No merge-base between {} and '{}', comparing the trees directly",
+                    head_name, base_ref
+                ));
+                base_commit
+                    .tree()
+                    .with_context(|| format!("Failed to get tree from '{}' commit", base_ref))?
+            }
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!(
+                        "Could not find merge-base between {} and '{}'. Ensure the branches share common history, \
+                         or use --merge-base-fallback or --no-merge-base to compare the trees directly.",
+                        head_name, base_ref
+                    )
+                });
+            }
+        }
+    } else {
+        base_commit
+            .tree()
+            .with_context(|| format!("Failed to get tree from '{}' commit", base_ref))?
+    };
 
     let mut diff_opts = DiffOptions::new();
-    diff_opts.include_untracked(false);
-
-    let diff = repo
-        .diff_tree_to_tree(Some(&merge_base_tree), Some(&head_tree), Some(&mut diff_opts))
-        .with_context(|| "Failed to compute diff between merge-base and HEAD")?;
+    diff_opts.include_untracked(range.include_working_tree);
+    diff_opts.recurse_untracked_dirs(range.include_working_tree);
+
+    let mut diff = if range.include_working_tree {
+        repo.diff_tree_to_workdir_with_index(Some(&base_tree), Some(&mut diff_opts))
+            .with_context(|| {
+                format!(
+                    "Failed to compute diff between '{}' and the working tree",
+                    base_ref
+                )
+            })?
+    } else {
+        repo.diff_tree_to_tree(Some(&base_tree), Some(&head_tree), Some(&mut diff_opts))
+            .with_context(|| {
+                format!(
+                    "Failed to compute diff between '{}' and {}",
+                    base_ref, head_name
+                )
+            })?
+    };
+
+    // Pair up deleted and added files so renames are not reported as add + delete
+    let mut find_opts = DiffFindOptions::new();
+    find_opts
+        .renames(true)
+        .copies(true)
+        .for_untracked(range.include_working_tree);
+    diff.find_similar(Some(&mut find_opts))
+        .with_context(|| "Failed to detect renamed files")?;
 
     let mut changed_files = Vec::new();
 
     diff.foreach(
         &mut |delta, _| {
             let change_type = match delta.status() {
-                Delta::Added => ChangeType::Added,
+                Delta::Added | Delta::Untracked => ChangeType::Added,
                 Delta::Deleted => ChangeType::Deleted,
                 Delta::Modified => ChangeType::Modified,
                 Delta::Renamed => ChangeType::Renamed,
@@ -117,9 +233,17 @@ pub fn get_changed_files(repo_path: &Path, base_ref: &str) -> Result<Vec<Changed
             };
 
             if let Some(path) = file_path {
-                let absolute_path = repo_root.join(path);
-                let path_str = absolute_path.to_string_lossy().to_string();
-                changed_files.push(ChangedFile::new(path_str, change_type));
+                let path_str = normalize_path(&repo_root.join(path));
+                let mut changed_file = ChangedFile::new(path_str, change_type);
+
+                if matches!(delta.status(), Delta::Renamed | Delta::Copied) {
+                    changed_file.old_path = delta
+                        .old_file()
+                        .path()
+                        .map(|old| normalize_path(&repo_root.join(old)));
+                }
+
+                changed_files.push(changed_file);
             }
 
             true
@@ -133,13 +257,102 @@ pub fn get_changed_files(repo_path: &Path, base_ref: &str) -> Result<Vec<Changed
     Ok(changed_files)
 }
 
+/// Finds the latest tag matching the glob `pattern` (e.g. `v*`) on the history of
+/// HEAD. Tags are ordered by version when they all carry one (`v1.10.0` after
+/// `v1.9.2`, a pre-release before its release) and by commit date otherwise.
+pub fn latest_tag(repo_path: &Path, pattern: &str) -> Result<String> {
+    let (repo, _) = discover_repository(repo_path)?;
+    let head = repo
+        .head()
+        .with_context(|| "Failed to get HEAD reference")?
+        .peel_to_commit()
+        .with_context(|| "HEAD does not point to a commit")?;
+
+    let names = repo
+        .tag_names(Some(pattern))
+        .with_context(|| format!("Failed to list tags matching '{}'", pattern))?;
+
+    let mut tags = Vec::new();
+    for name in names.iter().flatten() {
+        let Ok(commit) = resolve_commit(&repo, name) else {
+            continue;
+        };
+        let on_history = commit.id() == head.id()
+            || repo
+                .graph_descendant_of(head.id(), commit.id())
+                .unwrap_or(false);
+        if on_history {
+            tags.push((name, commit.time().seconds()));
+        }
+    }
+
+    let versions: Option<Vec<TagVersion>> = tags
+        .iter()
+        .map(|(name, _)| TagVersion::parse(name))
+        .collect();
+    let latest = match versions {
+        Some(versions) => tags
+            .iter()
+            .zip(versions)
+            .max_by(|a, b| a.1.cmp(&b.1))
+            .map(|(tag, _)| tag),
+        None => tags.iter().max_by_key(|(_, time)| *time),
+    };
+
+    latest.map(|(name, _)| name.to_string()).ok_or_else(|| {
+        anyhow::anyhow!("No tag matching '{}' found on the history of HEAD", pattern)
+    })
+}
+
+/// Version number of a tag such as `v1.2.3` or `release-2.0.0-rc.1`, ordered by
+/// its numbers and then with a pre-release before the release.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct TagVersion {
+    numbers: Vec<u64>,
+    release: bool,
+}
+
+impl TagVersion {
+    fn parse(tag: &str) -> Option<TagVersion> {
+        let version = &tag[tag.find(|c: char| c.is_ascii_digit())?..];
+        let (core, release) = match version.split_once('-') {
+            Some((core, _)) => (core, false),
+            None => (version, true),
+        };
+        let numbers = core
+            .split('.')
+            .map(|part| part.parse().ok())
+            .collect::<Option<Vec<u64>>>()?;
+
+        Some(TagVersion { numbers, release })
+    }
+}
+
+fn resolve_commit<'repo>(repo: &'repo Repository, reference: &str) -> Result<git2::Commit<'repo>> {
+    let object = repo.revparse_single(reference).with_context(|| {
+        format!(
+            "Could not resolve git reference '{}'. Ensure it exists.",
+            reference
+        )
+    })?;
+
+    object
+        .peel_to_commit()
+        .with_context(|| format!("Reference '{}' does not point to a commit", reference))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::report::Verbosity;
     use git2::Signature;
     use std::fs;
     use tempfile::tempdir;
 
+    fn quiet() -> Reporter<'static> {
+        Reporter::stdout(Verbosity::Quiet)
+    }
+
     fn create_commit(
         repo: &Repository,
         message: &str,
@@ -170,6 +383,7 @@ mod tests {
         let cf = ChangedFile::new("/path/to/file.ts".to_string(), ChangeType::Modified);
         assert_eq!(cf.path, "/path/to/file.ts");
         assert_eq!(cf.change_type, ChangeType::Modified);
+        assert_eq!(cf.old_path, None);
     }
 
     #[test]
@@ -236,7 +450,7 @@ mod tests {
         repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force())).unwrap();
 
         // Get changed files comparing feature branch to main
-        let changed = get_changed_files(temp.path(), "main").unwrap();
+        let changed = get_changed_files(temp.path(), &DiffRange::new("main"), &quiet()).unwrap();
 
         // Extract just the filenames for easier assertion
         let changed_names: Vec<&str> = changed
@@ -285,7 +499,7 @@ mod tests {
         fs::write(temp.path().join("file_c.txt"), "content c").unwrap();
         create_commit(&repo, "Commit C", Some(&commit_b));
 
-        let changed = get_changed_files(temp.path(), "v1.0").unwrap();
+        let changed = get_changed_files(temp.path(), &DiffRange::new("v1.0"), &quiet()).unwrap();
 
         let changed_names: Vec<&str> = changed
             .iter()
@@ -331,7 +545,7 @@ mod tests {
         let sig = Signature::now("Test", "test@test.com").unwrap();
         repo.commit(Some("HEAD"), &sig, &sig, "Changes", &tree, &[&commit_a]).unwrap();
 
-        let changed = get_changed_files(temp.path(), "base").unwrap();
+        let changed = get_changed_files(temp.path(), &DiffRange::new("base"), &quiet()).unwrap();
 
         let find_change = |name: &str| -> Option<&ChangedFile> {
             changed.iter().find(|cf| cf.path.ends_with(name))
@@ -342,4 +556,244 @@ mod tests {
         assert_eq!(find_change("new_file.txt").unwrap().change_type, ChangeType::Added);
         assert_eq!(changed.len(), 3);
     }
+
+    #[test]
+    fn test_get_changed_files_between_two_tags_direct() {
+        // main: A (v1.0) --- B (v2.0) --- C (HEAD)
+        // Diffing v1.0..v2.0 must only show B's changes, ignoring HEAD
+
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        repo.config().unwrap().set_str("user.name", "Test").unwrap();
+        repo.config().unwrap().set_str("user.email", "test@test.com").unwrap();
+
+        fs::write(temp.path().join("file_a.txt"), "content a").unwrap();
+        let commit_a_oid = create_commit(&repo, "Commit A", None);
+        let commit_a = repo.find_commit(commit_a_oid).unwrap();
+        repo.tag_lightweight("v1.0", commit_a.as_object(), false).unwrap();
+
+        fs::write(temp.path().join("file_b.txt"), "content b").unwrap();
+        let commit_b_oid = create_commit(&repo, "Commit B", Some(&commit_a));
+        let commit_b = repo.find_commit(commit_b_oid).unwrap();
+        repo.tag_lightweight("v2.0", commit_b.as_object(), false).unwrap();
+
+        fs::write(temp.path().join("file_c.txt"), "content c").unwrap();
+        create_commit(&repo, "Commit C", Some(&commit_b));
+
+        let range = DiffRange {
+            head_ref: Some("v2.0"),
+            use_merge_base: false,
+            ..DiffRange::new("v1.0")
+        };
+        let changed = get_changed_files(temp.path(), &range, &quiet()).unwrap();
+
+        assert_eq!(changed.len(), 1);
+        assert!(changed[0].path.ends_with("file_b.txt"));
+        assert_eq!(changed[0].change_type, ChangeType::Added);
+    }
+
+    #[test]
+    fn test_latest_tag_picks_highest_version_on_head_history() {
+        // main: A (v1.9.0) --- B (v1.10.0-rc.1) --- C (v1.10.0, HEAD)
+        //        \
+        //         D (v3.0.0, not merged)
+
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        fs::write(temp.path().join("file_a.txt"), "content a").unwrap();
+        let commit_a_oid = create_commit(&repo, "Commit A", None);
+        let commit_a = repo.find_commit(commit_a_oid).unwrap();
+        let sig = Signature::now("Test User", "test@example.com").unwrap();
+        let tree = commit_a.tree().unwrap();
+        let commit_d_oid = repo
+            .commit(None, &sig, &sig, "Commit D", &tree, &[&commit_a])
+            .unwrap();
+        let commit_d = repo.find_commit(commit_d_oid).unwrap();
+
+        fs::write(temp.path().join("file_b.txt"), "content b").unwrap();
+        let commit_b_oid = create_commit(&repo, "Commit B", Some(&commit_a));
+        let commit_b = repo.find_commit(commit_b_oid).unwrap();
+
+        fs::write(temp.path().join("file_c.txt"), "content c").unwrap();
+        let commit_c_oid = create_commit(&repo, "Commit C", Some(&commit_b));
+        let commit_c = repo.find_commit(commit_c_oid).unwrap();
+
+        for (tag, commit) in [
+            ("v1.9.0", &commit_a),
+            ("v3.0.0", &commit_d),
+            ("v1.10.0-rc.1", &commit_b),
+            ("v1.10.0", &commit_c),
+            ("nightly", &commit_c),
+        ] {
+            repo.tag_lightweight(tag, commit.as_object(), false)
+                .unwrap();
+        }
+
+        assert_eq!(latest_tag(temp.path(), "v*").unwrap(), "v1.10.0");
+        assert_eq!(latest_tag(temp.path(), "v1.9*").unwrap(), "v1.9.0");
+        assert!(latest_tag(temp.path(), "release-*").is_err());
+    }
+
+    #[test]
+    fn test_get_changed_files_falls_back_without_merge_base() {
+        // old-root: A      HEAD: X (unrelated root commit)
+        // Without a merge-base the trees of A and X are compared when allowed
+
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        fs::write(temp.path().join("shared.txt"), "old").unwrap();
+        fs::write(temp.path().join("removed.txt"), "gone soon").unwrap();
+        let commit_a_oid = create_commit(&repo, "Commit A", None);
+        let commit_a = repo.find_commit(commit_a_oid).unwrap();
+        repo.branch("old-root", &commit_a, false).unwrap();
+
+        fs::remove_file(temp.path().join("removed.txt")).unwrap();
+        fs::write(temp.path().join("shared.txt"), "new").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None).unwrap();
+        index.update_all(["*"].iter(), None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("Test User", "test@example.com").unwrap();
+        let commit_x_oid = repo.commit(None, &sig, &sig, "Commit X", &tree, &[]).unwrap();
+        repo.set_head_detached(commit_x_oid).unwrap();
+
+        let range = DiffRange::new("old-root");
+        assert!(get_changed_files(temp.path(), &range, &quiet()).is_err());
+
+        let range = DiffRange {
+            merge_base_fallback: true,
+            ..DiffRange::new("old-root")
+        };
+        let mut changed = get_changed_files(temp.path(), &range, &quiet()).unwrap();
+        changed.sort_by(|a, b| a.path.cmp(&b.path));
+
+        assert_eq!(changed.len(), 2);
+        assert!(changed[0].path.ends_with("removed.txt"));
+        assert_eq!(changed[0].change_type, ChangeType::Deleted);
+        assert!(changed[1].path.ends_with("shared.txt"));
+        assert_eq!(changed[1].change_type, ChangeType::Modified);
+    }
+
+    #[test]
+    fn test_get_changed_files_direct_diff_includes_base_side_changes() {
+        // Same divergent setup as the merge-base test, but compared tree-to-tree:
+        //   main:    A --- B
+        //             \
+        //   feature:   D (HEAD)
+        // The direct diff shows B's file as deleted from main's point of view.
+
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        repo.config().unwrap().set_str("user.name", "Test").unwrap();
+        repo.config().unwrap().set_str("user.email", "test@test.com").unwrap();
+
+        fs::write(temp.path().join("base.txt"), "base content").unwrap();
+        let commit_a_oid = create_commit(&repo, "Initial commit A", None);
+        let commit_a = repo.find_commit(commit_a_oid).unwrap();
+        repo.branch("main", &commit_a, false).unwrap();
+        repo.branch("feature", &commit_a, false).unwrap();
+
+        repo.set_head("refs/heads/main").unwrap();
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force())).unwrap();
+        fs::write(temp.path().join("main_only.txt"), "main only content").unwrap();
+        create_commit(&repo, "Main commit B", Some(&commit_a));
+
+        repo.set_head("refs/heads/feature").unwrap();
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force())).unwrap();
+        fs::remove_file(temp.path().join("main_only.txt")).ok();
+        fs::write(temp.path().join("feature.txt"), "feature content").unwrap();
+        create_commit(&repo, "Feature commit D", Some(&commit_a));
+
+        let merge_base_diff =
+            get_changed_files(temp.path(), &DiffRange::new("main"), &quiet()).unwrap();
+        assert_eq!(merge_base_diff.len(), 1);
+        assert!(merge_base_diff[0].path.ends_with("feature.txt"));
+
+        let range = DiffRange {
+            head_ref: Some("feature"),
+            use_merge_base: false,
+            ..DiffRange::new("main")
+        };
+        let direct_diff = get_changed_files(temp.path(), &range, &quiet()).unwrap();
+        let find_change = |name: &str| direct_diff.iter().find(|cf| cf.path.ends_with(name));
+        assert_eq!(direct_diff.len(), 2);
+        assert_eq!(find_change("feature.txt").unwrap().change_type, ChangeType::Added);
+        assert_eq!(find_change("main_only.txt").unwrap().change_type, ChangeType::Deleted);
+    }
+
+    #[test]
+    fn test_get_changed_files_includes_working_tree_changes() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        repo.config().unwrap().set_str("user.name", "Test").unwrap();
+        repo.config().unwrap().set_str("user.email", "test@test.com").unwrap();
+
+        fs::write(temp.path().join("committed.txt"), "original").unwrap();
+        let commit_a_oid = create_commit(&repo, "Commit A", None);
+        let commit_a = repo.find_commit(commit_a_oid).unwrap();
+        repo.tag_lightweight("base", commit_a.as_object(), false).unwrap();
+
+        // Uncommitted changes: one modified tracked file, one new untracked file
+        fs::write(temp.path().join("committed.txt"), "modified").unwrap();
+        fs::create_dir(temp.path().join("nested")).unwrap();
+        fs::write(temp.path().join("nested/uncommitted.txt"), "new").unwrap();
+
+        let committed_only =
+            get_changed_files(temp.path(), &DiffRange::new("base"), &quiet()).unwrap();
+        assert!(committed_only.is_empty());
+
+        let range = DiffRange {
+            include_working_tree: true,
+            ..DiffRange::new("base")
+        };
+        let changed = get_changed_files(temp.path(), &range, &quiet()).unwrap();
+        let find_change = |name: &str| changed.iter().find(|cf| cf.path.ends_with(name));
+
+        assert_eq!(changed.len(), 2);
+        assert_eq!(find_change("committed.txt").unwrap().change_type, ChangeType::Modified);
+        assert_eq!(find_change("nested/uncommitted.txt").unwrap().change_type, ChangeType::Added);
+    }
+
+    #[test]
+    fn test_get_changed_files_detects_renames() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        repo.config().unwrap().set_str("user.name", "Test").unwrap();
+        repo.config().unwrap().set_str("user.email", "test@test.com").unwrap();
+
+        let content = "export class UserService {\n  load() {\n    return 42;\n  }\n}\n";
+        fs::write(temp.path().join("old-name.ts"), content).unwrap();
+        let commit_a_oid = create_commit(&repo, "Commit A", None);
+        let commit_a = repo.find_commit(commit_a_oid).unwrap();
+        repo.tag_lightweight("base", commit_a.as_object(), false).unwrap();
+
+        fs::rename(
+            temp.path().join("old-name.ts"),
+            temp.path().join("new-name.ts"),
+        )
+        .unwrap();
+        let mut index = repo.index().unwrap();
+        index.remove_path(Path::new("old-name.ts")).unwrap();
+        index.add_path(Path::new("new-name.ts")).unwrap();
+        index.write().unwrap();
+        let tree_id = index.write_tree().unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
+        let sig = Signature::now("Test", "test@test.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Rename", &tree, &[&commit_a])
+            .unwrap();
+
+        let changed = get_changed_files(temp.path(), &DiffRange::new("base"), &quiet()).unwrap();
+
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].change_type, ChangeType::Renamed);
+        assert!(changed[0].path.ends_with("new-name.ts"));
+        assert!(changed[0].old_path.as_ref().unwrap().ends_with("old-name.ts"));
+    }
 }
@@ -11,6 +11,7 @@ pub(crate) struct GraphNode {
     #[serde(rename = "type")]
     pub entity_type: String,
     pub file: String,
+    pub used: bool,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -19,6 +20,27 @@ pub(crate) struct GraphEdge {
     pub target: String,
 }
 
+/// Edge as written by the adjacency output, see `DependencyGraph::to_adjacency_json`.
+#[derive(Debug, Serialize)]
+struct AdjacencyEdge<'a> {
+    from_id: &'a str,
+    to_id: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct AdjacencyGraph<'a> {
+    nodes: &'a [GraphNode],
+    edges: Vec<AdjacencyEdge<'a>>,
+}
+
+/// Part of the graph centered on one entity: everything within `depth` hops of it,
+/// following both dependencies and dependents.
+#[derive(Debug, Clone, Copy)]
+pub struct Neighborhood<'a> {
+    pub entity_id: &'a str,
+    pub depth: usize,
+}
+
 #[derive(Debug, Serialize)]
 pub(crate) struct DependencyGraph {
     pub nodes: Vec<GraphNode>,
@@ -45,6 +67,7 @@ impl DependencyGraph {
                 name: entity.name.clone(),
                 entity_type: entity.entity_type.to_string(),
                 file: entity.file_path.clone(),
+                used: entity.used,
             });
 
             // Create edges for each resolved dependency
@@ -67,6 +90,93 @@ impl DependencyGraph {
         serde_json::to_string_pretty(self)
     }
 
+    /// Serializes the graph as `nodes` plus `edges` with `from_id`/`to_id` pairs,
+    /// for tools that expect a plain adjacency list rather than d3 `links`.
+    pub fn to_adjacency_json(&self) -> Result<String, serde_json::Error> {
+        let edges = self
+            .edges
+            .iter()
+            .map(|edge| AdjacencyEdge {
+                from_id: &edge.source,
+                to_id: &edge.target,
+            })
+            .collect();
+
+        serde_json::to_string_pretty(&AdjacencyGraph {
+            nodes: &self.nodes,
+            edges,
+        })
+    }
+
+    /// Ids of the entities within `depth` hops of `center` in either direction,
+    /// including `center` itself.
+    pub fn neighborhood(&self, center: &str, depth: usize) -> HashSet<String> {
+        let mut neighbors: HashMap<&str, Vec<&str>> = HashMap::new();
+        for edge in &self.edges {
+            neighbors
+                .entry(&edge.source)
+                .or_default()
+                .push(&edge.target);
+            neighbors
+                .entry(&edge.target)
+                .or_default()
+                .push(&edge.source);
+        }
+
+        let mut visited: HashSet<String> = HashSet::from([center.to_string()]);
+        let mut queue: VecDeque<(&str, usize)> = VecDeque::from([(center, 0)]);
+
+        while let Some((current, distance)) = queue.pop_front() {
+            if distance == depth {
+                continue;
+            }
+            for &neighbor in neighbors.get(current).into_iter().flatten() {
+                if visited.insert(neighbor.to_string()) {
+                    queue.push_back((neighbor, distance + 1));
+                }
+            }
+        }
+
+        visited
+    }
+
+    /// Keeps only the nodes of the neighborhood and the edges between them.
+    pub fn retain_neighborhood(&mut self, focus: Neighborhood) {
+        let ids = self.neighborhood(focus.entity_id, focus.depth);
+        self.nodes.retain(|node| ids.contains(&node.id));
+        self.edges
+            .retain(|edge| ids.contains(&edge.source) && ids.contains(&edge.target));
+    }
+
+    /// Renders the transitive dependencies of `root_id` as an indented ASCII tree, one
+    /// entity per line. An entity already expanded elsewhere in the tree is listed
+    /// again with `(*)` but not expanded, which also stops cycles. Returns `None` when
+    /// there is no node with that id.
+    pub fn dependency_tree(&self, root_id: &str) -> Option<String> {
+        let nodes: HashMap<&str, &GraphNode> = self
+            .nodes
+            .iter()
+            .map(|node| (node.id.as_str(), node))
+            .collect();
+        let root = nodes.get(root_id)?;
+
+        let mut dependencies: HashMap<&str, Vec<&GraphNode>> = HashMap::new();
+        for edge in &self.edges {
+            if let Some(&target) = nodes.get(edge.target.as_str()) {
+                dependencies.entry(&edge.source).or_default().push(target);
+            }
+        }
+        for targets in dependencies.values_mut() {
+            targets.sort_by(|a, b| (&a.name, &a.id).cmp(&(&b.name, &b.id)));
+            targets.dedup_by(|a, b| a.id == b.id);
+        }
+
+        let mut output = format!("{}\n", tree_label(root));
+        let mut expanded = HashSet::from([root.id.as_str()]);
+        write_subtree(&mut output, root, &dependencies, &mut expanded, "");
+        Some(output)
+    }
+
     /// Build a reverse index mapping target_id -> Vec<source_ids>
     /// This allows us to find all entities that depend on a given entity.
     pub fn build_consumer_index(&self) -> HashMap<String, Vec<String>> {
@@ -126,6 +236,37 @@ impl DependencyGraph {
     }
 }
 
+fn tree_label(node: &GraphNode) -> String {
+    format!("{} ({}) {}", node.name, node.entity_type, node.file)
+}
+
+/// Appends the dependencies of `node` below it, each prefixed with `prefix` and a
+/// branch, expanding those not in `expanded` yet.
+fn write_subtree<'a>(
+    output: &mut String,
+    node: &GraphNode,
+    dependencies: &HashMap<&str, Vec<&'a GraphNode>>,
+    expanded: &mut HashSet<&'a str>,
+    prefix: &str,
+) {
+    let children = dependencies
+        .get(node.id.as_str())
+        .map_or(&[][..], Vec::as_slice);
+
+    for (index, child) in children.iter().enumerate() {
+        let last = index + 1 == children.len();
+        let branch = if last { "`-- " } else { "|-- " };
+
+        if expanded.insert(child.id.as_str()) {
+            output.push_str(&format!("{}{}{}\n", prefix, branch, tree_label(child)));
+            let child_prefix = format!("{}{}", prefix, if last { "    " } else { "|   " });
+            write_subtree(output, child, dependencies, expanded, &child_prefix);
+        } else {
+            output.push_str(&format!("{}{}{} (*)\n", prefix, branch, tree_label(child)));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -146,6 +287,39 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_dependency_tree_marks_repeated_entities() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+        let import = |name: &str, path: &str| ImportInfo::new(name.to_string(), path.to_string());
+
+        // A -> B, A -> C, B -> C, C -> A (cycle)
+        let a = create_entity(
+            "A",
+            EntityType::Class,
+            "/src/a.ts",
+            vec![import("B", "/src/b.ts"), import("C", "/src/c.ts")],
+        );
+        let b = create_entity("B", EntityType::Class, "/src/b.ts", vec![import("C", "/src/c.ts")]);
+        let c = create_entity("C", EntityType::Function, "/src/c.ts", vec![import("A", "/src/a.ts")]);
+        let a_id = a.id.clone();
+        for entity in [a, b, c] {
+            entities.insert(entity.id.clone(), entity);
+        }
+
+        let graph = DependencyGraph::from_entities(&entities);
+        let tree = graph.dependency_tree(&a_id).unwrap();
+
+        assert_eq!(
+            tree,
+            "A (class) /src/a.ts\n\
+             |-- B (class) /src/b.ts\n\
+             |   `-- C (function) /src/c.ts\n\
+             |       `-- A (class) /src/a.ts (*)\n\
+             `-- C (function) /src/c.ts (*)\n"
+        );
+        assert!(graph.dependency_tree("missing").is_none());
+    }
+
     #[test]
     fn test_empty_entities_produces_empty_graph() {
         let entities: HashMap<String, Entity> = HashMap::new();
@@ -309,6 +483,79 @@ mod tests {
         assert_eq!(parsed["links"].as_array().unwrap().len(), 1);
     }
 
+    #[test]
+    fn test_adjacency_json_has_nodes_and_edges() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+
+        let mut helper = create_entity("Helper", EntityType::Function, "/src/helper.ts", vec![]);
+        helper.used = true;
+        let helper_id = helper.id.clone();
+        entities.insert(helper.id.clone(), helper);
+
+        let import = ImportInfo::new("Helper".to_string(), "/src/helper.ts".to_string());
+        let service = create_entity("Service", EntityType::Class, "/src/service.ts", vec![import.clone()]);
+        let service_id = service.id.clone();
+        entities.insert(service.id.clone(), service);
+
+        let component = create_entity("Component", EntityType::Class, "/src/component.ts", vec![import]);
+        entities.insert(component.id.clone(), component);
+
+        let graph = DependencyGraph::from_entities(&entities);
+        let json = graph.to_adjacency_json().unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        let nodes = parsed["nodes"].as_array().unwrap();
+        let edges = parsed["edges"].as_array().unwrap();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(edges.len(), 2);
+
+        let helper_node = nodes.iter().find(|n| n["id"] == helper_id.as_str()).unwrap();
+        assert_eq!(helper_node["name"], "Helper");
+        assert_eq!(helper_node["type"], "function");
+        assert_eq!(helper_node["file"], "/src/helper.ts");
+        assert_eq!(helper_node["used"], true);
+
+        assert!(edges.iter().any(|e| e["from_id"] == service_id.as_str()));
+        assert!(edges.iter().all(|e| e["from_id"] != helper_id.as_str()));
+        assert!(edges.iter().all(|e| e["to_id"] == helper_id.as_str()));
+    }
+
+    #[test]
+    fn test_depth_one_neighborhood_of_hub_has_only_direct_neighbors() {
+        let mut entities: HashMap<String, Entity> = HashMap::new();
+        let import = |name: &str, path: &str| ImportInfo::new(name.to_string(), path.to_string());
+
+        // Base <- Hub <- Consumer <- App, and Hub -> Base
+        let base = create_entity("Base", EntityType::Class, "/src/base.ts", vec![]);
+        let base_id = base.id.clone();
+        entities.insert(base.id.clone(), base);
+        let hub = create_entity("Hub", EntityType::Class, "/src/hub.ts", vec![import("Base", "/src/base.ts")]);
+        let hub_id = hub.id.clone();
+        entities.insert(hub.id.clone(), hub);
+        let consumer = create_entity("Consumer", EntityType::Class, "/src/consumer.ts", vec![import("Hub", "/src/hub.ts")]);
+        let consumer_id = consumer.id.clone();
+        entities.insert(consumer.id.clone(), consumer);
+        let app = create_entity("App", EntityType::Class, "/src/app.ts", vec![import("Consumer", "/src/consumer.ts")]);
+        let app_id = app.id.clone();
+        entities.insert(app.id.clone(), app);
+
+        let mut graph = DependencyGraph::from_entities(&entities);
+        graph.retain_neighborhood(Neighborhood {
+            entity_id: &hub_id,
+            depth: 1,
+        });
+
+        let mut ids: Vec<&str> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+        ids.sort();
+        let mut expected = vec![base_id.as_str(), hub_id.as_str(), consumer_id.as_str()];
+        expected.sort();
+        assert_eq!(ids, expected);
+        assert_eq!(graph.edges.len(), 2);
+
+        let two_hops = DependencyGraph::from_entities(&entities).neighborhood(&hub_id, 2);
+        assert!(two_hops.contains(&app_id));
+    }
+
     #[test]
     fn test_build_consumer_index() {
         let mut entities: HashMap<String, Entity> = HashMap::new();
@@ -1,24 +1,66 @@
+mod analysis;
+mod cache;
+mod config;
 mod entity;
+mod export;
+mod filesystem;
 mod git;
 mod graph;
+pub mod parse;
 mod parser;
+mod project;
+mod report;
 mod scanner;
+mod style;
+mod template;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
-use anyhow::Result;
-
-use entity::{Entity, EntityType};
-use git::{ChangeType, ChangedFile, get_changed_files};
+use anyhow::{Context, Result};
+
+use analysis::{
+    barrel_files, coupling_matrix, dead_code, entities_named, entity_counts, entity_metrics,
+    find_deletable_files, matches_entry_point, most_depended_upon, orphan_files,
+    propagate_re_export_usage, reachable, threshold_violations,
+};
+use cache::ParseCache;
+pub use config::{Config, find_project_root};
+pub use entity::{Entity, EntityType, ImportInfo};
+use entity::{ReExport, Usage, generate_entity_id, normalize_path};
+pub use export::{OutputFormat, SortKey, SortOrder};
+use export::{
+    export_coupling_csv, export_csv, export_mermaid, export_sarif, sort_entities, write_json_lines,
+};
+use filesystem::{FileSystem, RealFileSystem};
+use git::get_changed_files;
+pub use git::{ChangeType, ChangedFile, DiffRange, latest_tag};
 use graph::DependencyGraph;
-use parser::Parser;
+pub use graph::Neighborhood;
+pub use parser::{EntityTypeRule, PathAlias};
+use parser::{Parser, WHOLE_FILE_IMPORT};
+use project::ProjectIndex;
+use report::Reporter;
+pub use report::Verbosity;
+pub use scanner::ScanError;
 use scanner::Scanner;
+pub use style::ColorChoice;
+use style::Palette;
 
 fn is_test_file(path: &str) -> bool {
-    path.ends_with(".test.ts") || path.ends_with(".spec.ts")
+    [
+        ".test.ts",
+        ".spec.ts",
+        ".test.mts",
+        ".spec.mts",
+        ".test.cts",
+        ".spec.cts",
+    ]
+    .iter()
+    .any(|suffix| path.ends_with(suffix))
 }
 
 fn find_test_files_in_directories(directories: &HashSet<String>) -> Vec<String> {
@@ -33,12 +75,11 @@ fn find_test_files_in_directories(directories: &HashSet<String>) -> Vec<String>
         if let Ok(entries) = fs::read_dir(dir) {
             for entry in entries.flatten() {
                 let path = entry.path();
-                if path.is_file() {
-                    if let Some(path_str) = path.to_str() {
-                        if is_test_file(path_str) {
-                            test_files.insert(path_str.to_string());
-                        }
-                    }
+                if path.is_file()
+                    && let Some(path_str) = path.to_str()
+                    && is_test_file(path_str)
+                {
+                    test_files.insert(path_str.to_string());
                 }
             }
         }
@@ -49,135 +90,831 @@ fn find_test_files_in_directories(directories: &HashSet<String>) -> Vec<String>
     sorted
 }
 
+/// Options that affect how files are discovered and parsed, shared by all commands.
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    /// Import path aliases, tried in order before the built-in `@awork/` alias
+    pub aliases: Vec<PathAlias>,
+    /// Package scopes (e.g. `@myorg`) that belong to the project and are never
+    /// recorded as external packages
+    pub internal_scopes: Vec<String>,
+    /// Record imports of external packages as `external:<package>` dependencies
+    pub include_external: bool,
+    /// Also record top-level declarations that are not exported
+    pub include_internal: bool,
+    /// Whether progress messages are printed
+    pub verbosity: Verbosity,
+    /// Files to analyze instead of scanning the project directories, relative to
+    /// the project root unless absolute
+    pub files: Vec<String>,
+    /// How many directory levels below each scanned directory are searched, unlimited when `None`
+    pub max_depth: Option<usize>,
+    /// Descend into symlinked directories, which are skipped otherwise
+    pub follow_symlinks: bool,
+    /// How many threads walk the scanned directories; one per core (at most eight)
+    /// when `None`
+    pub walk_threads: Option<usize>,
+    /// Directories to scan, relative to the project root; `apps/web`, `apps/mobile`
+    /// and `libs` when empty
+    pub dirs: Vec<String>,
+    /// Directory names to skip in addition to the built-in ones
+    pub skip_dirs: Vec<String>,
+    /// File name suffixes to skip in addition to the built-in ones
+    pub skip_file_suffixes: Vec<String>,
+    /// Extensions (without the dot) of the scanned files, `ts`, `tsx`, `mts` and
+    /// `cts` when empty
+    pub extensions: Vec<String>,
+    /// Rules classifying declarations by their line before the built-in types
+    pub entity_type_rules: Vec<EntityTypeRule>,
+    /// Entry point patterns (file name, path suffix or glob) the reachability of
+    /// the analysis commands starts from
+    pub entry_points: Vec<String>,
+    /// Glob patterns of files and directories to leave out of the scan
+    pub excludes: Vec<String>,
+    /// Scan test helpers and doubles (`mocks` directories, `mock.ts` files, ...),
+    /// which are skipped otherwise
+    pub include_tests: bool,
+    /// Whether the text output of the listing commands is colorized
+    pub color: ColorChoice,
+    /// Count Angular components whose selector appears in another component's
+    /// template (`templateUrl` file or inline `template`) as used
+    pub scan_templates: bool,
+}
+
+impl ScanOptions {
+    /// Fills the options that were left empty with the values of `config`, so
+    /// options given on the command line take precedence over the config file.
+    fn with_config(&self, config: Config) -> ScanOptions {
+        fn pick<T: Clone>(given: &[T], configured: Vec<T>) -> Vec<T> {
+            if given.is_empty() {
+                configured
+            } else {
+                given.to_vec()
+            }
+        }
+
+        ScanOptions {
+            aliases: pick(&self.aliases, config.aliases),
+            dirs: pick(&self.dirs, config.dirs),
+            skip_dirs: pick(&self.skip_dirs, config.skip_dirs),
+            skip_file_suffixes: pick(&self.skip_file_suffixes, config.skip_file_suffixes),
+            extensions: pick(&self.extensions, config.extensions),
+            entity_type_rules: pick(&self.entity_type_rules, config.entity_types),
+            entry_points: pick(&self.entry_points, config.entry_points),
+            ..self.clone()
+        }
+    }
+}
+
+/// Directories scanned when neither the options nor the config file name any.
+const DEFAULT_SCAN_DIRECTORIES: &[&str] = &["apps/web", "apps/mobile", "libs"];
+
 struct ScanResult {
     entities: HashMap<String, Entity>,
+    /// Imports of every parsed file, including files that export nothing
+    file_imports: HashMap<String, Vec<ImportInfo>>,
+    /// Re-exports (`export ... from`) of every parsed file that has any
+    file_re_exports: HashMap<String, Vec<ReExport>>,
+    /// Directories and files that were skipped because they could not be read or parsed
+    errors: Vec<ScanError>,
+}
+
+/// Scans and parses the project. `verbose` allows progress messages, which commands
+/// with machine-readable output turn off; `--quiet` turns them off everywhere.
+fn scan_and_parse_files(
+    root_path: &Path,
+    options: &ScanOptions,
+    verbose: bool,
+) -> Result<ScanResult> {
+    let verbosity = if verbose {
+        options.verbosity
+    } else {
+        Verbosity::Quiet
+    };
+    scan_and_parse_files_with(
+        &RealFileSystem,
+        root_path,
+        options,
+        &mut Reporter::stdout(verbosity),
+    )
+}
+
+/// Like `scan_and_parse_files`, also returning the options with the config file
+/// applied for the commands that read more settings than the scan does.
+fn scan_and_parse_configured(
+    root_path: &Path,
+    options: &ScanOptions,
+    verbose: bool,
+) -> Result<(ScanOptions, ScanResult)> {
+    let options = options.with_config(Config::load(root_path)?);
+    let verbosity = if verbose {
+        options.verbosity
+    } else {
+        Verbosity::Quiet
+    };
+    let result = parse_project(
+        &RealFileSystem,
+        root_path,
+        &options,
+        &mut Reporter::stdout(verbosity),
+    )?;
+    Ok((options, result))
+}
+
+/// Scans and parses the project as read from `file_system`.
+fn scan_and_parse_files_with(
+    file_system: &dyn FileSystem,
+    root_path: &Path,
+    options: &ScanOptions,
+    reporter: &mut Reporter,
+) -> Result<ScanResult> {
+    let options = &options.with_config(Config::load_from(file_system, root_path)?);
+    parse_project(file_system, root_path, options, reporter)
+}
+
+/// Scans and parses the project with `options` that include the config file already.
+fn parse_project(
+    file_system: &dyn FileSystem,
+    root_path: &Path,
+    options: &ScanOptions,
+    reporter: &mut Reporter,
+) -> Result<ScanResult> {
+    if !options.files.is_empty() {
+        return analyze_files(file_system, root_path, options, &options.files, reporter);
+    }
+
+    let mut errors: Vec<ScanError> = Vec::new();
+    let all_files = scan_files(file_system, root_path, options, &mut errors, reporter);
+
+    parse_files(
+        file_system,
+        root_path,
+        options,
+        &all_files,
+        errors,
+        reporter,
+        true,
+    )
 }
 
-fn scan_and_parse_files(root_path: &Path, verbose: bool) -> Result<ScanResult> {
-    let subdirs = ["apps/web", "apps/mobile", "libs"];
+/// Lists the files found in the scanned directories, each once, leaving out the
+/// skipped ones. Directories that cannot be read are reported and recorded in `errors`.
+fn scan_files(
+    file_system: &dyn FileSystem,
+    root_path: &Path,
+    options: &ScanOptions,
+    errors: &mut Vec<ScanError>,
+    reporter: &mut Reporter,
+) -> Vec<String> {
+    let subdirs = scan_directories(options);
     let mut all_files = Vec::new();
 
-    let scanner = Scanner::new();
+    let scanner = create_scanner(options)
+        .with_file_system(file_system)
+        .with_excludes(root_path, &options.excludes)
+        .with_max_depth(options.max_depth)
+        .with_follow_symlinks(options.follow_symlinks)
+        .with_walk_threads(options.walk_threads);
 
     for subdir in subdirs {
         let full_path = root_path.join(subdir);
 
-        if !full_path.exists() {
-            if verbose {
-                eprintln!(
-                    "Warning: Directory {:?} does not exist, skipping...",
-                    full_path
-                );
-            }
+        if !file_system.exists(&full_path) {
+            reporter.info(format!(
+                "Directory {:?} does not exist, skipping...",
+                full_path
+            ));
             continue;
         }
 
-        if verbose {
-            println!("Scanning directory: {:?}", full_path);
-        }
+        reporter.info(format!("Scanning directory: {:?}", full_path));
 
-        match scanner.scan(&full_path) {
-            Ok(mut files) => {
-                if verbose {
-                    println!("  Found {} TypeScript files", files.len());
-                }
-                all_files.append(&mut files);
+        let mut files = scanner.scan(&full_path, errors);
+        reporter.info(format!("  Found {} TypeScript files", files.len()));
+        all_files.append(&mut files);
+    }
+
+    // Overlapping directories (`libs` and `libs/shared`) find the same files twice
+    let found = all_files.len();
+    let mut seen = HashSet::new();
+    all_files.retain(|file| {
+        let path = Path::new(file);
+        seen.insert(
+            file_system
+                .canonicalize(path)
+                .unwrap_or_else(|_| path.to_path_buf()),
+        )
+    });
+    if all_files.len() < found {
+        reporter.info(format!(
+            "Skipping {} files found in more than one scanned directory",
+            found - all_files.len()
+        ));
+    }
+
+    for error in errors.iter() {
+        reporter.warn(format!(
+            "Could not read directory {}: {}",
+            error.path, error.message
+        ));
+    }
+
+    all_files
+}
+
+/// Prints the files a scan parses, one per line, without parsing them: the files
+/// given with `--file`, or else the ones found in the scanned directories once
+/// skipped directories and suffixes, excludes and test helpers are left out.
+/// Returns the number of files.
+pub fn list_files(root_path: &Path, options: &ScanOptions, out: &mut dyn Write) -> Result<usize> {
+    let options = &options.with_config(Config::load(root_path)?);
+    let mut reporter = Reporter::stdout(Verbosity::Quiet);
+    let mut errors: Vec<ScanError> = Vec::new();
+
+    let files = if options.files.is_empty() {
+        scan_files(
+            &RealFileSystem,
+            root_path,
+            options,
+            &mut errors,
+            &mut reporter,
+        )
+    } else {
+        select_files(
+            &RealFileSystem,
+            root_path,
+            options,
+            &options.files,
+            &mut errors,
+            &mut reporter,
+        )
+    };
+
+    for file in &files {
+        writeln!(out, "{}", file)?;
+    }
+    out.flush()?;
+
+    Ok(files.len())
+}
+
+/// Parses only the given files instead of scanning the project directories. Relative
+/// paths are taken from the project root, and imports are still resolved against it.
+/// Files that do not exist are recorded as errors, other non-TypeScript files are ignored.
+fn analyze_files(
+    file_system: &dyn FileSystem,
+    root_path: &Path,
+    options: &ScanOptions,
+    files: &[String],
+    reporter: &mut Reporter,
+) -> Result<ScanResult> {
+    let mut errors: Vec<ScanError> = Vec::new();
+    let selected_files = select_files(
+        file_system,
+        root_path,
+        options,
+        files,
+        &mut errors,
+        reporter,
+    );
+
+    // Cached results of the files left out must survive a partial analysis
+    parse_files(
+        file_system,
+        root_path,
+        options,
+        &selected_files,
+        errors,
+        reporter,
+        false,
+    )
+}
+
+/// Resolves the files given with `--file` against the project root, keeping the
+/// TypeScript ones, sorted and without duplicates. Missing files are recorded in `errors`.
+fn select_files(
+    file_system: &dyn FileSystem,
+    root_path: &Path,
+    options: &ScanOptions,
+    files: &[String],
+    errors: &mut Vec<ScanError>,
+    reporter: &mut Reporter,
+) -> Vec<String> {
+    let scanner = create_scanner(options);
+    let mut selected_files = Vec::new();
+
+    for file in files {
+        let path = root_path.join(file);
+        match file_system.canonicalize(&path) {
+            Ok(path) if scanner.is_scannable(&path) => {
+                selected_files.push(normalize_path(&path));
             }
+            Ok(_) => {}
             Err(e) => {
-                if verbose {
-                    eprintln!("Warning: Could not read directory {:?}: {}", full_path, e);
-                }
+                reporter.warn(format!("Could not read file {}: {}", path.display(), e));
+                errors.push(ScanError::new(&path, e));
+            }
+        }
+    }
+
+    selected_files.sort();
+    selected_files.dedup();
+    selected_files
+}
+
+/// Reads the project's files one at a time as the scan finds them and hands each
+/// path and content to `visit`. Unlike `scan_and_parse_files`, no file contents or
+/// parse results are kept, so memory grows only with the set of visited paths that
+/// deduplicates overlapping directories; this serves the commands that look at one
+/// file at a time on very large repositories. Files come in walk order, sorted
+/// within each directory, on the calling thread whatever the walk threads option.
+/// Unreadable files are skipped with a warning.
+/// `options` are expected to include the config file already.
+fn stream_files(
+    file_system: &dyn FileSystem,
+    root_path: &Path,
+    options: &ScanOptions,
+    mut visit: impl FnMut(&str, &str),
+) -> Result<()> {
+    let mut reporter = Reporter::stdout(Verbosity::Quiet);
+    let mut errors: Vec<ScanError> = Vec::new();
+    let selected_files = select_files(
+        file_system,
+        root_path,
+        options,
+        &options.files,
+        &mut errors,
+        &mut reporter,
+    );
+    let mut found = 0;
+    let mut seen = HashSet::new();
+
+    let mut read_and_visit = |file: String| {
+        // Overlapping directories (`libs` and `libs/shared`) find the same files twice
+        let path = Path::new(&file);
+        if !seen.insert(
+            file_system
+                .canonicalize(path)
+                .unwrap_or_else(|_| path.to_path_buf()),
+        ) {
+            return;
+        }
+        found += 1;
+        match parser::read_source(file_system, &file) {
+            Ok(content) => visit(&file, content.strip_prefix('\u{feff}').unwrap_or(&content)),
+            Err(e) => reporter.warn(format!("Could not parse file {}: {}", file, e)),
+        }
+    };
+
+    if !options.files.is_empty() {
+        selected_files.into_iter().for_each(&mut read_and_visit);
+    } else {
+        let scanner = create_scanner(options)
+            .with_file_system(file_system)
+            .with_excludes(root_path, &options.excludes)
+            .with_max_depth(options.max_depth)
+            .with_follow_symlinks(options.follow_symlinks);
+        for subdir in scan_directories(options) {
+            let full_path = root_path.join(subdir);
+            if file_system.exists(&full_path) {
+                scanner.for_each_file(&full_path, &mut errors, &mut read_and_visit);
             }
         }
+
+        for error in &errors {
+            reporter.warn(format!(
+                "Could not read directory {}: {}",
+                error.path, error.message
+            ));
+        }
+    }
+
+    if found == 0 {
+        anyhow::bail!("No TypeScript files found in {}", root_path.display());
     }
+    Ok(())
+}
 
+/// Parses the files into the entity map. With `full_scan`, cached results of files
+/// that are not in `all_files` are dropped.
+fn parse_files(
+    file_system: &dyn FileSystem,
+    root_path: &Path,
+    options: &ScanOptions,
+    all_files: &[String],
+    mut errors: Vec<ScanError>,
+    reporter: &mut Reporter,
+    full_scan: bool,
+) -> Result<ScanResult> {
     if all_files.is_empty() {
         anyhow::bail!("No TypeScript files found in {}", root_path.display());
     }
 
     let mut entities_map: HashMap<String, Entity> = HashMap::new();
+    let mut file_imports: HashMap<String, Vec<ImportInfo>> = HashMap::new();
+    let mut file_re_exports: HashMap<String, Vec<ReExport>> = HashMap::new();
+    // Files used as a whole (e.g. by bare dynamic imports), with the importing file
+    let mut whole_file_imports: Vec<(String, &String)> = Vec::new();
 
-    if verbose {
-        println!("Processing {} TypeScript files...\n", all_files.len());
-    }
+    reporter.info(format!(
+        "Processing {} TypeScript files...\n",
+        all_files.len()
+    ));
 
-    let parser = Parser::new(root_path);
+    let parser = create_parser(root_path, options).with_file_system(file_system);
+    let mut cache = ParseCache::load(file_system, root_path, &parser.settings_fingerprint());
 
-    for file in &all_files {
-        match parser.parse(file) {
+    for file in all_files {
+        match cache.parse(&parser, file) {
             Ok(result) => {
+                // Imports from test files are tracked separately from real usage
+                let from_test = is_test_file(file);
                 for import in &result.imports {
-                    if let Some(existing) = entities_map.get_mut(&import.id) {
-                        existing.used = true;
+                    if import.name == WHOLE_FILE_IMPORT {
+                        whole_file_imports.push((import.path.clone(), file));
+                        continue;
+                    }
+                    // Assets such as `./icon.svg?raw` stay dependencies but declare nothing
+                    if parser.is_asset(&import.path) {
+                        continue;
+                    }
+
+                    let imported_entity =
+                        entities_map.entry(import.id.clone()).or_insert_with(|| {
+                            Entity::new(
+                                import.name.clone(),
+                                EntityType::Unknown,
+                                import.path.clone(),
+                                Rc::new(Vec::new()),
+                            )
+                        });
+                    if from_test {
+                        imported_entity.used_in_tests = true;
                     } else {
-                        let mut imported_entity = Entity::new(
-                            import.name.clone(),
-                            EntityType::Unknown,
-                            import.path.clone(),
-                            Rc::new(Vec::new()),
-                        );
-                        imported_entity.used = true;
-                        entities_map.insert(import.id.clone(), imported_entity);
+                        imported_entity.mark_used(Usage::Import(file.clone()));
                     }
                 }
 
                 for entity in result.entities {
                     if let Some(existing) = entities_map.get_mut(&entity.id) {
                         existing.entity_type = entity.entity_type;
+                        existing.line = entity.line;
                         existing.deps = entity.deps;
+                        existing.bases = entity.bases;
+                        existing.used_locally = entity.used_locally;
+                        existing.exported = entity.exported;
+                        existing.content_hash = entity.content_hash;
+                        if let Some(usage) = entity.used_by {
+                            existing.mark_used(usage);
+                        }
                     } else {
                         entities_map.insert(entity.id.clone(), entity);
                     }
                 }
+
+                if !result.re_exports.is_empty() {
+                    file_re_exports.insert(file.clone(), result.re_exports);
+                }
+                file_imports.insert(file.clone(), result.imports);
             }
             Err(e) => {
-                if verbose {
-                    eprintln!("Warning: Could not parse file {}: {}", file, e);
+                reporter.warn(format!("Could not parse file {}: {}", file, e));
+                errors.push(ScanError::new(Path::new(file), e));
+            }
+        }
+    }
+
+    for (path, importer) in &whole_file_imports {
+        for entity in entities_map.values_mut() {
+            if &entity.file_path == path {
+                if is_test_file(importer) {
+                    entity.used_in_tests = true;
+                } else {
+                    entity.mark_used(Usage::Import(importer.to_string()));
                 }
             }
         }
     }
 
+    propagate_re_export_usage(&mut entities_map, &file_re_exports);
+
+    if options.scan_templates {
+        mark_template_usage(file_system, &mut entities_map, file_imports.keys());
+    }
+
+    let mut projects = ProjectIndex::new(file_system, root_path);
+    for entity in entities_map.values_mut() {
+        entity.project = projects.project_of(&entity.file_path);
+    }
+
+    if full_scan {
+        cache.retain_files(all_files);
+    }
+    if let Err(e) = cache.save() {
+        reporter.warn(format!("Could not save parse cache: {}", e));
+    }
+
     Ok(ScanResult {
         entities: entities_map,
+        file_imports,
+        file_re_exports,
+        errors,
     })
 }
 
-fn print_entity(entity: &Entity, show_id: bool, show_deps: bool) {
+/// Marks the Angular components declared in `files` as used when their selector
+/// appears in the template of another component. When several components use it,
+/// the first one by file path is recorded.
+fn mark_template_usage<'a>(
+    file_system: &dyn FileSystem,
+    entities: &mut HashMap<String, Entity>,
+    files: impl Iterator<Item = &'a String>,
+) {
+    let mut files: Vec<&String> = files.collect();
+    files.sort();
+
+    let mut declared = Vec::new();
+    let mut templates = Vec::new();
+    for file in files {
+        let Ok(content) = file_system.read_to_string(Path::new(file)) else {
+            continue;
+        };
+        for component in template::components(&content, file) {
+            let id = generate_entity_id(file, &component.class_name);
+            let template = component
+                .template_path
+                .as_ref()
+                .and_then(|path| file_system.read_to_string(path).ok())
+                .into_iter()
+                .chain(component.inline_template);
+            let owner = Usage::Template {
+                component: component.class_name.clone(),
+                file: file.clone(),
+            };
+            templates.extend(template.map(|template| (id.clone(), owner.clone(), template)));
+            declared.push((id, component.selectors));
+        }
+    }
+
+    for (id, selectors) in declared {
+        let owner = templates.iter().find(|(owner_id, _, template)| {
+            *owner_id != id
+                && selectors
+                    .iter()
+                    .any(|selector| selector.is_used_in(template))
+        });
+        if let Some((_, owner, _)) = owner
+            && let Some(entity) = entities.get_mut(&id)
+        {
+            entity.mark_used(owner.clone());
+        }
+    }
+}
+
+/// The directories scanned with `options`, relative to the project root.
+fn scan_directories(options: &ScanOptions) -> Vec<&str> {
+    if options.dirs.is_empty() {
+        DEFAULT_SCAN_DIRECTORIES.to_vec()
+    } else {
+        options.dirs.iter().map(String::as_str).collect()
+    }
+}
+
+fn create_parser<'a>(root_path: &'a Path, options: &'a ScanOptions) -> Parser<'a> {
+    Parser::new(root_path)
+        .with_aliases(&options.aliases)
+        .with_internal_scopes(&options.internal_scopes)
+        .with_external_packages(options.include_external)
+        .with_extensions(&options.extensions)
+        .with_internal_declarations(options.include_internal)
+        .with_entity_type_rules(&options.entity_type_rules)
+}
+
+fn create_scanner(options: &ScanOptions) -> Scanner<'static> {
+    Scanner::new()
+        .with_skip_directories(&options.skip_dirs)
+        .with_skip_file_suffixes(&options.skip_file_suffixes)
+        .with_extensions(&options.extensions)
+        .with_tests(options.include_tests)
+}
+
+/// Returns true when the entity matches the type filter; an empty filter matches all.
+fn matches_type_filter(entity: &Entity, types: &[EntityType]) -> bool {
+    types.is_empty() || types.contains(&entity.entity_type)
+}
+
+/// Returns true when the entity belongs to the project; no project matches all.
+fn matches_project(entity: &Entity, project: Option<&str>) -> bool {
+    project.is_none_or(|project| entity.project.as_deref() == Some(project))
+}
+
+fn check_project(entities: &HashMap<String, Entity>, project: Option<&str>) -> Result<()> {
+    if let Some(project) = project
+        && !entities
+            .values()
+            .any(|entity| entity.project.as_deref() == Some(project))
+    {
+        anyhow::bail!("Project not found: {}", project);
+    }
+    Ok(())
+}
+
+/// Renders entities grouped by file: each run of entities from the same file gets
+/// a header with the path and count, followed by one indented line per entity.
+fn format_unused_by_file(entities: &[&Entity], palette: Palette) -> String {
+    let mut output = String::new();
+
+    for (index, group) in entities
+        .chunk_by(|a, b| a.file_path == b.file_path)
+        .enumerate()
+    {
+        if index > 0 {
+            output.push('\n');
+        }
+        output.push_str(&format!(
+            "{} {}\n",
+            palette.path(&group[0].file_path),
+            palette.unused(format!("({} unused)", group.len()))
+        ));
+
+        for entity in group {
+            output.push_str(&format!(
+                "  {} {}",
+                palette.entity_type(&entity.entity_type),
+                entity.name
+            ));
+            if entity.line > 0 {
+                output.push_str(&format!(" (line {})", entity.line));
+            }
+            if !entity.exported {
+                output.push_str(" [not exported]");
+            }
+            if entity.is_test_only() {
+                output.push_str(" [only used by tests]");
+            }
+            output.push('\n');
+        }
+    }
+
+    output
+}
+
+/// Writes an entity as `Key: value` lines; the name of an unused entity is
+/// highlighted by `palette`.
+fn write_entity(
+    out: &mut dyn Write,
+    entity: &Entity,
+    show_id: bool,
+    show_deps: bool,
+    palette: Palette,
+) -> io::Result<()> {
     if show_id {
-        println!("ID: {}", entity.id);
+        writeln!(out, "ID: {}", entity.id)?;
+    }
+    if entity.used || entity.is_placeholder() {
+        writeln!(out, "Name: {}", entity.name)?;
+    } else {
+        writeln!(out, "Name: {}", palette.unused(&entity.name))?;
+    }
+    writeln!(out, "Type: {}", palette.entity_type(&entity.entity_type))?;
+    writeln!(out, "File: {}", palette.path(entity.location()))?;
+    if !entity.exported {
+        writeln!(out, "Note: not exported")?;
+    }
+    if entity.is_test_only() {
+        writeln!(out, "Note: only used by tests")?;
     }
-    println!("Name: {}", entity.name);
-    println!("Type: {}", entity.entity_type);
-    println!("File: {}", entity.file_path);
     if show_deps {
-        println!("Deps: {:?}", entity.deps);
+        writeln!(out, "Deps: {:?}", entity.deps)?;
     }
-    println!("---");
+    writeln!(out, "---")
+}
+
+/// Opens the destination of a command's output: the file at `path`, creating its
+/// parent directories, or stdout when there is no path.
+pub fn output_writer(path: Option<&Path>) -> Result<Box<dyn Write>> {
+    let Some(path) = path else {
+        return Ok(Box::new(io::stdout().lock()));
+    };
+
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Unable to create directory {}", parent.display()))?;
+    }
+    let file = fs::File::create(path)
+        .with_context(|| format!("Unable to create output file {}", path.display()))?;
+    Ok(Box::new(BufWriter::new(file)))
 }
 
-pub fn query_all(root_path: &Path) -> Result<()> {
-    let result = scan_and_parse_files(root_path, true)?;
+/// Writes already rendered output to the file at `path` or to stdout, see
+/// [`output_writer`].
+pub fn write_output(path: Option<&Path>, content: &str) -> Result<()> {
+    let mut out = output_writer(path)?;
+    out.write_all(content.as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Selects which entities the `query-all` command lists.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EntityFilter<'a> {
+    /// Only list entities of these types; all types when empty
+    pub types: &'a [EntityType],
+    /// Leave out placeholders for imports that do not resolve to a declaration
+    pub exclude_placeholders: bool,
+    /// Only list entities declared in files changed in this range
+    pub changed_since: Option<&'a DiffRange<'a>>,
+    /// Only list entities of this Nx project
+    pub project: Option<&'a str>,
+}
 
-    println!("Found {} entities:\n", result.entities.len());
+/// Lists the scanned entities, writing them to `out`.
+pub fn query_all(
+    root_path: &Path,
+    options: &ScanOptions,
+    filter: EntityFilter,
+    format: OutputFormat,
+    order: SortOrder,
+    out: &mut dyn Write,
+) -> Result<()> {
+    if format == OutputFormat::Sarif {
+        anyhow::bail!("SARIF output is only supported by the unused command");
+    }
+    let EntityFilter {
+        types,
+        exclude_placeholders,
+        changed_since,
+        project,
+    } = filter;
+
+    let changed_paths = match changed_since {
+        Some(range) => Some(changed_source_paths(root_path, options, range)?),
+        None => None,
+    };
+
+    let mut result = scan_and_parse_files(root_path, options, format == OutputFormat::Text)?;
+    let palette = Palette::new(options.color);
+    check_project(&result.entities, project)?;
+
+    let counts = entity_counts(&result.entities);
+    // The whole project is parsed so dependencies resolve, but with `changed_since`
+    // only the entities declared in changed files are listed
+    result.entities.retain(|_, entity| {
+        matches_type_filter(entity, types)
+            && matches_project(entity, project)
+            && !(exclude_placeholders && entity.is_placeholder())
+            && changed_paths
+                .as_ref()
+                .is_none_or(|paths| paths.contains(&entity.file_path))
+    });
 
     let mut sorted_entities: Vec<_> = result.entities.values().collect();
-    sorted_entities.sort_by(|a, b| a.id.cmp(&b.id));
+    sort_entities(&mut sorted_entities, order);
+
+    if format == OutputFormat::Csv {
+        write!(out, "{}", export_csv(&sorted_entities))?;
+        out.flush()?;
+        return Ok(());
+    }
+
+    if format == OutputFormat::Json {
+        writeln!(out, "{}", serde_json::to_string_pretty(&sorted_entities)?)?;
+        out.flush()?;
+        return Ok(());
+    }
+
+    if format == OutputFormat::JsonLines {
+        write_json_lines(out, &sorted_entities)?;
+        return Ok(());
+    }
+
+    writeln!(out, "Found {} entities:\n", result.entities.len())?;
 
     for entity in sorted_entities {
-        print_entity(entity, true, true);
+        write_entity(out, entity, true, true, palette)?;
     }
 
-    println!("\nTotal entities in map: {}", result.entities.len());
+    writeln!(
+        out,
+        "\nTotal entities in map: {} ({} defined, {} unresolved import placeholders)",
+        counts.total(),
+        counts.defined,
+        counts.placeholders
+    )?;
+    out.flush()?;
 
     Ok(())
 }
 
-pub fn query(root_path: &Path, query: &str) -> Result<()> {
-    let result = scan_and_parse_files(root_path, false)?;
+pub fn query(root_path: &Path, options: &ScanOptions, query: &str) -> Result<()> {
+    let result = scan_and_parse_files(root_path, options, false)?;
+    let palette = Palette::new(options.color);
 
     if let Some(entity) = result.entities.get(query) {
-        print_entity(entity, true, true);
+        write_entity(&mut io::stdout().lock(), entity, true, true, palette)?;
     } else {
         println!("Entity not found: {}", query);
     }
@@ -185,599 +922,4150 @@ pub fn query(root_path: &Path, query: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn unused(root_path: &Path) -> Result<()> {
-    let result = scan_and_parse_files(root_path, true)?;
+/// Prints every declared entity called `name`, which may be declared in several files.
+pub fn query_by_name(root_path: &Path, options: &ScanOptions, name: &str) -> Result<()> {
+    let result = scan_and_parse_files(root_path, options, false)?;
+    let palette = Palette::new(options.color);
+    let entities = entities_named(&result.entities, name);
+
+    let out = &mut io::stdout().lock();
+    match entities.as_slice() {
+        [] => writeln!(out, "Entity not found: {}", name)?,
+        [entity] => write_entity(out, entity, true, true, palette)?,
+        _ => {
+            writeln!(out, "Found {} entities named {}:\n", entities.len(), name)?;
+            for entity in &entities {
+                write_entity(out, entity, true, true, palette)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Computes the ids of the entities reachable from the files matching `entry_points`,
+/// or `None` without entry points. Fails when no parsed file matches them.
+fn reachable_from_entry_points(
+    result: &ScanResult,
+    entry_points: &[String],
+    max_depth: Option<usize>,
+) -> Result<Option<HashSet<String>>> {
+    if entry_points.is_empty() {
+        return Ok(None);
+    }
+
+    let entry_files: Vec<String> = result
+        .file_imports
+        .keys()
+        .filter(|file| {
+            entry_points
+                .iter()
+                .any(|pattern| matches_entry_point(file, pattern))
+        })
+        .cloned()
+        .collect();
+
+    if entry_files.is_empty() {
+        anyhow::bail!(
+            "No files match the entry points: {}",
+            entry_points.join(", ")
+        );
+    }
+
+    Ok(Some(reachable(
+        &result.entities,
+        &result.file_imports,
+        &result.file_re_exports,
+        &entry_files,
+        max_depth,
+    )))
+}
+
+/// Selects which entities the `unused` command reports.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnusedQuery<'a> {
+    /// Only report entities of these types; all types when empty
+    pub types: &'a [EntityType],
+    /// Leave out entities that are only used by test files
+    pub exclude_test_only: bool,
+    /// With entry points, only follow imports this many levels deep; entities beyond
+    /// are not reached and so reported as unused
+    pub max_propagation_depth: Option<usize>,
+    /// Only report entities of this Nx project; usage from other projects still counts
+    pub project: Option<&'a str>,
+}
+
+/// Lists unused entities to `out` and returns how many were reported. Text output
+/// groups them by file unless `flat` is set. With entry points in `options` or the
+/// config file, entities not reachable from a matching file are unused regardless
+/// of their used flag.
+pub fn unused(
+    root_path: &Path,
+    options: &ScanOptions,
+    query: UnusedQuery,
+    format: OutputFormat,
+    order: SortOrder,
+    flat: bool,
+    out: &mut dyn Write,
+) -> Result<usize> {
+    let (options, result) =
+        scan_and_parse_configured(root_path, options, format == OutputFormat::Text)?;
+    let options = &options;
+    let palette = Palette::new(options.color);
+    let entry_points = &options.entry_points;
+    let UnusedQuery {
+        types,
+        exclude_test_only,
+        max_propagation_depth,
+        project,
+    } = query;
+    check_project(&result.entities, project)?;
+
+    // With entry points, usage means being reachable from them rather than the used flag
+    if entry_points.is_empty() && max_propagation_depth.is_some() {
+        Reporter::stdout(options.verbosity)
+            .warn("--max-propagation-depth only applies with entry points");
+    }
+    let reachable_ids = reachable_from_entry_points(&result, entry_points, max_propagation_depth)?;
 
     let mut unused_entities: Vec<_> = result
         .entities
         .values()
-        .filter(|e| !e.used && !matches!(e.entity_type, EntityType::Unknown))
+        .filter(|e| !matches!(e.entity_type, EntityType::Unknown))
+        .filter(|e| matches_type_filter(e, types))
+        .filter(|e| matches_project(e, project))
+        .filter(|e| !(exclude_test_only && e.used_in_tests))
+        .filter(|e| match &reachable_ids {
+            Some(ids) => !ids.contains(&e.id),
+            None => !e.used,
+        })
         .collect();
 
-    unused_entities.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+    sort_entities(&mut unused_entities, order);
+
+    match format {
+        OutputFormat::Text => {
+            writeln!(out, "Found {} unused entities:\n", unused_entities.len())?;
+
+            if flat {
+                for entity in &unused_entities {
+                    write_entity(out, entity, false, false, palette)?;
+                }
+            } else {
+                write!(out, "{}", format_unused_by_file(&unused_entities, palette))?;
+            }
+
+            writeln!(
+                out,
+                "\nTotal: {} unused out of {} entities",
+                unused_entities.len(),
+                result.entities.len()
+            )?;
+        }
+        OutputFormat::Csv => write!(out, "{}", export_csv(&unused_entities))?,
+        OutputFormat::Json => {
+            writeln!(out, "{}", serde_json::to_string_pretty(&unused_entities)?)?;
+        }
+        OutputFormat::JsonLines => write_json_lines(out, &unused_entities)?,
+        OutputFormat::Sarif => {
+            writeln!(out, "{}", export_sarif(&unused_entities, root_path))?;
+        }
+    }
+    out.flush()?;
+
+    Ok(unused_entities.len())
+}
+
+/// Lists unused entities and what only they use. With entry points in `options` or
+/// the config file, the search starts from the entities not reachable from them.
+pub fn dead(root_path: &Path, options: &ScanOptions) -> Result<()> {
+    let (options, result) = scan_and_parse_configured(root_path, options, true)?;
+    let palette = Palette::new(options.color);
+
+    let reachable_ids = reachable_from_entry_points(&result, &options.entry_points, None)?;
+    let dead_entities = dead_code(
+        &result.entities,
+        reachable_ids.as_ref(),
+        &options.entry_points,
+    );
 
-    println!("Found {} unused entities:\n", unused_entities.len());
+    println!("Found {} dead entities:\n", dead_entities.len());
 
-    for entity in &unused_entities {
-        print_entity(entity, false, false);
+    for entity in &dead_entities {
+        write_entity(&mut io::stdout().lock(), entity, false, false, palette)?;
     }
 
     println!(
-        "\nTotal: {} unused out of {} entities",
-        unused_entities.len(),
+        "\nTotal: {} dead out of {} entities",
+        dead_entities.len(),
         result.entities.len()
     );
 
     Ok(())
 }
 
-pub fn graph_json(root_path: &Path) -> Result<String> {
-    let result = scan_and_parse_files(root_path, false)?;
-    let graph = DependencyGraph::from_entities(&result.entities);
-    let json = graph.to_json()?;
-    Ok(json)
-}
+/// Lists files that can be deleted as a whole. Entry points from `options` or the
+/// config file are kept in addition to the built-in ones.
+pub fn deletable(root_path: &Path, options: &ScanOptions, exclude_tests: bool) -> Result<()> {
+    let (options, result) = scan_and_parse_configured(root_path, options, true)?;
 
-pub fn affected(
-    root_path: &Path,
-    base_ref: &str,
-    transitive: bool,
-    paths_only: bool,
-    tests_only: bool,
-) -> Result<()> {
-    if !paths_only && !tests_only {
-        println!("Analyzing changes between HEAD and '{}'...\n", base_ref);
-    }
+    let deletable_files: Vec<String> =
+        find_deletable_files(&result.entities, &options.entry_points)
+            .into_iter()
+            .filter(|path| !exclude_tests || !is_test_file(path))
+            .collect();
 
-    let changed_files = get_changed_files(root_path, base_ref)?;
+    println!("Found {} deletable files:\n", deletable_files.len());
 
-    if changed_files.is_empty() {
-        if !paths_only && !tests_only {
-            println!("No changes found between HEAD and '{}'.", base_ref);
-        }
-        return Ok(());
+    for file in &deletable_files {
+        println!("{}", file);
     }
 
-    if !paths_only && !tests_only {
-        println!("Changed files ({}):", changed_files.len());
-        for cf in &changed_files {
-            println!("  [{}] {}", cf.change_type, cf.path);
-        }
-        println!();
-    }
+    println!("\nTotal: {} deletable files", deletable_files.len());
 
-    let result = scan_and_parse_files(root_path, false)?;
+    Ok(())
+}
 
-    let graph = DependencyGraph::from_entities(&result.entities);
+/// Lists files that only re-export other modules and declare nothing themselves.
+pub fn barrels(root_path: &Path, options: &ScanOptions) -> Result<()> {
+    let result = scan_and_parse_files(root_path, options, true)?;
 
-    let changed_paths: HashSet<String> = changed_files.iter().map(|cf| cf.path.clone()).collect();
+    let barrels = barrel_files(&result.entities, &result.file_re_exports);
 
-    let mut direct_affected: Vec<(&Entity, &ChangedFile)> = Vec::new();
-    let mut direct_affected_ids: HashSet<String> = HashSet::new();
+    println!("Found {} barrel files:\n", barrels.len());
 
-    for entity in result.entities.values() {
-        if changed_paths.contains(&entity.file_path) {
-            if let Some(cf) = changed_files.iter().find(|cf| cf.path == entity.file_path) {
-                direct_affected.push((entity, cf));
-                direct_affected_ids.insert(entity.id.clone());
-            }
-        }
+    for file in &barrels {
+        println!("{}", file);
     }
 
-    direct_affected.sort_by(|a, b| a.0.file_path.cmp(&b.0.file_path));
+    println!("\nTotal: {} barrel files", barrels.len());
 
-    let consumer_ids = graph.find_consumers(&direct_affected_ids, transitive);
+    Ok(())
+}
 
-    let mut consumers: Vec<(&Entity, String)> = Vec::new();
-    for consumer_id in &consumer_ids {
-        if let Some(entity) = result.entities.get(consumer_id) {
-            let consumes: Vec<String> = entity
-                .deps
-                .iter()
-                .filter_map(|dep| {
-                    for (affected_entity, _) in &direct_affected {
-                        if affected_entity.file_path == dep.path && affected_entity.name == dep.name
-                        {
-                            return Some(affected_entity.name.clone());
-                        }
-                    }
-                    None
-                })
-                .collect();
+/// Lists files that declare nothing and that nothing imports.
+pub fn orphans(root_path: &Path, options: &ScanOptions) -> Result<()> {
+    let (options, result) = scan_and_parse_configured(root_path, options, true)?;
 
-            let reason = if consumes.is_empty() {
-                "Transitive dependency".to_string()
-            } else {
-                format!("Imports: {}", consumes.join(", "))
-            };
+    let orphans = orphan_files(
+        &result.entities,
+        &result.file_imports,
+        &result.file_re_exports,
+        &options.entry_points,
+    );
 
-            consumers.push((entity, reason));
-        }
+    println!("Found {} orphan files:\n", orphans.len());
+
+    for file in &orphans {
+        println!("{}", file);
     }
 
-    consumers.sort_by(|a, b| a.0.file_path.cmp(&b.0.file_path));
+    println!("\nTotal: {} orphan files", orphans.len());
 
-    if tests_only {
-        let mut test_files: HashSet<String> = HashSet::new();
+    Ok(())
+}
 
-        // Collect directories from directly affected entities
-        let mut affected_dirs: HashSet<String> = HashSet::new();
-        for (entity, _) in &direct_affected {
-            if let Some(parent) = Path::new(&entity.file_path).parent() {
-                affected_dirs.insert(parent.to_string_lossy().to_string());
-            }
-        }
+pub fn metrics(root_path: &Path, options: &ScanOptions, top: Option<usize>) -> Result<()> {
+    let result = scan_and_parse_files(root_path, options, false)?;
 
-        // Collect directories from consumer entities
-        for (entity, _) in &consumers {
-            if let Some(parent) = Path::new(&entity.file_path).parent() {
-                affected_dirs.insert(parent.to_string_lossy().to_string());
-            }
-        }
+    let metrics = match top {
+        Some(n) => most_depended_upon(&result.entities, n),
+        None => entity_metrics(&result.entities),
+    };
 
-        // Find test files in those directories
-        let discovered_tests = find_test_files_in_directories(&affected_dirs);
-        for test_path in discovered_tests {
-            test_files.insert(test_path);
-        }
+    println!(
+        "{:>5} {:>5}  {:<10} {:<40} File",
+        "In", "Out", "Type", "Name"
+    );
+    for metric in &metrics {
+        println!(
+            "{:>5} {:>5}  {:<10} {:<40} {}",
+            metric.in_degree,
+            metric.out_degree,
+            metric.entity.entity_type.to_string(),
+            metric.entity.name,
+            metric.entity.file_path
+        );
+    }
 
-        // Include test files that were directly changed in the git diff
-        for cf in &changed_files {
-            if is_test_file(&cf.path) {
-                test_files.insert(cf.path.clone());
-            }
-        }
+    println!(
+        "\nShowing {} of {} entities",
+        metrics.len(),
+        result.entities.len()
+    );
 
-        // Output sorted test file paths
-        let mut sorted_tests: Vec<String> = test_files.into_iter().collect();
-        sorted_tests.sort();
+    Ok(())
+}
 
-        for test_path in sorted_tests {
-            println!("{}", test_path);
+/// Reports entities with more dependencies or dependents than allowed and returns
+/// how many were found, so callers can fail when there are any.
+pub fn lint(
+    root_path: &Path,
+    options: &ScanOptions,
+    max_dependencies: Option<usize>,
+    max_dependents: Option<usize>,
+) -> Result<usize> {
+    let result = scan_and_parse_files(root_path, options, false)?;
+    let violations = threshold_violations(&result.entities, max_dependencies, max_dependents);
+
+    for metric in &violations {
+        let entity = metric.entity;
+        if let Some(max) = max_dependencies
+            && metric.out_degree > max
+        {
+            println!(
+                "{}: {} {} has {} dependencies (max {})",
+                entity.location(),
+                entity.entity_type,
+                entity.name,
+                metric.out_degree,
+                max
+            );
         }
+        if let Some(max) = max_dependents
+            && metric.in_degree > max
+        {
+            println!(
+                "{}: {} {} has {} dependents (max {})",
+                entity.location(),
+                entity.entity_type,
+                entity.name,
+                metric.in_degree,
+                max
+            );
+        }
+    }
 
-        return Ok(());
+    println!("\nTotal: {} entities over the limits", violations.len());
+
+    Ok(violations.len())
+}
+
+pub fn stats(root_path: &Path, options: &ScanOptions, format: OutputFormat) -> Result<()> {
+    if !matches!(format, OutputFormat::Text | OutputFormat::Json) {
+        anyhow::bail!("The stats command only supports text and JSON output");
     }
 
-    if paths_only {
-        let mut unique_dirs: HashSet<String> = HashSet::new();
+    let result = scan_and_parse_files(root_path, options, false)?;
+    let stats = analysis::stats(&result.entities, &result.file_imports, &result.errors);
 
-        for (entity, _) in &direct_affected {
-            if let Some(parent) = Path::new(&entity.file_path).parent() {
-                unique_dirs.insert(parent.to_string_lossy().to_string());
-            }
-        }
+    if format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
 
-        for (entity, _) in &consumers {
-            if let Some(parent) = Path::new(&entity.file_path).parent() {
-                unique_dirs.insert(parent.to_string_lossy().to_string());
-            }
-        }
+    println!("Entities: {}", stats.total_entities);
+    println!("Unused: {}", stats.unused_entities);
+    println!("Files: {}", stats.files);
+    println!("Average dependencies: {:.2}", stats.average_dependencies);
+    if !stats.errors.is_empty() {
+        println!("Skipped (unreadable): {}", stats.errors.len());
+    }
+    println!("\nBy type:");
+    for (entity_type, count) in &stats.by_type {
+        println!("  {:<10} {}", entity_type, count);
+    }
 
-        let mut sorted_dirs: Vec<_> = unique_dirs.into_iter().collect();
-        sorted_dirs.sort();
+    Ok(())
+}
 
-        for dir in sorted_dirs {
-            println!("{}", dir);
-        }
+/// Reads a snapshot of the entities written by `query-all --format json` (or
+/// `--format json-lines`).
+fn read_snapshot(path: &Path) -> Result<Vec<Entity>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Unable to read snapshot: {}", path.display()))?;
+    let entities = if content.trim_start().starts_with('[') {
+        serde_json::from_str(&content)?
     } else {
-        println!("---");
-        println!("Directly affected entities ({}):\n", direct_affected.len());
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?
+    };
+    Ok(entities)
+}
 
-        for (entity, cf) in &direct_affected {
-            print_affected_entity(
-                entity,
-                &format!("{} file", change_type_to_reason(&cf.change_type)),
-            );
-        }
+/// Compares two snapshots written by `query-all --format json` and reports the
+/// entities added, removed, newly unused and newly used between them.
+pub fn diff(
+    before: &Path,
+    after: &Path,
+    options: &ScanOptions,
+    format: OutputFormat,
+    out: &mut dyn Write,
+) -> Result<()> {
+    if !matches!(format, OutputFormat::Text | OutputFormat::Json) {
+        anyhow::bail!("The diff command only supports text and JSON output");
+    }
 
-        if !consumers.is_empty() {
-            println!("Consumer entities ({}):\n", consumers.len());
+    let before_entities =
+        read_snapshot(before).with_context(|| format!("Invalid snapshot: {}", before.display()))?;
+    let after_entities =
+        read_snapshot(after).with_context(|| format!("Invalid snapshot: {}", after.display()))?;
+    let diff = analysis::diff_snapshots(&before_entities, &after_entities);
 
-            for (entity, reason) in &consumers {
-                print_affected_entity(entity, reason);
-            }
+    if format == OutputFormat::Json {
+        writeln!(out, "{}", serde_json::to_string_pretty(&diff)?)?;
+        out.flush()?;
+        return Ok(());
+    }
+
+    let palette = Palette::new(options.color);
+    writeln!(
+        out,
+        "Added: {}, removed: {}, modified: {}, newly unused: {}, newly used: {}",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.modified.len(),
+        diff.newly_unused.len(),
+        diff.newly_used.len()
+    )?;
+    for (title, entities) in [
+        ("Added", &diff.added),
+        ("Removed", &diff.removed),
+        ("Modified", &diff.modified),
+        ("Newly unused", &diff.newly_unused),
+        ("Newly used", &diff.newly_used),
+    ] {
+        if entities.is_empty() {
+            continue;
         }
+        writeln!(out, "\n{}:", title)?;
+        for entity in entities {
+            writeln!(
+                out,
+                "  {} ({}) {}",
+                entity.name,
+                palette.entity_type(&entity.entity_type),
+                palette.path(entity.location())
+            )?;
+        }
+    }
+    out.flush()?;
 
-        let total = direct_affected.len() + consumers.len();
+    Ok(())
+}
+
+/// Prints the number of entities and unused entities per directory, see
+/// [`analysis::distribution`].
+pub fn distribution(
+    root_path: &Path,
+    options: &ScanOptions,
+    depth: Option<usize>,
+    format: OutputFormat,
+) -> Result<()> {
+    if !matches!(format, OutputFormat::Text | OutputFormat::Json) {
+        anyhow::bail!("The distribution command only supports text and JSON output");
+    }
+
+    let result = scan_and_parse_files(root_path, options, false)?;
+    let options = options.with_config(Config::load(root_path)?);
+    let counts = analysis::distribution(
+        &result.entities,
+        &normalize_path(root_path),
+        &scan_directories(&options),
+        depth,
+    );
+
+    if format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&counts)?);
+        return Ok(());
+    }
+
+    let width = counts
+        .iter()
+        .map(|count| count.directory.len())
+        .chain(std::iter::once("Directory".len()))
+        .max()
+        .unwrap_or_default();
+    println!(
+        "{:<width$}  {:>8}  {:>6}",
+        "Directory", "Entities", "Unused"
+    );
+    for count in &counts {
         println!(
-            "Summary: {} changed files, {} direct, {} consumers, {} total affected",
-            changed_files.len(),
-            direct_affected.len(),
-            consumers.len(),
-            total
+            "{:<width$}  {:>8}  {:>6}",
+            count.directory, count.entities, count.unused
         );
     }
 
     Ok(())
 }
 
-fn change_type_to_reason(change_type: &ChangeType) -> &'static str {
-    match change_type {
-        ChangeType::Added => "New",
-        ChangeType::Modified => "Modified",
-        ChangeType::Deleted => "Deleted",
-        ChangeType::Renamed => "Renamed",
-    }
+/// Lists imports that are never referenced in the body of the importing file.
+pub fn unused_imports(root_path: &Path, options: &ScanOptions) -> Result<()> {
+    let options = &options.with_config(Config::load(root_path)?);
+    let parser = create_parser(root_path, options);
+
+    let mut total = 0;
+    stream_files(&RealFileSystem, root_path, options, |file, content| {
+        for import in parser.unused_imports(content, file) {
+            println!("{}: {}", file, import.name);
+            total += 1;
+        }
+    })?;
+
+    println!("\nTotal: {} unused imports", total);
+
+    Ok(())
 }
 
-fn print_affected_entity(entity: &Entity, reason: &str) {
-    println!("Name: {}", entity.name);
-    println!("Type: {}", entity.entity_type);
-    println!("File: {}", entity.file_path);
-    println!("Reason: {}", reason);
-    println!("---");
+/// Lists imports whose resolved file does not exist and returns how many there are.
+pub fn validate(root_path: &Path, options: &ScanOptions) -> Result<usize> {
+    let options = &options.with_config(Config::load(root_path)?);
+    let parser = create_parser(root_path, options);
+
+    let mut total = 0;
+    stream_files(&RealFileSystem, root_path, options, |file, content| {
+        for broken in parser.broken_imports(content, file) {
+            println!(
+                "{}: '{}' resolves to missing {}",
+                file, broken.source, broken.resolved_path
+            );
+            total += 1;
+        }
+    })?;
+
+    println!("\nTotal: {} broken imports", total);
+
+    Ok(total)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::parser::{Parser, strip_comments};
-    use std::path::Path;
+/// Lists imports that resolve to the importing file itself.
+pub fn self_imports(root_path: &Path, options: &ScanOptions) -> Result<()> {
+    let result = scan_and_parse_files(root_path, options, false)?;
+    let found = analysis::self_imports(&result.file_imports);
 
-    #[test]
-    fn test_extract_single_named_import() {
-        let content = r#"import { Foo } from './foo';"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/bar.ts";
+    for (file, import) in &found {
+        println!("{}: {}", file, import.name);
+    }
 
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+    println!("\nTotal: {} self-imports", found.len());
 
-        assert_eq!(imports.len(), 1);
-        assert_eq!(imports[0].name, "Foo");
-        assert!(imports[0].path.contains("foo"));
+    Ok(())
+}
+
+/// Lists imports of files that exist but lie outside the scanned directories, and the
+/// directories that would have to be scanned as well to resolve them.
+pub fn out_of_scope(root_path: &Path, options: &ScanOptions) -> Result<()> {
+    let result = scan_and_parse_files(root_path, options, false)?;
+    let options = options.with_config(Config::load(root_path)?);
+    let found = analysis::out_of_scope_imports(
+        &result.file_imports,
+        &normalize_path(root_path),
+        &scan_directories(&options),
+    );
+
+    for item in &found {
+        println!(
+            "{}: {} -> {}",
+            item.file, item.import.name, item.import.path
+        );
     }
 
-    #[test]
-    fn test_extract_multiple_named_imports() {
-        let content = r#"import { Foo, Bar, Baz } from './utils';"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/index.ts";
+    let suggested_dirs: BTreeSet<&str> = found
+        .iter()
+        .map(|item| item.suggested_dir.as_str())
+        .collect();
+    if !suggested_dirs.is_empty() {
+        println!(
+            "\nScan these directories as well (--dir, or `dirs` in {}):",
+            config::CONFIG_FILE
+        );
+        for dir in &suggested_dirs {
+            println!("  {}", dir);
+        }
+    }
 
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+    println!(
+        "\nTotal: {} imports outside the scanned directories",
+        found.len()
+    );
 
-        assert_eq!(imports.len(), 3);
-        assert_eq!(imports[0].name, "Foo");
-        assert_eq!(imports[1].name, "Bar");
-        assert_eq!(imports[2].name, "Baz");
+    Ok(())
+}
+
+/// Lists imports that go against the layering, see [`analysis::layer_violations`],
+/// and returns how many there are.
+pub fn arch_check(root_path: &Path, options: &ScanOptions, layers: &[String]) -> Result<usize> {
+    let result = scan_and_parse_files(root_path, options, false)?;
+    let found =
+        analysis::layer_violations(&result.file_imports, &normalize_path(root_path), layers);
+
+    for violation in &found {
+        println!(
+            "{}: {} -> {} ({} must not import from {})",
+            violation.file,
+            violation.import.name,
+            violation.import.path,
+            violation.from_layer,
+            violation.to_layer
+        );
     }
 
-    #[test]
-    fn test_extract_multiline_named_imports() {
-        let content = r#"import {
-  Foo,
-  Bar,
-  Baz
-} from './utils';"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/index.ts";
+    println!("\nTotal: {} imports against the layering", found.len());
 
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+    Ok(found.len())
+}
 
-        assert_eq!(imports.len(), 3);
-        assert_eq!(imports[0].name, "Foo");
-        assert_eq!(imports[1].name, "Bar");
-        assert_eq!(imports[2].name, "Baz");
+/// Lists the external packages imported by the project and the files importing them.
+pub fn externals(root_path: &Path, options: &ScanOptions) -> Result<()> {
+    let options = ScanOptions {
+        include_external: true,
+        ..options.clone()
+    };
+    let result = scan_and_parse_files(root_path, &options, false)?;
+    let packages = analysis::external_packages(&result.file_imports);
+
+    for (package, files) in &packages {
+        println!("{} ({} files)", package, files.len());
+        for file in files {
+            println!("  {}", file);
+        }
     }
 
-    #[test]
-    fn test_extract_aliased_import() {
-        let content = r#"import { Foo as F, Bar as B } from './utils';"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/index.ts";
+    println!("\nTotal: {} external packages", packages.len());
 
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+    Ok(())
+}
 
-        assert_eq!(imports.len(), 2);
-        assert_eq!(imports[0].name, "Foo");
-        assert_eq!(imports[1].name, "Bar");
+/// Lists each entity with the external packages it depends on, directly or through
+/// the entities it imports.
+pub fn entity_externals(
+    root_path: &Path,
+    options: &ScanOptions,
+    format: OutputFormat,
+) -> Result<()> {
+    if !matches!(format, OutputFormat::Text | OutputFormat::Json) {
+        anyhow::bail!("The entity-externals command only supports text and JSON output");
     }
 
-    #[test]
-    fn test_extract_default_import() {
-        let content = r#"import Foo from './foo';"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/bar.ts";
+    let options = ScanOptions {
+        include_external: true,
+        ..options.clone()
+    };
+    let result = scan_and_parse_files(root_path, &options, false)?;
+    let found = analysis::transitive_external_packages(&result.entities);
+
+    if format == OutputFormat::Json {
+        let by_id: BTreeMap<&str, &BTreeSet<&str>> = found
+            .iter()
+            .map(|(entity, packages)| (entity.id.as_str(), packages))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&by_id)?);
+        return Ok(());
+    }
 
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+    for (entity, packages) in &found {
+        let packages: Vec<&str> = packages.iter().copied().collect();
+        println!(
+            "{} {} ({}): {}",
+            entity.entity_type,
+            entity.name,
+            entity.location(),
+            packages.join(", ")
+        );
+    }
 
-        assert_eq!(imports.len(), 1);
-        assert_eq!(imports[0].name, "Foo");
+    println!(
+        "\nTotal: {} entities depending on external packages",
+        found.len()
+    );
+
+    Ok(())
+}
+
+pub fn coupling_csv(root_path: &Path, options: &ScanOptions) -> Result<String> {
+    let result = scan_and_parse_files(root_path, options, false)?;
+    let (files, matrix) = coupling_matrix(&result.entities);
+    Ok(export_coupling_csv(&files, &matrix))
+}
+
+/// Dependency graph as JSON, either d3-style `nodes`/`links` or, with `adjacency`,
+/// `nodes`/`edges` with `from_id`/`to_id` pairs. `focus` limits it to the
+/// neighborhood of one entity.
+pub fn graph_json(
+    root_path: &Path,
+    options: &ScanOptions,
+    adjacency: bool,
+    focus: Option<Neighborhood>,
+) -> Result<String> {
+    let result = scan_and_parse_files(root_path, options, false)?;
+    check_focus(&result.entities, focus)?;
+
+    let mut graph = DependencyGraph::from_entities(&result.entities);
+    if let Some(focus) = focus {
+        graph.retain_neighborhood(focus);
     }
 
-    #[test]
-    fn test_extract_awork_alias_import() {
-        let content = r#"import { Model } from '@awork/models';"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/apps/web/src/index.ts";
+    let json = if adjacency {
+        graph.to_adjacency_json()?
+    } else {
+        graph.to_json()?
+    };
+    Ok(json)
+}
 
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+pub fn graph_mermaid(
+    root_path: &Path,
+    options: &ScanOptions,
+    focus: Option<Neighborhood>,
+) -> Result<String> {
+    let result = scan_and_parse_files(root_path, options, false)?;
+    check_focus(&result.entities, focus)?;
 
-        assert_eq!(imports.len(), 1);
-        assert_eq!(imports[0].name, "Model");
-        assert!(imports[0].path.contains("libs/shared/src/lib"));
-        assert!(imports[0].path.contains("models"));
-        assert!(!imports[0].path.contains("@awork"));
+    Ok(export_mermaid(&result.entities, focus))
+}
+
+/// Renders the transitive dependencies of the entity with id `id`, or of every
+/// entity called `name`, as indented trees separated by blank lines.
+pub fn dependency_tree(
+    root_path: &Path,
+    options: &ScanOptions,
+    id: Option<&str>,
+    name: Option<&str>,
+) -> Result<String> {
+    let result = scan_and_parse_files(root_path, options, false)?;
+
+    let ids: Vec<&str> = match (id, name) {
+        (Some(id), _) => vec![id],
+        (None, Some(name)) => entities_named(&result.entities, name)
+            .iter()
+            .map(|entity| entity.id.as_str())
+            .collect(),
+        (None, None) => anyhow::bail!("An entity id or name is required"),
+    };
+
+    let graph = DependencyGraph::from_entities(&result.entities);
+    let trees: Vec<String> = ids
+        .iter()
+        .filter_map(|id| graph.dependency_tree(id))
+        .collect();
+    if trees.is_empty() {
+        anyhow::bail!("Entity not found: {}", id.or(name).unwrap_or_default());
+    }
+
+    Ok(trees.join("\n"))
+}
+
+/// Explains why the entity with the given id, or each entity with the given name,
+/// is used: the first usage that marked it, such as the file importing it or the
+/// barrel re-exporting it.
+pub fn why_used(
+    root_path: &Path,
+    options: &ScanOptions,
+    id: Option<&str>,
+    name: Option<&str>,
+) -> Result<String> {
+    let result = scan_and_parse_files(root_path, options, false)?;
+
+    let entities: Vec<&Entity> = match (id, name) {
+        (Some(id), _) => result.entities.get(id).into_iter().collect(),
+        (None, Some(name)) => entities_named(&result.entities, name),
+        (None, None) => anyhow::bail!("An entity id or name is required"),
+    };
+    if entities.is_empty() {
+        anyhow::bail!("Entity not found: {}", id.or(name).unwrap_or_default());
+    }
+
+    let reasons: Vec<String> = entities
+        .iter()
+        .map(|entity| {
+            let reason = match &entity.used_by {
+                Some(usage) => usage.to_string(),
+                None if entity.is_test_only() => "not used, only imported by tests".to_string(),
+                None => "not used".to_string(),
+            };
+            format!(
+                "{} ({}) {}\n  {}",
+                entity.name,
+                entity.entity_type,
+                entity.location(),
+                reason
+            )
+        })
+        .collect();
+
+    Ok(reasons.join("\n"))
+}
+
+fn check_focus(entities: &HashMap<String, Entity>, focus: Option<Neighborhood>) -> Result<()> {
+    if let Some(focus) = focus
+        && !entities.contains_key(focus.entity_id)
+    {
+        anyhow::bail!("Entity not found: {}", focus.entity_id);
+    }
+    Ok(())
+}
+
+/// The files changed in a diff range together with the entities of the project,
+/// for tools relating changes to what they affect.
+#[derive(Debug)]
+pub struct ChangeAnalysis {
+    /// Canonical root of the git repository containing the project
+    pub repo_root: PathBuf,
+    /// Every file changed in the range, with paths below `repo_root`
+    pub changed_files: Vec<ChangedFile>,
+    /// The entities of the project by id; their file paths match the changed paths
+    pub entities: HashMap<String, Entity>,
+}
+
+impl ChangeAnalysis {
+    /// The entities declared in changed files.
+    pub fn changed_entities(&self) -> impl Iterator<Item = &Entity> {
+        let changed: HashSet<&str> = self
+            .changed_files
+            .iter()
+            .map(|cf| cf.path.as_str())
+            .collect();
+        self.entities
+            .values()
+            .filter(move |entity| changed.contains(entity.file_path.as_str()))
+    }
+}
+
+/// Lists the files changed in `range` and scans the project at `root_path`, which
+/// must be in a git repository, returning both together. Both use canonical paths,
+/// so changed files and entity file paths match even when `root_path` is reached
+/// through a symlink.
+pub fn analyze_changes(
+    root_path: &Path,
+    options: &ScanOptions,
+    range: &DiffRange,
+) -> Result<ChangeAnalysis> {
+    let root_path = &root_path
+        .canonicalize()
+        .with_context(|| format!("Unable to resolve path: {}", root_path.display()))?;
+    let (repo, repo_root) = git::discover_repository(root_path)?;
+    let reporter = Reporter::stdout(options.verbosity);
+    let changed_files = git::changed_files_in(&repo, &repo_root, range, &reporter)?;
+    let result = scan_and_parse_files(root_path, options, false)?;
+
+    Ok(ChangeAnalysis {
+        repo_root,
+        changed_files,
+        entities: result.entities,
+    })
+}
+
+/// Paths of the source files changed in `range`, see [`source_changes`].
+fn changed_source_paths(
+    root_path: &Path,
+    options: &ScanOptions,
+    range: &DiffRange,
+) -> Result<HashSet<String>> {
+    let reporter = Reporter::stdout(options.verbosity);
+    let changed_files = get_changed_files(root_path, range, &reporter)?;
+    Ok(source_changes(options, &changed_files)
+        .into_iter()
+        .map(|cf| cf.path)
+        .collect())
+}
+
+/// Keeps the changes to files that the scan parses as sources: files with a scanned
+/// extension that are not skipped by their suffix and are not test files.
+fn source_changes(options: &ScanOptions, changed_files: &[ChangedFile]) -> Vec<ChangedFile> {
+    let scanner = create_scanner(options);
+    changed_files
+        .iter()
+        .filter(|cf| scanner.is_scannable(Path::new(&cf.path)) && !is_test_file(&cf.path))
+        .cloned()
+        .collect()
+}
+
+pub fn affected(
+    root_path: &Path,
+    options: &ScanOptions,
+    range: &DiffRange,
+    transitive: bool,
+    paths_only: bool,
+    tests_only: bool,
+) -> Result<()> {
+    let base_ref = range.base_ref;
+    let head_name = if range.include_working_tree {
+        "the working tree"
+    } else {
+        range.head_ref.unwrap_or("HEAD")
+    };
+
+    let mut reporter = Reporter::stdout(options.verbosity);
+    if !paths_only && !tests_only {
+        reporter.info(format!(
+            "Analyzing changes between {} and '{}'...\n",
+            head_name, base_ref
+        ));
+    }
+
+    let changed_files = get_changed_files(root_path, range, &reporter)?;
+
+    if changed_files.is_empty() {
+        if !paths_only && !tests_only {
+            println!("No changes found between {} and '{}'.", head_name, base_ref);
+        }
+        return Ok(());
+    }
+
+    if !paths_only && !tests_only {
+        println!("Changed files ({}):", changed_files.len());
+        for cf in &changed_files {
+            match &cf.old_path {
+                Some(old_path) => println!("  [{}] {} -> {}", cf.change_type, old_path, cf.path),
+                None => println!("  [{}] {}", cf.change_type, cf.path),
+            }
+        }
+        println!();
+    }
+
+    let result = scan_and_parse_files(root_path, options, false)?;
+
+    let graph = DependencyGraph::from_entities(&result.entities);
+
+    let changed_sources = source_changes(options, &changed_files);
+    let changed_paths: HashSet<String> = changed_sources.iter().map(|cf| cf.path.clone()).collect();
+
+    let mut direct_affected: Vec<(&Entity, &ChangedFile)> = Vec::new();
+    let mut direct_affected_ids: HashSet<String> = HashSet::new();
+
+    for entity in result.entities.values() {
+        if changed_paths.contains(&entity.file_path)
+            && let Some(cf) = changed_sources
+                .iter()
+                .find(|cf| cf.path == entity.file_path)
+        {
+            direct_affected.push((entity, cf));
+            direct_affected_ids.insert(entity.id.clone());
+        }
+    }
+
+    direct_affected.sort_by(|a, b| a.0.file_path.cmp(&b.0.file_path));
+
+    let consumer_ids = graph.find_consumers(&direct_affected_ids, transitive);
+
+    let mut consumers: Vec<(&Entity, String)> = Vec::new();
+    for consumer_id in &consumer_ids {
+        if let Some(entity) = result.entities.get(consumer_id) {
+            let consumes: Vec<String> = entity
+                .deps
+                .iter()
+                .filter_map(|dep| {
+                    for (affected_entity, _) in &direct_affected {
+                        if affected_entity.file_path == dep.path && affected_entity.name == dep.name
+                        {
+                            return Some(affected_entity.name.clone());
+                        }
+                    }
+                    None
+                })
+                .collect();
+
+            let reason = if consumes.is_empty() {
+                "Transitive dependency".to_string()
+            } else {
+                format!("Imports: {}", consumes.join(", "))
+            };
+
+            consumers.push((entity, reason));
+        }
+    }
+
+    consumers.sort_by(|a, b| a.0.file_path.cmp(&b.0.file_path));
+
+    if tests_only {
+        let mut test_files: HashSet<String> = HashSet::new();
+
+        // Collect directories from directly affected entities
+        let mut affected_dirs: HashSet<String> = HashSet::new();
+        for (entity, _) in &direct_affected {
+            if let Some(parent) = Path::new(&entity.file_path).parent() {
+                affected_dirs.insert(parent.to_string_lossy().to_string());
+            }
+        }
+
+        // Collect directories from consumer entities
+        for (entity, _) in &consumers {
+            if let Some(parent) = Path::new(&entity.file_path).parent() {
+                affected_dirs.insert(parent.to_string_lossy().to_string());
+            }
+        }
+
+        // Find test files in those directories
+        let discovered_tests = find_test_files_in_directories(&affected_dirs);
+        for test_path in discovered_tests {
+            test_files.insert(test_path);
+        }
+
+        // Include test files that were directly changed in the git diff
+        for cf in &changed_files {
+            if is_test_file(&cf.path) {
+                test_files.insert(cf.path.clone());
+            }
+        }
+
+        // Output sorted test file paths
+        let mut sorted_tests: Vec<String> = test_files.into_iter().collect();
+        sorted_tests.sort();
+
+        for test_path in sorted_tests {
+            println!("{}", test_path);
+        }
+
+        return Ok(());
+    }
+
+    if paths_only {
+        let mut unique_dirs: HashSet<String> = HashSet::new();
+
+        for (entity, _) in &direct_affected {
+            if let Some(parent) = Path::new(&entity.file_path).parent() {
+                unique_dirs.insert(parent.to_string_lossy().to_string());
+            }
+        }
+
+        for (entity, _) in &consumers {
+            if let Some(parent) = Path::new(&entity.file_path).parent() {
+                unique_dirs.insert(parent.to_string_lossy().to_string());
+            }
+        }
+
+        let mut sorted_dirs: Vec<_> = unique_dirs.into_iter().collect();
+        sorted_dirs.sort();
+
+        for dir in sorted_dirs {
+            println!("{}", dir);
+        }
+    } else {
+        println!("---");
+        println!("Directly affected entities ({}):\n", direct_affected.len());
+
+        for (entity, cf) in &direct_affected {
+            print_affected_entity(
+                entity,
+                &format!("{} file", change_type_to_reason(&cf.change_type)),
+            );
+        }
+
+        if !consumers.is_empty() {
+            println!("Consumer entities ({}):\n", consumers.len());
+
+            for (entity, reason) in &consumers {
+                print_affected_entity(entity, reason);
+            }
+        }
+
+        let total = direct_affected.len() + consumers.len();
+        println!(
+            "Summary: {} changed files, {} direct, {} consumers, {} total affected",
+            changed_files.len(),
+            direct_affected.len(),
+            consumers.len(),
+            total
+        );
+    }
+
+    Ok(())
+}
+
+fn change_type_to_reason(change_type: &ChangeType) -> &'static str {
+    match change_type {
+        ChangeType::Added => "New",
+        ChangeType::Modified => "Modified",
+        ChangeType::Deleted => "Deleted",
+        ChangeType::Renamed => "Renamed",
+    }
+}
+
+fn print_affected_entity(entity: &Entity, reason: &str) {
+    println!("Name: {}", entity.name);
+    println!("Type: {}", entity.entity_type);
+    println!("File: {}", entity.location());
+    println!("Reason: {}", reason);
+    println!("---");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::entity::{EntityType, normalize_path};
+    use super::parser::{Parser, PathAlias, mask_string_literals, strip_comments};
+    use std::path::Path;
+
+    #[test]
+    fn test_extract_single_named_import() {
+        let content = r#"import { Foo } from './foo';"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/src/bar.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].name, "Foo");
+        assert!(imports[0].path.contains("foo"));
+    }
+
+    #[test]
+    fn test_extract_multiple_named_imports() {
+        let content = r#"import { Foo, Bar, Baz } from './utils';"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/src/index.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 3);
+        assert_eq!(imports[0].name, "Foo");
+        assert_eq!(imports[1].name, "Bar");
+        assert_eq!(imports[2].name, "Baz");
+    }
+
+    #[test]
+    fn test_extract_multiline_named_imports() {
+        let content = r#"import {
+  Foo,
+  Bar,
+  Baz
+} from './utils';"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/src/index.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 3);
+        assert_eq!(imports[0].name, "Foo");
+        assert_eq!(imports[1].name, "Bar");
+        assert_eq!(imports[2].name, "Baz");
+    }
+
+    #[test]
+    fn test_extract_aliased_import() {
+        let content = r#"import { Foo as F, Bar as B } from './utils';"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/src/index.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 2);
+        assert_eq!(imports[0].name, "Foo");
+        assert_eq!(imports[0].local_name(), "F");
+        assert_eq!(imports[1].name, "Bar");
+        assert_eq!(imports[1].local_name(), "B");
+    }
+
+    #[test]
+    fn test_import_used_only_under_its_alias_uses_the_origin() {
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("foo.ts"), "export class Foo {}\n").unwrap();
+        std::fs::write(src.join("other.ts"), "export class Foo {}\n").unwrap();
+        let content = "import { Foo } from './other';\n\
+                       import { Foo as Bar } from './foo';\n\n\
+                       export const bar = new Bar();\n";
+        std::fs::write(src.join("bar.ts"), content).unwrap();
+
+        let result = scan_and_parse_files(temp_dir.path(), &ScanOptions::default(), false).unwrap();
+        let foo = result
+            .entities
+            .values()
+            .find(|entity| entity.name == "Foo" && entity.file_path.ends_with("/foo.ts"))
+            .unwrap();
+        assert!(foo.used);
+
+        // Only the import whose local name is referenced counts as used
+        let bar_path = normalize_path(&src.canonicalize().unwrap().join("bar.ts"));
+        let parser = Parser::new(temp_dir.path());
+        let unused = parser.unused_imports(content, &bar_path);
+        assert_eq!(unused.len(), 1);
+        assert!(unused[0].path.ends_with("/other.ts"));
+        assert_eq!(unused[0].alias, None);
+    }
+
+    #[test]
+    fn test_import_extraction_is_identical_across_files_and_parsers() {
+        let content = r#"import { Foo, Bar as B } from './foo';
+import Baz from './baz';
+import * as models from './models';
+import Legacy = require('./legacy');
+const { Lazy } = await import('./lazy');
+export const use = () => [B, Baz, models.Color, Legacy, Lazy];
+"#;
+        let summary = |parser: &Parser, file_path: &str| -> Vec<(String, String, Option<String>)> {
+            parser
+                .extract_imports(content, file_path)
+                .into_iter()
+                .map(|import| (import.name, import.path, import.alias))
+                .collect()
+        };
+
+        let expected = |dir: &str| -> Vec<(String, String, Option<String>)> {
+            [
+                ("Foo", "foo", None),
+                ("Bar", "foo", Some("B")),
+                ("Baz", "baz", None),
+                ("Legacy", "legacy", None),
+                ("Color", "models", None),
+                ("Lazy", "lazy", None),
+            ]
+            .into_iter()
+            .map(|(name, file, alias)| {
+                (
+                    name.to_string(),
+                    format!("unresolved:{}/./{}", dir, file),
+                    alias.map(str::to_string),
+                )
+            })
+            .collect()
+        };
+
+        let parser = Parser::new(Path::new("/project"));
+        for _ in 0..3 {
+            for (file_path, dir) in [
+                ("/project/src/a.ts", "/project/src"),
+                ("/project/libs/feature/b.ts", "/project/libs/feature"),
+            ] {
+                assert_eq!(summary(&parser, file_path), expected(dir));
+                assert_eq!(
+                    summary(&Parser::new(Path::new("/project")), file_path),
+                    expected(dir)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_extract_default_import() {
+        let content = r#"import Foo from './foo';"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/src/bar.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].name, "Foo");
+    }
+
+    #[test]
+    fn test_extract_awork_alias_import() {
+        let content = r#"import { Model } from '@awork/models';"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/apps/web/src/index.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].name, "Model");
+        assert!(imports[0].path.contains("libs/shared/src/lib"));
+        assert!(imports[0].path.contains("models"));
+        assert!(!imports[0].path.contains("@awork"));
+    }
+
+    #[test]
+    fn test_configured_aliases_resolve_to_distinct_directories() {
+        let content = "import { Logger } from '@core/logging';\n\
+                       import { Button } from '@ui/button';\n\
+                       import { Model } from '@awork/models';";
+        let aliases: Vec<PathAlias> = ["@core/=libs/core/src", "@ui/=libs/ui/src/lib"]
+            .iter()
+            .map(|alias| alias.parse().unwrap())
+            .collect();
+
+        let parser = Parser::new(Path::new("/project")).with_aliases(&aliases);
+        let imports = parser.extract_imports(content, "/project/apps/web/src/index.ts");
+
+        assert_eq!(imports.len(), 3);
+        assert_eq!(imports[0].path, "unresolved:/project/libs/core/src/logging");
+        assert_eq!(
+            imports[1].path,
+            "unresolved:/project/libs/ui/src/lib/button"
+        );
+        assert_eq!(
+            imports[2].path,
+            "unresolved:/project/libs/shared/src/lib/models"
+        );
+    }
+
+    #[test]
+    fn test_alias_to_package_resolves_its_package_json_entry() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let ui = root.join("libs/ui");
+        let forms = root.join("libs/forms");
+        std::fs::create_dir_all(ui.join("src")).unwrap();
+        std::fs::create_dir_all(forms.join("src")).unwrap();
+        std::fs::write(
+            ui.join("package.json"),
+            r#"{ "main": "./src/public-api.js" }"#,
+        )
+        .unwrap();
+        std::fs::write(ui.join("src/public-api.ts"), "export class Button {}\n").unwrap();
+        std::fs::write(ui.join("index.ts"), "export class Legacy {}\n").unwrap();
+        std::fs::write(
+            forms.join("package.json"),
+            r#"{ "main": "./dist/index.js", "exports": { ".": { "types": "./src/entry.d.ts", "default": "./dist/entry.js" } } }"#,
+        )
+        .unwrap();
+        std::fs::write(forms.join("src/entry.ts"), "export class Form {}\n").unwrap();
+
+        let aliases: Vec<PathAlias> = ["@myorg/ui=libs/ui", "@myorg/forms=libs/forms"]
+            .iter()
+            .map(|alias| alias.parse().unwrap())
+            .collect();
+        let parser = Parser::new(&root).with_aliases(&aliases);
+        let content = "import { Button } from '@myorg/ui';\nimport { Form } from '@myorg/forms';";
+        let imports =
+            parser.extract_imports(content, &normalize_path(&root.join("apps/web/main.ts")));
+
+        assert_eq!(
+            imports[0].path,
+            normalize_path(&ui.join("src/public-api.ts"))
+        );
+        assert_eq!(imports[1].path, normalize_path(&forms.join("src/entry.ts")));
+    }
+
+    #[test]
+    fn test_similarly_named_scope_is_not_resolved_as_alias() {
+        let content = "import { Model } from '@awork/models';\n\
+                       import { Widget } from '@awork-labs/widgets';\n\
+                       import { Logger } from '@core-ui/logging';";
+        let aliases: Vec<PathAlias> = vec!["@core=libs/core/src".parse().unwrap()];
+        let file_path = "/project/apps/web/src/index.ts";
+
+        let parser = Parser::new(Path::new("/project"))
+            .with_aliases(&aliases)
+            .with_external_packages(true);
+        let imports = parser.extract_imports(content, file_path);
+        let paths: Vec<&str> = imports.iter().map(|i| i.path.as_str()).collect();
+
+        assert_eq!(
+            paths,
+            vec![
+                "unresolved:/project/libs/shared/src/lib/models",
+                "external:@awork-labs/widgets",
+                "external:@core-ui/logging",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_internal_scopes_are_not_recorded_as_external() {
+        let content = "import { Model } from '@myorg/models';\n\
+                       import { Widget } from '@myorg-labs/widgets';";
+        let internal_scopes = vec!["@myorg".to_string()];
+
+        let parser = Parser::new(Path::new("/project"))
+            .with_internal_scopes(&internal_scopes)
+            .with_external_packages(true);
+        let imports = parser.extract_imports(content, "/project/apps/web/src/index.ts");
+
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].path, "external:@myorg-labs/widgets");
+    }
+
+    #[test]
+    fn test_external_imports_captured_only_when_enabled() {
+        let content = "import { Component } from '@angular/core';\n\
+                       import { map } from 'rxjs/operators';\n\
+                       import { Local } from './local';";
+        let file_path = "/project/apps/web/src/app.ts";
+
+        let parser = Parser::new(Path::new("/project"));
+        let imports = parser.extract_imports(content, file_path);
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].name, "Local");
+
+        let parser = Parser::new(Path::new("/project")).with_external_packages(true);
+        let imports = parser.extract_imports(content, file_path);
+        let found: Vec<(&str, &str)> = imports
+            .iter()
+            .map(|i| (i.name.as_str(), i.path.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("Component", "external:@angular/core"),
+                ("map", "external:rxjs"),
+                ("Local", "unresolved:/project/apps/web/src/./local"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_path_alias_from_str() {
+        let alias: PathAlias = "@core/=libs/core/src".parse().unwrap();
+        assert_eq!(alias.prefix, "@core/");
+        assert_eq!(alias.path, Path::new("libs/core/src"));
+
+        assert!("@core/".parse::<PathAlias>().is_err());
+        assert!("=libs/core".parse::<PathAlias>().is_err());
+    }
+
+    #[test]
+    fn test_skip_external_package_imports() {
+        let content = r#"import { useState } from 'react';
+import { Observable } from 'rxjs';
+import { Foo } from './local';"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/src/index.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].name, "Foo");
+    }
+
+    #[test]
+    fn test_extract_multiple_import_statements() {
+        let content = r#"import { Foo } from './foo';
+import { Bar, Baz } from './bar';
+import Default from './default';"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/src/index.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 4);
+        assert_eq!(imports[0].name, "Foo");
+        assert_eq!(imports[1].name, "Bar");
+        assert_eq!(imports[2].name, "Baz");
+        assert_eq!(imports[3].name, "Default");
+    }
+
+    #[test]
+    fn test_extract_relative_parent_path_import() {
+        let content = r#"import { Util } from '../utils/helper';"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/src/components/button.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].name, "Util");
+        assert!(imports[0].path.contains("utils"));
+        assert!(imports[0].path.contains("helper"));
+    }
+
+    #[test]
+    fn test_import_path_gets_extension_of_existing_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("foo.ts"), "export class Foo {}\n").unwrap();
+        std::fs::write(src.join("view.tsx"), "export const View = () => null;\n").unwrap();
+        let content = "import { Foo } from './foo';\nimport { View } from './view';";
+        let file_path = normalize_path(&src.canonicalize().unwrap().join("bar.ts"));
+
+        let parser = Parser::new(temp_dir.path());
+        let imports = parser.extract_imports(content, &file_path);
+
+        assert_eq!(imports.len(), 2);
+        assert!(imports[0].path.ends_with("/src/foo.ts"));
+        assert!(imports[1].path.ends_with("/src/view.tsx"));
+    }
+
+    #[test]
+    fn test_unresolved_import_path_is_marked_instead_of_getting_ts_extension() {
+        let content = "import { Foo } from './foo';\n\
+                       import { View } from './view.tsx';\n\
+                       import data from './data.json';";
+        let parser = Parser::new(Path::new("/project"));
+        let imports = parser.extract_imports(content, "/project/src/bar.ts");
+
+        let paths: Vec<&str> = imports.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "unresolved:/project/src/./foo",
+                "/project/src/./view.tsx",
+                "/project/src/./data.json",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_loader_suffixes_are_ignored_and_assets_declare_nothing() {
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("icon.svg"), "<svg/>").unwrap();
+        std::fs::write(src.join("foo.ts"), "export class Foo {}\n").unwrap();
+        let content = "import icon from './icon.svg?raw';\n\
+                       import { Foo } from './foo?v=2#top';\n\n\
+                       export const logo = [icon, Foo];\n";
+        std::fs::write(src.join("logo.ts"), content).unwrap();
+
+        let result = scan_and_parse_files(temp_dir.path(), &ScanOptions::default(), false).unwrap();
+        let logo = result.entities.values().find(|e| e.name == "logo").unwrap();
+        let deps: Vec<(&str, &str)> = logo
+            .deps
+            .iter()
+            .map(|dep| (dep.name.as_str(), dep.path.as_str()))
+            .collect();
+        let src = normalize_path(&src.canonicalize().unwrap());
+        assert_eq!(
+            deps,
+            [
+                ("Foo", format!("{}/foo.ts", src).as_str()),
+                ("icon", format!("{}/icon.svg", src).as_str()),
+            ]
+        );
+
+        assert!(
+            result
+                .entities
+                .values()
+                .find(|e| e.name == "Foo")
+                .unwrap()
+                .used
+        );
+        assert!(!result.entities.values().any(|e| e.name == "icon"));
+    }
+
+    #[test]
+    fn test_json_import_keeps_json_extension() {
+        let content = r#"import config from './config.json';"#;
+        let parser = Parser::new(Path::new("/project"));
+        let imports = parser.extract_imports(content, "/project/src/bar.ts");
+
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].name, "config");
+        assert!(imports[0].path.ends_with("config.json"));
+    }
+
+    #[test]
+    fn test_extensionless_import_resolves_to_json_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("settings.json"), "{}").unwrap();
+
+        let parser = Parser::new(temp_dir.path());
+        let importing_file = src.join("bar.ts");
+        let imports = parser.extract_imports(
+            "import settings from './settings';",
+            importing_file.to_str().unwrap(),
+        );
+
+        assert_eq!(imports.len(), 1);
+        assert!(imports[0].path.ends_with("settings.json"));
+    }
+
+    #[test]
+    fn test_import_resolves_to_module_typescript_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("util.mts"), "export const util = 1;").unwrap();
+        std::fs::write(src.join("legacy.cts"), "export const legacy = 1;").unwrap();
+
+        let parser = Parser::new(temp_dir.path());
+        let importing_file = src.join("bar.mts");
+        let imports = parser.extract_imports(
+            "import { util } from './util';\n\
+             import { util as esm } from './util.mjs';\n\
+             import { legacy } from './legacy.cjs';",
+            importing_file.to_str().unwrap(),
+        );
+
+        assert_eq!(imports.len(), 3);
+        assert!(imports[0].path.ends_with("src/util.mts"));
+        assert_eq!(imports[1].path, imports[0].path);
+        assert!(imports[2].path.ends_with("src/legacy.cts"));
+    }
+
+    #[test]
+    fn test_multiline_import_with_trailing_comma() {
+        let content = r#"import {
+  Foo,
+  Bar,
+} from './utils';"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/src/index.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 2);
+        assert_eq!(imports[0].name, "Foo");
+        assert_eq!(imports[1].name, "Bar");
+    }
+
+    #[test]
+    fn test_import_info_has_id() {
+        let content = r#"import { Foo } from './foo';"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/src/bar.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 1);
+        assert!(!imports[0].id.is_empty());
+    }
+
+    #[test]
+    fn test_strip_single_line_comment() {
+        let content = "const a = 1; // this is a comment\nconst b = 2;";
+        let result = strip_comments(content);
+        assert_eq!(result, "const a = 1; \nconst b = 2;");
+    }
+
+    #[test]
+    fn test_strip_multiline_comment() {
+        let content = "const a = 1; /* this is\na multiline\ncomment */ const b = 2;";
+        let result = strip_comments(content);
+        assert_eq!(result, "const a = 1; \n\n const b = 2;");
+    }
+
+    #[test]
+    fn test_strip_full_line_comment() {
+        let content = "// full line comment\nconst a = 1;";
+        let result = strip_comments(content);
+        assert_eq!(result, "\nconst a = 1;");
+    }
+
+    #[test]
+    fn test_preserve_string_with_comment_like_content() {
+        let content = r#"const a = "// not a comment";"#;
+        let result = strip_comments(content);
+        assert_eq!(result, r#"const a = "// not a comment";"#);
+    }
+
+    #[test]
+    fn test_preserve_string_with_multiline_comment_like_content() {
+        let content = r#"const a = "/* not a comment */";"#;
+        let result = strip_comments(content);
+        assert_eq!(result, r#"const a = "/* not a comment */";"#);
+    }
+
+    #[test]
+    fn test_preserve_comment_like_content_in_template_interpolation() {
+        let content = r#"const a = `value: ${a || "/* x */"}`; // trailing"#;
+        let result = strip_comments(content);
+        assert_eq!(result, r#"const a = `value: ${a || "/* x */"}`; "#);
+    }
+
+    #[test]
+    fn test_preserve_nested_template_literals() {
+        let content =
+            "const a = `outer ${cond ? `inner ${b} // kept` : '}'} done`; // removed\nconst b = 2;";
+        let result = strip_comments(content);
+        assert_eq!(
+            result,
+            "const a = `outer ${cond ? `inner ${b} // kept` : '}'} done`; \nconst b = 2;"
+        );
+    }
+
+    #[test]
+    fn test_strip_comment_inside_template_interpolation() {
+        let content = "const a = `${ {x: 1}.x /* note */ } // kept`;";
+        let result = strip_comments(content);
+        assert_eq!(result, "const a = `${ {x: 1}.x  } // kept`;");
+    }
+
+    #[test]
+    fn test_preserve_regex_literal_with_slashes() {
+        let content = "const re = /https:\\/\\//g; // comment\nexport const a = 1;";
+        let result = strip_comments(content);
+        assert_eq!(result, "const re = /https:\\/\\//g; \nexport const a = 1;");
+    }
+
+    #[test]
+    fn test_preserve_regex_literal_with_character_class() {
+        let content = "if (/[/*]+/.test(value)) { return /a\\/b/; }";
+        let result = strip_comments(content);
+        assert_eq!(result, content);
+    }
+
+    #[test]
+    fn test_division_is_not_regex() {
+        let content =
+            "const ratio = a / b; // half\nconst c = (x + y) / 2 / z;\nexport const d = 1;";
+        let result = strip_comments(content);
+        assert_eq!(
+            result,
+            "const ratio = a / b; \nconst c = (x + y) / 2 / z;\nexport const d = 1;"
+        );
+    }
+
+    #[test]
+    fn test_regex_literal_does_not_drop_following_exports() {
+        let content = r#"const SEPARATOR_RE = /[/*]+/g;
+export class Linkifier {}"#;
+        let parser = Parser::new(Path::new("/project"));
+        let result = parser.parse_content(content, "/project/src/linkifier.ts");
+
+        assert_eq!(result.entities.len(), 1);
+        assert_eq!(result.entities[0].name, "Linkifier");
+    }
+
+    #[test]
+    fn test_skip_commented_import() {
+        let content = r#"// import { Foo } from './foo';
+import { Bar } from './bar';"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/src/index.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].name, "Bar");
+    }
+
+    #[test]
+    fn test_skip_multiline_commented_import() {
+        let content = r#"/* import { Foo } from './foo'; */
+import { Bar } from './bar';"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/src/index.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].name, "Bar");
+    }
+
+    #[test]
+    fn test_skip_import_inside_multiline_comment() {
+        let content = r#"/*
+import { Foo } from './foo';
+import { Baz } from './baz';
+*/
+import { Bar } from './bar';"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/src/index.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].name, "Bar");
+    }
+
+    #[test]
+    fn test_extract_angular_lazy_loaded_import() {
+        let content = r#"const routes: Routes = [
+    {
+        path: 'auth',
+        loadChildren: () => import('./auth/auth.module').then(m => m.AuthModule)
+    }
+];"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/src/app-routing.module.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].name, "AuthModule");
+        assert!(imports[0].path.contains("auth/auth.module"));
+    }
+
+    #[test]
+    fn test_extract_multiple_angular_lazy_loaded_imports() {
+        let content = r#"const routes: Routes = [
+    {
+        path: 'auth',
+        loadChildren: () => import('./auth/auth.module').then(m => m.AuthModule)
+    },
+    {
+        path: 'dashboard',
+        loadChildren: () => import('./dashboard/dashboard.module').then(mod => mod.DashboardModule)
+    }
+];"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/src/app-routing.module.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 2);
+        assert_eq!(imports[0].name, "AuthModule");
+        assert_eq!(imports[1].name, "DashboardModule");
+    }
+
+    #[test]
+    fn test_extract_lazy_import_with_different_param_names() {
+        let content = r#"loadChildren: () => import('./users/users.module').then(module => module.UsersModule)"#;
+        let root_path = Path::new("/project");
+        let file_path = "/project/src/app-routing.module.ts";
+
+        let parser = Parser::new(root_path);
+        let imports = parser.extract_imports(content, file_path);
+
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].name, "UsersModule");
+    }
+
+    #[test]
+    fn test_extract_lazy_import_with_parenthesized_param() {
+        let content = r#"const routes: Routes = [
+    { path: 'a', loadChildren: () => import('./a/a.module').then((m) => m.AModule) },
+    {
+        path: 'b',
+        loadChildren: () =>
+            import('./b/b.module')
+                .then((m: any,) => m.BModule),
+    },
+];"#;
+        let parser = Parser::new(Path::new("/project"));
+        let imports = parser.extract_imports(content, "/project/src/app.routes.ts");
+
+        let names: Vec<&str> = imports.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["AModule", "BModule"]);
+    }
+
+    #[test]
+    fn test_extract_lazy_import_with_async_and_block_body() {
+        let content = r#"const routes: Routes = [
+    { path: 'a', loadChildren: async () => import('./a/a.module').then(async m => m.AModule) },
+    {
+        path: 'b',
+        loadChildren: () => {
+            return import('./b/b.module').then(m => {
+                return m.BModule;
+            });
+        },
+    },
+];"#;
+        let parser = Parser::new(Path::new("/project"));
+        let imports = parser.extract_imports(content, "/project/src/app.routes.ts");
+
+        let names: Vec<&str> = imports.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["AModule", "BModule"]);
+    }
+
+    #[test]
+    fn test_extract_bare_dynamic_import_as_whole_file() {
+        let content = "await import('./x');\n\
+                       const m = await import('./y');\n\
+                       const routes = () => import('./lazy').then(m => m.LazyModule);";
+        let parser = Parser::new(Path::new("/project"));
+        let imports = parser.extract_imports(content, "/project/src/app.ts");
+
+        let found: Vec<(&str, &str)> = imports
+            .iter()
+            .map(|i| (i.name.as_str(), i.path.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("LazyModule", "unresolved:/project/src/./lazy"),
+                ("*", "unresolved:/project/src/./x"),
+                ("*", "unresolved:/project/src/./y"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_destructured_dynamic_import() {
+        let content = "const { Foo, Bar: Baz } = await import('./y');";
+        let parser = Parser::new(Path::new("/project"));
+        let imports = parser.extract_imports(content, "/project/src/app.ts");
+
+        let names: Vec<&str> = imports.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["Foo", "Bar"]);
+        assert!(
+            imports
+                .iter()
+                .all(|i| i.path == "unresolved:/project/src/./y")
+        );
+    }
+
+    #[test]
+    fn test_bare_dynamic_import_marks_whole_file_used() {
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("x.ts"), "export class A {}\nexport const b = 1;\n").unwrap();
+        std::fs::write(src.join("other.ts"), "export class Other {}\n").unwrap();
+        std::fs::write(
+            src.join("app.ts"),
+            "export async function start() {\n  await import('./x');\n}\n",
+        )
+        .unwrap();
+
+        let result = scan_and_parse_files(temp_dir.path(), &ScanOptions::default(), false).unwrap();
+        let used = |name: &str| {
+            result
+                .entities
+                .values()
+                .find(|e| e.name == name)
+                .unwrap()
+                .used
+        };
+
+        assert!(used("A"));
+        assert!(used("b"));
+        assert!(!used("Other"));
+        assert!(!result.entities.values().any(|e| e.name == "*"));
+    }
+
+    #[test]
+    fn test_enums_used_through_member_access_are_used() {
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("color.ts"), "export enum Color { Red, Blue }\n").unwrap();
+        std::fs::write(
+            src.join("models.ts"),
+            "export enum Size { S, M }\nexport enum Shape { Round }\nexport enum Unused { A }\n",
+        )
+        .unwrap();
+        std::fs::write(
+            src.join("app.ts"),
+            "import { Color } from './color';\n\
+             import * as models from './models';\n\n\
+             export const red = Color.Red;\n\
+             export const size = models.Size.M;\n\
+             export const round = models['Shape']['Round'];\n",
+        )
+        .unwrap();
+
+        let result = scan_and_parse_files(temp_dir.path(), &ScanOptions::default(), false).unwrap();
+        let used = |name: &str| {
+            result
+                .entities
+                .values()
+                .find(|e| e.name == name && e.entity_type == EntityType::Enum)
+                .unwrap()
+                .used
+        };
+
+        assert!(used("Color"));
+        assert!(used("Size"));
+        assert!(used("Shape"));
+        assert!(!used("Unused"));
+    }
+
+    #[test]
+    fn test_namespace_import_used_as_a_whole_uses_every_export() {
+        let content =
+            "import * as models from './models';\n\nexport const all = Object.keys(models);\n";
+        let parser = Parser::new(Path::new("/project"));
+        let imports = parser.extract_imports(content, "/project/src/app.ts");
+
+        let names: Vec<&str> = imports.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, [crate::parser::WHOLE_FILE_IMPORT]);
+    }
+
+    #[test]
+    fn test_arrow_table_const_is_not_function() {
+        let content = "export const HANDLERS = { a: () => {} };";
+        let parser = Parser::new(Path::new("/project"));
+        let result = parser.parse_content(content, "/project/src/handlers.ts");
+
+        assert_eq!(result.entities.len(), 1);
+        assert_eq!(result.entities[0].name, "HANDLERS");
+        assert!(matches!(result.entities[0].entity_type, EntityType::Const));
+    }
+
+    #[test]
+    fn test_top_level_arrow_and_function_expressions_are_functions() {
+        let content = r#"export const add = (a: number, b: number) => a + b;
+export const load = async () => {
+export const double = x => x * 2;
+export const legacy = function () {
+export const typed: (a: string) => void = (a) => {};"#;
+        let parser = Parser::new(Path::new("/project"));
+        let result = parser.parse_content(content, "/project/src/utils.ts");
+
+        assert_eq!(result.entities.len(), 5);
+        for entity in &result.entities {
+            assert!(
+                matches!(entity.entity_type, EntityType::Function),
+                "{} should be a function",
+                entity.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_arrow_nested_in_call_or_array_is_const() {
+        let content = r#"export const ROUTES = [{ path: '', load: () => import('./a') }];
+export const selectUser = createSelector(selectState, (state) => state.user);
+export const typedValue: () => void = noop;"#;
+        let parser = Parser::new(Path::new("/project"));
+        let result = parser.parse_content(content, "/project/src/routes.ts");
+
+        assert_eq!(result.entities.len(), 3);
+        for entity in &result.entities {
+            assert!(
+                matches!(entity.entity_type, EntityType::Const),
+                "{} should be a const",
+                entity.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_matches_type_filter_on_mixed_entities() {
+        use super::entity::Entity;
+        use std::rc::Rc;
+
+        let entities: Vec<Entity> = [
+            ("Service", EntityType::Class),
+            ("Color", EntityType::Enum),
+            ("User", EntityType::Interface),
+            ("helper", EntityType::Function),
+        ]
+        .into_iter()
+        .map(|(name, entity_type)| {
+            Entity::new(
+                name.to_string(),
+                entity_type,
+                "/src/mixed.ts".to_string(),
+                Rc::new(vec![]),
+            )
+        })
+        .collect();
+
+        let filter = [EntityType::Class, EntityType::Interface];
+        let names: Vec<&str> = entities
+            .iter()
+            .filter(|e| super::matches_type_filter(e, &filter))
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Service", "User"]);
+
+        let all = entities
+            .iter()
+            .filter(|e| super::matches_type_filter(e, &[]))
+            .count();
+        assert_eq!(all, 4);
+    }
+
+    #[test]
+    fn test_export_enum_yields_single_enum() {
+        let parser = Parser::new(Path::new("/project"));
+        let result = parser.parse_content(
+            "export enum Status { Active, Inactive }",
+            "/project/src/status.ts",
+        );
+
+        assert_eq!(result.entities.len(), 1);
+        assert_eq!(result.entities[0].name, "Status");
+        assert!(matches!(result.entities[0].entity_type, EntityType::Enum));
+    }
+
+    #[test]
+    fn test_export_const_enum_yields_single_enum() {
+        let parser = Parser::new(Path::new("/project"));
+        let result = parser.parse_content(
+            "export const enum Color {\n  Red,\n  Blue,\n}",
+            "/project/src/color.ts",
+        );
+
+        assert_eq!(result.entities.len(), 1);
+        assert_eq!(result.entities[0].name, "Color");
+        assert!(matches!(result.entities[0].entity_type, EntityType::Enum));
+    }
+
+    #[test]
+    fn test_export_const_with_keyword_in_name_yields_single_const() {
+        let parser = Parser::new(Path::new("/project"));
+        let result = parser.parse_content(
+            "export const userType = 'admin';\nexport const classNames = ['a', 'b'];",
+            "/project/src/user.ts",
+        );
+
+        assert_eq!(result.entities.len(), 2);
+        assert_eq!(result.entities[0].name, "userType");
+        assert!(matches!(result.entities[0].entity_type, EntityType::Const));
+        assert_eq!(result.entities[1].name, "classNames");
+        assert!(matches!(result.entities[1].entity_type, EntityType::Const));
+    }
+
+    #[test]
+    fn test_export_comma_separated_declarations() {
+        let parser = Parser::new(Path::new("/project"));
+        let result = parser.parse_content(
+            "export const a = 1, b = 2, handler = (x: number, y: number) => x + y;",
+            "/project/src/values.ts",
+        );
+
+        let found: Vec<(&str, &EntityType)> = result
+            .entities
+            .iter()
+            .map(|e| (e.name.as_str(), &e.entity_type))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("a", &EntityType::Const),
+                ("b", &EntityType::Const),
+                ("handler", &EntityType::Function),
+            ]
+        );
+        assert_ne!(result.entities[0].id, result.entities[1].id);
+    }
+
+    #[test]
+    fn test_export_destructured_declarations() {
+        let parser = Parser::new(Path::new("/project"));
+        let content = "export const { x, y: renamed, z = 3, ...others } = obj;\n\
+                       export const [first, , second] = list;";
+        let result = parser.parse_content(content, "/project/src/values.ts");
+
+        let names: Vec<&str> = result.entities.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["x", "renamed", "z", "others", "first", "second"]
+        );
+        assert!(
+            result
+                .entities
+                .iter()
+                .all(|e| matches!(e.entity_type, EntityType::Const))
+        );
+    }
+
+    #[test]
+    fn test_export_declaration_classified_by_leading_keyword() {
+        let parser = Parser::new(Path::new("/project"));
+        let content = r#"export type Handler = (event: string) => void;
+export function typeGuard(value: unknown): value is Handler { return true; }
+export interface EnumOptions { values: string[]; }
+export default abstract class BaseClass {}
+"#;
+        let result = parser.parse_content(content, "/project/src/types.ts");
+
+        let found: Vec<(&str, &EntityType)> = result
+            .entities
+            .iter()
+            .map(|e| (e.name.as_str(), &e.entity_type))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("Handler", &EntityType::Type),
+                ("typeGuard", &EntityType::Function),
+                ("EnumOptions", &EntityType::Interface),
+                ("BaseClass", &EntityType::Class),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_name_in_comment_is_not_local_usage() {
+        let parser = Parser::new(Path::new("/project"));
+        let content = "// Legacy is kept for reference\n\
+                       export class Legacy {}\n\
+                       /* Legacy\n   Legacy */\n";
+        let result = parser.parse_content(content, "/project/src/legacy.ts");
+
+        assert_eq!(result.entities.len(), 1);
+        assert_eq!(result.entities[0].line, 2);
+        assert!(!result.entities[0].used_locally);
+        assert!(!result.entities[0].used);
+    }
+
+    #[test]
+    fn test_name_in_string_is_not_local_usage() {
+        let parser = Parser::new(Path::new("/project"));
+        let content = r#"export const Config = {};
+export const label = 'Config';
+export const message = `Config is ${"Config"}`;
+"#;
+        let result = parser.parse_content(content, "/project/src/config.ts");
+
+        let config = result.entities.iter().find(|e| e.name == "Config").unwrap();
+        assert!(!config.used_locally);
+    }
+
+    #[test]
+    fn test_reference_outside_declaration_is_local_usage() {
+        let parser = Parser::new(Path::new("/project"));
+        let content = "export const base = 1;\nexport const derived = `${base + 1}`;\n";
+        let result = parser.parse_content(content, "/project/src/values.ts");
+
+        let base = result.entities.iter().find(|e| e.name == "base").unwrap();
+        assert!(base.used_locally);
+        let derived = result
+            .entities
+            .iter()
+            .find(|e| e.name == "derived")
+            .unwrap();
+        assert!(!derived.used_locally);
+    }
+
+    #[test]
+    fn test_extends_and_implements_record_dependencies_on_bases() {
+        let content = r#"import { OnInit } from '@angular/core';
+import { Repository } from './repository';
+
+export abstract class Bar {}
+export interface Named { name: string }
+
+export class Foo<T extends object>
+  extends Bar
+  implements OnInit, Named, Repository<T> {
+  ngOnInit() {}
+}
+"#;
+        let parser = Parser::new(Path::new("/project"));
+        let file_path = "/project/src/foo.ts";
+        let result = parser.parse_content(content, file_path);
+        let foo = result.entities.iter().find(|e| e.name == "Foo").unwrap();
+
+        let bases: Vec<&str> = foo.bases.iter().map(|base| base.name.as_str()).collect();
+        assert_eq!(bases, ["Bar", "Named", "Repository"]);
+        assert!(
+            foo.deps
+                .iter()
+                .any(|dep| dep.id == crate::entity::generate_entity_id(file_path, "Bar"))
+        );
+        assert!(foo.deps.iter().any(|dep| dep.name == "Named"));
+
+        // Entities without bases keep sharing the file's imports
+        let bar = result.entities.iter().find(|e| e.name == "Bar").unwrap();
+        assert!(bar.bases.is_empty());
+        assert_eq!(bar.deps.len(), 1);
+    }
+
+    #[test]
+    fn test_entity_type_rules_classify_before_built_in_types() {
+        use super::EntityTypeRule;
+
+        let content = r#"import { createStore } from '@ngneat/elf';
+
+export const FooStore = createStore({ name: 'foo' });
+export const BAR = 1;
+export class BarStore {}
+"#;
+        let rules: Vec<EntityTypeRule> = vec![
+            r"store=\bcreateStore\(".parse().unwrap(),
+            r"const=class \w+Store\b".parse().unwrap(),
+        ];
+        let parser = Parser::new(Path::new("/project")).with_entity_type_rules(&rules);
+        let result = parser.parse_content(content, "/project/src/foo.store.ts");
+        let entity_type = |name: &str| {
+            result
+                .entities
+                .iter()
+                .find(|e| e.name == name)
+                .unwrap()
+                .entity_type
+                .clone()
+        };
+
+        assert_eq!(
+            entity_type("FooStore"),
+            EntityType::Custom("store".to_string())
+        );
+        assert_eq!(entity_type("FooStore").to_string(), "store");
+        assert_eq!(entity_type("BAR"), EntityType::Const);
+        assert_eq!(entity_type("BarStore"), EntityType::Const);
+    }
+
+    #[test]
+    fn test_identifiers_in_array_and_object_literals_are_usages() {
+        let content = r#"import { Store, Logger } from './services';
+
+export class AuthService {}
+export class ApiService {}
+export class Unused {}
+
+export const APP_PROVIDERS = { providers: [AuthService], deps: { ApiService } };
+export const inject = () => ({ providers: [Store,
+  Logger] });
+"#;
+        let parser = Parser::new(Path::new("/project"));
+        let result = parser.parse_content(content, "/project/src/app.config.ts");
+        let used_locally = |name: &str| {
+            result
+                .entities
+                .iter()
+                .find(|e| e.name == name)
+                .unwrap()
+                .used_locally
+        };
+
+        assert!(used_locally("AuthService"));
+        assert!(used_locally("ApiService"));
+        assert!(!used_locally("Unused"));
+        assert!(
+            parser
+                .unused_imports(content, "/project/src/app.config.ts")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_shadowing_and_own_declaration_are_not_local_usage() {
+        let parser = Parser::new(Path::new("/project"));
+        let content = r#"export function foo(foo: Foo): Foo {
+    return foo;
+}
+
+export function format(value: string) {
+    const label = value.trim();
+    return label;
+}
+
+export const label = 'x';
+
+export function run(items: string[], config: Config) {
+    items.forEach((label) => console.log(label));
+    return config.label;
+}
+
+export class Config {}
+
+export function handle() {
+    try {
+        run([], new Config());
+    } catch (format) {
+        throw format;
+    }
+}
+"#;
+        let result = parser.parse_content(content, "/project/src/shadow.ts");
+        let used_locally = |name: &str| {
+            result
+                .entities
+                .iter()
+                .find(|e| e.name == name)
+                .unwrap()
+                .used_locally
+        };
+
+        assert!(!used_locally("foo"));
+        assert!(!used_locally("format"));
+        assert!(!used_locally("label"));
+        assert!(used_locally("run"));
+        assert!(used_locally("Config"));
+    }
+
+    #[test]
+    fn test_mask_string_literals_keeps_quotes_and_interpolations() {
+        assert_eq!(
+            mask_string_literals(r#"a('x\'y', "z", `t ${b} u`)"#),
+            r#"a('    ', " ", `  ${b}  `)"#
+        );
+        assert_eq!(mask_string_literals("'a\nb'"), "' \n '");
+    }
+
+    #[test]
+    fn test_export_assignment_records_declared_entity() {
+        let parser = Parser::new(Path::new("/project"));
+        let content = "import x = require('./x');\n\n\
+                       class Legacy {\n  run() {}\n}\n\n\
+                       export = Legacy;\n";
+        let result = parser.parse_content(content, "/project/src/legacy.ts");
+
+        assert_eq!(result.entities.len(), 1);
+        assert_eq!(result.entities[0].name, "Legacy");
+        assert_eq!(result.entities[0].line, 3);
+        assert!(matches!(result.entities[0].entity_type, EntityType::Class));
+        assert!(!result.entities[0].used_locally);
+    }
+
+    #[test]
+    fn test_export_list_renames_local_declaration() {
+        let parser = Parser::new(Path::new("/project")).with_internal_declarations(true);
+        let content = "export {\n  internalFoo as Foo,\n  bar,\n};\n\
+                       export { Baz } from './baz';\n\n\
+                       function internalFoo() {}\n\
+                       const bar = 1;\n";
+        let result = parser.parse_content(content, "/project/src/foo.ts");
+
+        let entity = |name: &str| {
+            result
+                .entities
+                .iter()
+                .find(|e| e.name == name)
+                .unwrap_or_else(|| panic!("missing entity {}", name))
+        };
+        let foo = entity("Foo");
+        assert!(foo.exported);
+        assert_eq!(foo.line, 7);
+        assert!(matches!(foo.entity_type, EntityType::Function));
+        assert!(!foo.used);
+
+        let internal = entity("internalFoo");
+        assert!(!internal.exported);
+        assert!(internal.used && internal.used_locally);
+
+        let bar = entity("bar");
+        assert!(bar.exported);
+        assert!(!bar.used_locally);
+        assert!(!result.entities.iter().any(|e| e.name == "Baz"));
+    }
+
+    #[test]
+    fn test_extract_import_require() {
+        let content = "import Legacy = require('./legacy');\nimport fs = require('fs');";
+        let parser = Parser::new(Path::new("/project"));
+        let imports = parser.extract_imports(content, "/project/src/app.ts");
+
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].name, "Legacy");
+        assert!(imports[0].path.ends_with("/legacy"));
+    }
+
+    #[test]
+    fn test_extract_re_exports() {
+        let content = "export * from './button';\n\
+                       export * as icons from './icons';\n\
+                       export {\n  Card,\n  CardHeader as Header,\n} from './card';\n\
+                       export { Foo };\n";
+        let parser = Parser::new(Path::new("/project"));
+        let re_exports = parser.extract_re_exports(content, "/project/src/index.ts");
+
+        let names: Vec<(&str, &str)> = re_exports
+            .iter()
+            .map(|r| (r.exported_name.as_str(), r.origin.name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("*", "*"),
+                ("icons", "*"),
+                ("Card", "Card"),
+                ("Header", "CardHeader")
+            ]
+        );
+        assert!(re_exports[0].origin.path.ends_with("/button"));
+        assert!(re_exports[3].origin.path.ends_with("/card"));
+    }
+
+    #[test]
+    fn test_pure_barrel_is_reported_but_mixed_file_is_not() {
+        use super::analysis::barrel_files;
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/ui/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("button.ts"), "export class Button {}\n").unwrap();
+        std::fs::write(
+            src.join("index.ts"),
+            "export * from './button';\nexport { Button as Btn } from './button';\n",
+        )
+        .unwrap();
+        std::fs::write(
+            src.join("mixed.ts"),
+            "export * from './button';\nexport const size = 1;\n",
+        )
+        .unwrap();
+
+        let result = scan_and_parse_files(temp_dir.path(), &ScanOptions::default(), false).unwrap();
+        let barrels = barrel_files(&result.entities, &result.file_re_exports);
+
+        assert_eq!(barrels.len(), 1);
+        assert!(barrels[0].ends_with("index.ts"));
+    }
+
+    #[test]
+    fn test_broken_imports_are_the_ones_resolving_to_missing_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().canonicalize().unwrap().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("foo.ts"), "export class Foo {}\n").unwrap();
+        let content = "import { Foo } from './foo';\n\
+                       import { Gone } from './missing';\n\
+                       import { map } from 'rxjs';\n\
+                       export { Gone } from './missing';\n\
+                       const lazy = () => import('./lazy.ts');\n";
+        let file_path = normalize_path(&src.join("app.ts"));
+
+        let parser = Parser::new(temp_dir.path());
+        let broken = parser.broken_imports(content, &file_path);
+
+        let sources: Vec<&str> = broken.iter().map(|b| b.source.as_str()).collect();
+        assert_eq!(sources, ["./missing", "./lazy.ts"]);
+        assert_eq!(
+            broken[0].resolved_path,
+            normalize_path(&src.join("./missing"))
+        );
+    }
+
+    #[test]
+    fn test_unused_imports_reports_only_unreferenced_names() {
+        let content = r#"import { Used, Unused } from './models';
+import { Original as Renamed } from './original';
+import type { Props } from './props';
+import Widget from './widget';
+// Unused is only mentioned in this comment
+
+export const create = (props: Props): Used => new Renamed(props);
+export const view = () => <Widget />;
+"#;
+        let parser = Parser::new(Path::new("/project"));
+        let unused = parser.unused_imports(content, "/project/src/view.tsx");
+
+        let names: Vec<&str> = unused.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["Unused"]);
+    }
+
+    #[test]
+    fn test_types_used_only_in_member_annotations_are_not_unused() {
+        let content = r#"import { Component, EventEmitter, Input, Output } from '@angular/core';
+import { User } from './user';
+import type { Team } from './team';
+import { Role } from './role';
+import { Unused } from './unused';
+
+export interface Selection {
+  user: User;
+}
+
+@Component({ selector: 'app-user' })
+export class UserComponent {
+  @Input() user!: User;
+  @Input() teams?: Array<Team>;
+  @Output() roleChange = new EventEmitter<Role>();
+  @Output() selected = new EventEmitter<Selection>();
+}
+"#;
+        let parser = Parser::new(Path::new("/project"));
+        let unused = parser.unused_imports(content, "/project/src/user.component.ts");
+        let names: Vec<&str> = unused.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["Unused"]);
+
+        let result = parser.parse_content(content, "/project/src/user.component.ts");
+        let selection = result
+            .entities
+            .iter()
+            .find(|e| e.name == "Selection")
+            .unwrap();
+        assert!(selection.used_locally);
+    }
+
+    #[test]
+    fn test_component_rendered_only_in_jsx_is_used() {
+        let content = r#"import { Card } from './card';
+
+export function Badge() {
+  return <span />;
+}
+
+export const Page = () => (
+  <main>
+    <p>Don't forget</p>
+    <Badge />
+    <Card.Header title="x" />
+  </main>
+);
+"#;
+        let parser = Parser::new(Path::new("/project"));
+
+        let unused = parser.unused_imports(content, "/project/src/page.tsx");
+        assert!(unused.is_empty());
+
+        let result = parser.parse_content(content, "/project/src/page.tsx");
+        let badge = result.entities.iter().find(|e| e.name == "Badge").unwrap();
+        assert!(badge.used_locally);
+    }
+
+    #[test]
+    fn test_is_test_file_spec_ts() {
+        assert!(super::is_test_file("/path/to/foo.spec.ts"));
+        assert!(super::is_test_file("foo.spec.ts"));
+    }
+
+    #[test]
+    fn test_is_test_file_test_ts() {
+        assert!(super::is_test_file("/path/to/foo.test.ts"));
+        assert!(super::is_test_file("foo.test.ts"));
+    }
+
+    #[test]
+    fn test_is_test_file_module_extensions() {
+        assert!(super::is_test_file("/path/to/foo.spec.mts"));
+        assert!(super::is_test_file("/path/to/foo.test.cts"));
+    }
+
+    #[test]
+    fn test_is_test_file_non_test_files() {
+        assert!(!super::is_test_file("/path/to/foo.ts"));
+        assert!(!super::is_test_file("/path/to/foo.spec.tsx"));
+        assert!(!super::is_test_file("/path/to/foo.test.tsx"));
+        assert!(!super::is_test_file("/path/to/spec.ts.bak"));
+    }
+
+    #[test]
+    fn test_find_test_files_in_directories() {
+        use std::collections::HashSet;
+        use std::fs::{self, File};
+
+        // Create a temp directory with test files
+        let temp_dir = std::env::temp_dir().join("sting_test_find_tests");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        // Create test files
+        File::create(temp_dir.join("foo.spec.ts")).unwrap();
+        File::create(temp_dir.join("bar.test.ts")).unwrap();
+        File::create(temp_dir.join("baz.ts")).unwrap();
+
+        let mut dirs: HashSet<String> = HashSet::new();
+        dirs.insert(temp_dir.to_string_lossy().to_string());
+
+        let result = super::find_test_files_in_directories(&dirs);
+
+        assert_eq!(result.len(), 2);
+        assert!(result.iter().any(|p| p.ends_with("foo.spec.ts")));
+        assert!(result.iter().any(|p| p.ends_with("bar.test.ts")));
+        assert!(!result.iter().any(|p| p.ends_with("baz.ts")));
+
+        // Cleanup
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_entity_line_matches_declaration() {
+        let parser = Parser::new(Path::new("/project"));
+        let content = r#"import { Foo } from './foo';
+
+/**
+ * Multi-line doc comment
+ */
+export class First {}
+/* inline */ export const second = 1, third = 2;
+
+export function fourth() {}
+"#;
+        let result = parser.parse_content(content, "/project/src/lines.ts");
+
+        let lines: Vec<(&str, usize)> = result
+            .entities
+            .iter()
+            .map(|e| (e.name.as_str(), e.line))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![("First", 6), ("second", 7), ("third", 7), ("fourth", 9)]
+        );
+        assert_eq!(result.entities[0].location(), "/project/src/lines.ts:6");
+    }
+
+    #[test]
+    fn test_export_split_across_lines() {
+        let parser = Parser::new(Path::new("/project"));
+        let content = r#"export
+class First {}
+
+export default
+  abstract class Second {}
+
+export class
+    Third {}
+
+export
+@Injectable()
+class Fourth {}
+
+export const enum
+Fifth { A }
+"#;
+        let result = parser.parse_content(content, "/project/src/split.ts");
+
+        let entities: Vec<(&str, String, usize)> = result
+            .entities
+            .iter()
+            .map(|e| (e.name.as_str(), e.entity_type.to_string(), e.line))
+            .collect();
+        assert_eq!(
+            entities,
+            vec![
+                ("First", "class".to_string(), 1),
+                ("Second", "class".to_string(), 4),
+                ("Third", "class".to_string(), 7),
+                ("Fourth", "class".to_string(), 10),
+                ("Fifth", "enum".to_string(), 14),
+            ]
+        );
+        assert!(result.entities.iter().all(|e| !e.used_locally));
+    }
+
+    #[test]
+    fn test_export_namespace_and_module() {
+        let parser = Parser::new(Path::new("/project"));
+        let content = r#"export namespace Foo {}
+
+export declare namespace Validation {
+    const pattern: RegExp;
+}
+
+export module Legacy {
+}
+
+declare module 'external-lib' {
+}
+"#;
+        let result = parser.parse_content(content, "/project/src/namespaces.ts");
+
+        let entities: Vec<(&str, &EntityType, usize)> = result
+            .entities
+            .iter()
+            .map(|e| (e.name.as_str(), &e.entity_type, e.line))
+            .collect();
+        assert_eq!(
+            entities,
+            vec![
+                ("Foo", &EntityType::Namespace, 1),
+                ("Validation", &EntityType::Namespace, 3),
+                ("Legacy", &EntityType::Namespace, 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_export_declare_forms() {
+        let parser = Parser::new(Path::new("/project"));
+        let content = r#"export declare class Foo {
+    run(): void;
+}
+export declare abstract class Base {}
+export declare function bar(value: string): void;
+export declare function bar(value: number): void;
+export declare const VERSION: string;
+export declare const handler: (event: Event) => void;
+export declare let counter: number;
+export declare enum Color { Red }
+export declare const enum Flag { On }
+export declare type Id = string;
+export declare interface Shape {}
+"#;
+        let result = parser.parse_content(content, "/project/src/ambient.ts");
+
+        let entities: Vec<(&str, &EntityType)> = result
+            .entities
+            .iter()
+            .map(|e| (e.name.as_str(), &e.entity_type))
+            .collect();
+        assert_eq!(
+            entities,
+            vec![
+                ("Foo", &EntityType::Class),
+                ("Base", &EntityType::Class),
+                ("bar", &EntityType::Function),
+                ("VERSION", &EntityType::Const),
+                ("handler", &EntityType::Const),
+                ("counter", &EntityType::Const),
+                ("Color", &EntityType::Enum),
+                ("Flag", &EntityType::Enum),
+                ("Id", &EntityType::Type),
+                ("Shape", &EntityType::Interface),
+            ]
+        );
+        // The second `bar` signature is an overload, not a use of `bar`
+        assert!(result.entities.iter().all(|e| !e.used_locally));
+    }
+
+    #[test]
+    fn test_decorator_metadata_marks_same_file_entity_used() {
+        let parser = Parser::new(Path::new("/project"));
+        let content = r#"export class FooService {}
+
+@Component({
+  selector: 'app-root',
+  providers: [
+    FooService,
+  ],
+})
+export class AppComponent {}
+"#;
+        let result = parser.parse_content(content, "/project/src/app.component.ts");
+
+        let service = result
+            .entities
+            .iter()
+            .find(|e| e.name == "FooService")
+            .unwrap();
+        assert!(service.used);
+        assert!(service.used_locally);
+        let component = result
+            .entities
+            .iter()
+            .find(|e| e.name == "AppComponent")
+            .unwrap();
+        assert!(!component.used);
     }
 
     #[test]
-    fn test_skip_external_package_imports() {
-        let content = r#"import { useState } from 'react';
-import { Observable } from 'rxjs';
-import { Foo } from './local';"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/index.ts";
+    fn test_service_referenced_only_in_providers_is_not_unused() {
+        use std::fs;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("apps/web/src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(
+            src.join("foo.service.ts"),
+            "@Injectable()\nexport class FooService {}\n",
+        )
+        .unwrap();
+        fs::write(
+            src.join("app.component.ts"),
+            "import { FooService } from './foo.service';\n\n\
+             @Component({\n  providers: [FooService],\n})\nexport class AppComponent {}\n",
+        )
+        .unwrap();
+
+        let options = super::ScanOptions::default();
+        let result = super::scan_and_parse_files(temp_dir.path(), &options, false).unwrap();
+
+        let service = result
+            .entities
+            .values()
+            .find(|e| e.name == "FooService")
+            .unwrap();
+        assert!(service.used);
+        assert!(matches!(service.entity_type, EntityType::Class));
+    }
 
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+    #[test]
+    fn test_diff_categorizes_entities_between_snapshots() {
+        use super::{OutputFormat, ScanOptions, diff};
+
+        let entity = |id: &str, name: &str, used: bool| {
+            format!(
+                r#"{{"id":"{}","name":"{}","entity_type":"Function","file_path":"/src/{}.ts","line":1,"used":{},"used_locally":false,"used_in_tests":false,"exported":true}}"#,
+                id, name, name, used
+            )
+        };
+        let temp_dir = tempfile::tempdir().unwrap();
+        let before = temp_dir.path().join("before.json");
+        let after = temp_dir.path().join("after.jsonl");
+        std::fs::write(
+            &before,
+            format!(
+                "[{}]",
+                [
+                    entity("1", "kept", true),
+                    entity("2", "removed", true),
+                    entity("3", "dropped", true),
+                    entity("4", "adopted", false),
+                ]
+                .join(",")
+            ),
+        )
+        .unwrap();
+        // The newer snapshot is written as JSON lines
+        std::fs::write(
+            &after,
+            [
+                entity("1", "kept", true),
+                entity("3", "dropped", false),
+                entity("4", "adopted", true),
+                entity("5", "added", false),
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let mut output = Vec::new();
+        diff(
+            &before,
+            &after,
+            &ScanOptions::default(),
+            OutputFormat::Json,
+            &mut output,
+        )
+        .unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        let names = |category: &str| -> Vec<String> {
+            report[category]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entity| entity["name"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        assert_eq!(names("added"), ["added"]);
+        assert_eq!(names("removed"), ["removed"]);
+        assert_eq!(names("newly_unused"), ["dropped"]);
+        assert_eq!(names("newly_used"), ["adopted"]);
+    }
 
-        assert_eq!(imports.len(), 1);
-        assert_eq!(imports[0].name, "Foo");
+    #[test]
+    fn test_diff_reports_entities_whose_declaration_changed() {
+        use super::{EntityFilter, OutputFormat, ScanOptions, SortKey, SortOrder, diff, query_all};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        let snapshot = |name: &str, changed: &str| {
+            std::fs::write(
+                src.join("a.ts"),
+                format!(
+                    "export function changed() {{\n  return {};\n}}\n\n\
+                     export class Untouched {{\n  run() {{}}\n}}\n",
+                    changed
+                ),
+            )
+            .unwrap();
+            let path = temp_dir.path().join(name);
+            let mut out = std::fs::File::create(&path).unwrap();
+            query_all(
+                temp_dir.path(),
+                &ScanOptions::default(),
+                EntityFilter::default(),
+                OutputFormat::Json,
+                SortOrder {
+                    key: SortKey::Id,
+                    reverse: false,
+                },
+                &mut out,
+            )
+            .unwrap();
+            path
+        };
+        let before = snapshot("before.json", "1");
+        // The longer body moves `Untouched` down, which does not modify it
+        let after = snapshot("after.json", "1 +\n    1");
+
+        let mut output = Vec::new();
+        diff(
+            &before,
+            &after,
+            &ScanOptions::default(),
+            OutputFormat::Json,
+            &mut output,
+        )
+        .unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        let modified: Vec<&str> = report["modified"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entity| entity["name"].as_str().unwrap())
+            .collect();
+
+        assert_eq!(modified, ["changed"]);
     }
 
     #[test]
-    fn test_extract_multiple_import_statements() {
-        let content = r#"import { Foo } from './foo';
-import { Bar, Baz } from './bar';
-import Default from './default';"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/index.ts";
+    fn test_diff_reports_modified_entity_imported_before_its_declaration() {
+        use super::{EntityFilter, OutputFormat, ScanOptions, SortKey, SortOrder, diff, query_all};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        // `a.ts` is parsed first, so `Foo` starts out as an import placeholder
+        std::fs::write(
+            src.join("a.ts"),
+            "import { Foo } from './b';\n\nexport const x = new Foo();\n",
+        )
+        .unwrap();
+        let snapshot = |name: &str, body: &str| {
+            std::fs::write(
+                src.join("b.ts"),
+                format!("export class Foo {{ {} }}\n", body),
+            )
+            .unwrap();
+            let path = temp_dir.path().join(name);
+            let mut out = std::fs::File::create(&path).unwrap();
+            query_all(
+                temp_dir.path(),
+                &ScanOptions::default(),
+                EntityFilter::default(),
+                OutputFormat::Json,
+                SortOrder {
+                    key: SortKey::Id,
+                    reverse: false,
+                },
+                &mut out,
+            )
+            .unwrap();
+            path
+        };
+        let before = snapshot("before.json", "");
+        let after = snapshot("after.json", "run() {}");
+
+        let mut output = Vec::new();
+        diff(
+            &before,
+            &after,
+            &ScanOptions::default(),
+            OutputFormat::Json,
+            &mut output,
+        )
+        .unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        let modified: Vec<&str> = report["modified"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entity| entity["name"].as_str().unwrap())
+            .collect();
+
+        assert_eq!(modified, ["Foo"]);
+    }
 
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+    #[test]
+    fn test_streamed_files_match_batch_scan() {
+        use super::filesystem::RealFileSystem;
+        use super::{ScanOptions, create_parser, scan_and_parse_files, stream_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(src.join("lib/nested")).unwrap();
+        std::fs::write(src.join("index.ts"), "export * from './lib/a';\n").unwrap();
+        std::fs::write(
+            src.join("lib/a.ts"),
+            "import { b } from './nested/b';\nexport const a = b;\n",
+        )
+        .unwrap();
+        std::fs::write(
+            src.join("lib/nested/b.ts"),
+            "\u{feff}import { a } from '../a';\nexport const b = 1;\n",
+        )
+        .unwrap();
+        std::fs::write(src.join("lib/notes.md"), "# not scanned\n").unwrap();
+
+        let options = ScanOptions::default();
+        let batch = scan_and_parse_files(temp_dir.path(), &options, false).unwrap();
+        let parser = create_parser(temp_dir.path(), &options);
+
+        let mut streamed = Vec::new();
+        stream_files(
+            &RealFileSystem,
+            temp_dir.path(),
+            &options,
+            |file, content| {
+                let imports: Vec<String> = parser
+                    .parse_content(content, file)
+                    .imports
+                    .iter()
+                    .map(|import| import.id.clone())
+                    .collect();
+                streamed.push((file.to_string(), imports));
+            },
+        )
+        .unwrap();
+
+        assert_eq!(streamed.len(), batch.file_imports.len());
+        for (file, imports) in &streamed {
+            let batch_imports: Vec<String> = batch.file_imports[file]
+                .iter()
+                .map(|import| import.id.clone())
+                .collect();
+            assert_eq!(imports, &batch_imports, "{}", file);
+        }
+    }
 
-        assert_eq!(imports.len(), 4);
-        assert_eq!(imports[0].name, "Foo");
-        assert_eq!(imports[1].name, "Bar");
-        assert_eq!(imports[2].name, "Baz");
-        assert_eq!(imports[3].name, "Default");
+    #[test]
+    fn test_stream_files_reads_an_in_memory_tree() {
+        use super::filesystem::MemoryFileSystem;
+        use super::{ScanOptions, create_parser, stream_files};
+
+        let root = Path::new("/project");
+        let file_system = MemoryFileSystem::new()
+            .with_file(
+                "/project/libs/a.ts",
+                "import { b } from './b';\nexport const a = b;\n",
+            )
+            .with_file(
+                "/project/libs/b.ts",
+                "import { gone } from './gone';\nexport const b = 1;\n",
+            )
+            .with_file("/project/libs/notes.md", "# not scanned\n");
+        let options = ScanOptions::default();
+        let parser = create_parser(root, &options).with_file_system(&file_system);
+
+        let mut broken = Vec::new();
+        stream_files(&file_system, root, &options, |file, content| {
+            for import in parser.broken_imports(content, file) {
+                broken.push((file.to_string(), import.source));
+            }
+        })
+        .unwrap();
+
+        assert_eq!(
+            broken,
+            [("/project/libs/b.ts".to_string(), "./gone".to_string())]
+        );
     }
 
     #[test]
-    fn test_extract_relative_parent_path_import() {
-        let content = r#"import { Util } from '../utils/helper';"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/components/button.ts";
+    fn test_validate_reports_files_in_overlapping_directories_once() {
+        use super::{ScanOptions, validate};
 
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("u.ts"), "import { gone } from './gone.ts';\n").unwrap();
 
-        assert_eq!(imports.len(), 1);
-        assert_eq!(imports[0].name, "Util");
-        assert!(imports[0].path.contains("utils"));
-        assert!(imports[0].path.contains("helper"));
+        let options = ScanOptions {
+            dirs: vec!["libs".to_string(), "libs/shared".to_string()],
+            ..ScanOptions::default()
+        };
+
+        assert_eq!(validate(temp_dir.path(), &options).unwrap(), 1);
     }
 
     #[test]
-    fn test_import_path_gets_ts_extension() {
-        let content = r#"import { Foo } from './foo';"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/bar.ts";
+    fn test_component_used_only_in_template_is_used_with_scan_templates() {
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(
+            src.join("card.component.ts"),
+            "@Component({ selector: 'app-card', template: '<p>card</p>' })\n\
+             export class CardComponent {}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            src.join("page.component.ts"),
+            "@Component({\n  selector: 'app-page',\n  templateUrl: './page.component.html',\n})\n\
+             export class PageComponent {}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            src.join("page.component.html"),
+            "<main>\n  <app-card></app-card>\n</main>\n",
+        )
+        .unwrap();
+
+        let used = |scan_templates: bool| {
+            let options = ScanOptions {
+                scan_templates,
+                ..ScanOptions::default()
+            };
+            let result = scan_and_parse_files(temp_dir.path(), &options, false).unwrap();
+            let mut used: Vec<String> = result
+                .entities
+                .values()
+                .filter(|entity| entity.used)
+                .map(|entity| entity.name.clone())
+                .collect();
+            used.sort();
+            used
+        };
 
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+        assert!(used(false).is_empty());
+        assert_eq!(used(true), ["CardComponent"]);
+    }
 
-        assert_eq!(imports.len(), 1);
-        assert!(imports[0].path.ends_with(".ts"));
+    #[test]
+    fn test_template_usage_names_the_first_owner_by_file() {
+        use super::entity::generate_entity_id;
+        use super::filesystem::MemoryFileSystem;
+        use super::report::{Reporter, Verbosity};
+        use super::{ScanOptions, scan_and_parse_files_with};
+
+        let src = "/project/libs/shared/src";
+        let mut file_system = MemoryFileSystem::new().with_file(
+            format!("{}/badge.component.ts", src),
+            "@Component({ selector: 'app-badge', template: '<b></b>' })\n\
+             export class BadgeComponent {}\n",
+        );
+        for owner in ["z-page", "a-page", "m-page"] {
+            file_system = file_system.with_file(
+                format!("{}/{}.component.ts", src, owner),
+                "@Component({ selector: 'app-page', template: '<app-badge></app-badge>' })\n\
+                 export class PageComponent {}\n",
+            );
+        }
+        let options = ScanOptions {
+            scan_templates: true,
+            ..ScanOptions::default()
+        };
+        let badge_id = generate_entity_id(&format!("{}/badge.component.ts", src), "BadgeComponent");
+
+        for _ in 0..10 {
+            let mut output = Vec::new();
+            let result = scan_and_parse_files_with(
+                &file_system,
+                Path::new("/project"),
+                &options,
+                &mut Reporter::new(Verbosity::Quiet, &mut output),
+            )
+            .unwrap();
+
+            assert_eq!(
+                result.entities[&badge_id]
+                    .used_by
+                    .as_ref()
+                    .unwrap()
+                    .to_string(),
+                format!(
+                    "used in the template of PageComponent in {}/a-page.component.ts",
+                    src
+                )
+            );
+        }
     }
 
     #[test]
-    fn test_multiline_import_with_trailing_comma() {
-        let content = r#"import {
-  Foo,
-  Bar,
-} from './utils';"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/index.ts";
+    fn test_quiet_scan_omits_progress_messages() {
+        use super::filesystem::RealFileSystem;
+        use super::report::Reporter;
+        use super::{ScanOptions, Verbosity, scan_and_parse_files_with};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("a.ts"), "export const a = 1;\n").unwrap();
+
+        let scan = |verbosity: Verbosity| {
+            let mut output = Vec::new();
+            let options = ScanOptions {
+                verbosity,
+                ..ScanOptions::default()
+            };
+            let result = scan_and_parse_files_with(
+                &RealFileSystem,
+                temp_dir.path(),
+                &options,
+                &mut Reporter::new(verbosity, &mut output),
+            )
+            .unwrap();
+            assert_eq!(result.entities.len(), 1);
+            String::from_utf8(output).unwrap()
+        };
+
+        let normal = scan(Verbosity::Normal);
+        assert!(normal.contains("Scanning directory"));
+        assert!(normal.contains("Processing 1 TypeScript files"));
+
+        assert_eq!(scan(Verbosity::Quiet), "");
+    }
 
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+    #[test]
+    fn test_why_used_reports_the_importing_file() {
+        use super::{ScanOptions, why_used};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(
+            src.join("a.ts"),
+            "export class A {}\nexport class Idle {}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            src.join("b.ts"),
+            "import { A } from './a';\n\nexport const b = new A();\n",
+        )
+        .unwrap();
+        let options = ScanOptions::default();
+
+        let reason = why_used(temp_dir.path(), &options, None, Some("A")).unwrap();
+        let importer = src.join("b.ts");
+        assert!(
+            reason.ends_with(&format!("imported by {}", importer.display())),
+            "{}",
+            reason
+        );
 
-        assert_eq!(imports.len(), 2);
-        assert_eq!(imports[0].name, "Foo");
-        assert_eq!(imports[1].name, "Bar");
+        let reason = why_used(temp_dir.path(), &options, None, Some("Idle")).unwrap();
+        assert!(reason.ends_with("not used"), "{}", reason);
+        assert!(why_used(temp_dir.path(), &options, None, Some("Missing")).is_err());
     }
 
     #[test]
-    fn test_import_info_has_id() {
-        let content = r#"import { Foo } from './foo';"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/bar.ts";
+    fn test_list_files_prints_the_scanned_files() {
+        use super::{ScanOptions, list_files};
 
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        for dir in ["lib", "mocks", "i18n"] {
+            std::fs::create_dir_all(src.join(dir)).unwrap();
+        }
+        for file in [
+            "index.ts",
+            "lib/button.tsx",
+            "lib/button.spec.ts",
+            "lib/types.d.ts",
+            "lib/notes.md",
+            "mocks/api.ts",
+            "i18n/messages.ts",
+        ] {
+            std::fs::write(src.join(file), "export const x = 1;\n").unwrap();
+        }
 
-        assert_eq!(imports.len(), 1);
-        assert!(!imports[0].id.is_empty());
+        let mut out = Vec::new();
+        let count = list_files(temp_dir.path(), &ScanOptions::default(), &mut out).unwrap();
+
+        let listed: Vec<String> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                line.strip_prefix(&format!("{}/", src.display()))
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(listed, ["index.ts", "lib/button.spec.ts", "lib/button.tsx"]);
+        assert_eq!(count, 3);
     }
 
     #[test]
-    fn test_strip_single_line_comment() {
-        let content = "const a = 1; // this is a comment\nconst b = 2;";
-        let result = strip_comments(content);
-        assert_eq!(result, "const a = 1; \nconst b = 2;");
+    fn test_overlapping_scan_dirs_parse_each_file_once() {
+        use super::filesystem::RealFileSystem;
+        use super::report::Reporter;
+        use super::{ScanOptions, Verbosity, scan_and_parse_files_with};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("a.ts"), "export const a = 1;\n").unwrap();
+        std::fs::write(
+            src.join("b.ts"),
+            "import { a } from './a';\n\nexport const b = a;\n",
+        )
+        .unwrap();
+
+        let mut output = Vec::new();
+        let options = ScanOptions {
+            dirs: vec!["libs".to_string(), "libs/shared".to_string()],
+            ..ScanOptions::default()
+        };
+        let result = scan_and_parse_files_with(
+            &RealFileSystem,
+            temp_dir.path(),
+            &options,
+            &mut Reporter::new(Verbosity::Normal, &mut output),
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("Skipping 2 files found in more than one scanned directory"));
+        assert!(output.contains("Processing 2 TypeScript files"));
+        assert_eq!(result.entities.len(), 2);
+        let b = result.entities.values().find(|e| e.name == "b").unwrap();
+        assert_eq!(b.deps.len(), 1);
     }
 
     #[test]
-    fn test_strip_multiline_comment() {
-        let content = "const a = 1; /* this is\na multiline\ncomment */ const b = 2;";
-        let result = strip_comments(content);
-        assert_eq!(result, "const a = 1;  const b = 2;");
+    fn test_unparsable_file_is_recorded_as_scan_error() {
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("good.ts"), "export const good = 1;\n").unwrap();
+        std::fs::write(src.join("binary.ts"), [0xff, 0xfe, 0x00, 0x80]).unwrap();
+
+        let result = scan_and_parse_files(temp_dir.path(), &ScanOptions::default(), false).unwrap();
+
+        assert_eq!(result.entities.len(), 1);
+        assert_eq!(result.errors.len(), 1);
+        assert!(result.errors[0].path.ends_with("binary.ts"));
+        assert!(result.errors[0].message.contains("not valid UTF-8"));
     }
 
     #[test]
-    fn test_strip_full_line_comment() {
-        let content = "// full line comment\nconst a = 1;";
-        let result = strip_comments(content);
-        assert_eq!(result, "\nconst a = 1;");
+    fn test_byte_order_mark_does_not_hide_first_line_export() {
+        let content = "\u{feff}export class First {}\nexport const second = 1;\n";
+        let parser = Parser::new(Path::new("/project"));
+        let result = parser.parse_content(content, "/project/src/bom.ts");
+
+        let names: Vec<(&str, usize)> = result
+            .entities
+            .iter()
+            .map(|e| (e.name.as_str(), e.line))
+            .collect();
+        assert_eq!(names, [("First", 1), ("second", 2)]);
     }
 
     #[test]
-    fn test_preserve_string_with_comment_like_content() {
-        let content = r#"const a = "// not a comment";"#;
-        let result = strip_comments(content);
-        assert_eq!(result, r#"const a = "// not a comment";"#);
+    fn test_import_through_barrel_marks_origin_used() {
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(
+            src.join("a.ts"),
+            "export class Foo {}\nexport class Unrelated {}\n",
+        )
+        .unwrap();
+        std::fs::write(src.join("b.ts"), "export { Foo } from './a';\n").unwrap();
+        std::fs::write(
+            src.join("c.ts"),
+            "import { Foo } from './b';\n\nexport const foo = new Foo();\n",
+        )
+        .unwrap();
+
+        let result = scan_and_parse_files(temp_dir.path(), &ScanOptions::default(), false).unwrap();
+        let declared = |name: &str| {
+            result
+                .entities
+                .values()
+                .find(|e| e.name == name && e.file_path.ends_with("a.ts"))
+                .unwrap()
+        };
+
+        assert!(declared("Foo").used);
+        assert!(!declared("Unrelated").used);
     }
 
     #[test]
-    fn test_preserve_string_with_multiline_comment_like_content() {
-        let content = r#"const a = "/* not a comment */";"#;
-        let result = strip_comments(content);
-        assert_eq!(result, r#"const a = "/* not a comment */";"#);
+    fn test_unused_with_entry_points_follows_barrel_re_exports() {
+        use super::{OutputFormat, ScanOptions, SortKey, SortOrder, UnusedQuery, unused};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let lib = temp_dir.path().join("libs/shared");
+        let app = temp_dir.path().join("apps/web");
+        std::fs::create_dir_all(&lib).unwrap();
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::write(lib.join("foo.ts"), "export class Foo {}\n").unwrap();
+        std::fs::write(
+            lib.join("bar.ts"),
+            "export class Bar {}\nexport class Dead {}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            lib.join("index.ts"),
+            "export { Foo } from './foo';\nexport * from './bar';\n",
+        )
+        .unwrap();
+        std::fs::write(
+            app.join("main.ts"),
+            "import { Foo, Bar } from '../../libs/shared';\n\nconsole.log(Foo, Bar);\n",
+        )
+        .unwrap();
+
+        let report = |options: &ScanOptions| {
+            let mut out = Vec::new();
+            let count = unused(
+                temp_dir.path(),
+                options,
+                UnusedQuery::default(),
+                OutputFormat::Json,
+                SortOrder {
+                    key: SortKey::File,
+                    reverse: false,
+                },
+                false,
+                &mut out,
+            )
+            .unwrap();
+            let reported: serde_json::Value = serde_json::from_slice(&out).unwrap();
+            (count, reported)
+        };
+
+        // The barrel declares nothing, and what it re-exports is reached through it
+        let (count, reported) = report(&ScanOptions {
+            entry_points: vec!["main.ts".to_string()],
+            ..ScanOptions::default()
+        });
+        assert_eq!(count, 1);
+        assert_eq!(reported[0]["name"], "Dead");
+
+        std::fs::write(
+            temp_dir.path().join(".nxalyzer.toml"),
+            "entry_points = [\"main.ts\"]\n",
+        )
+        .unwrap();
+        assert_eq!(report(&ScanOptions::default()).0, 1);
     }
 
     #[test]
-    fn test_skip_commented_import() {
-        let content = r#"// import { Foo } from './foo';
-import { Bar } from './bar';"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/index.ts";
-
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
-
-        assert_eq!(imports.len(), 1);
-        assert_eq!(imports[0].name, "Bar");
+    fn test_import_of_own_file_is_reported_as_self_import() {
+        use super::analysis::self_imports;
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(
+            src.join("thisfile.ts"),
+            "import { Other } from './other';\nimport { Own } from './thisfile';\n\n\
+             export class Own {}\n",
+        )
+        .unwrap();
+        std::fs::write(src.join("other.ts"), "export class Other {}\n").unwrap();
+
+        let result = scan_and_parse_files(temp_dir.path(), &ScanOptions::default(), false).unwrap();
+        let found = self_imports(&result.file_imports);
+
+        assert_eq!(found.len(), 1);
+        assert!(found[0].0.ends_with("thisfile.ts"));
+        assert_eq!(found[0].1.name, "Own");
     }
 
     #[test]
-    fn test_skip_multiline_commented_import() {
-        let content = r#"/* import { Foo } from './foo'; */
-import { Bar } from './bar';"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/index.ts";
-
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+    fn test_import_outside_scanned_directories_is_reported() {
+        use super::analysis::out_of_scope_imports;
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        let tools = temp_dir.path().join("tools/format");
+        let admin = temp_dir.path().join("apps/admin");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::create_dir_all(&tools).unwrap();
+        std::fs::create_dir_all(&admin).unwrap();
+        std::fs::write(
+            src.join("a.ts"),
+            "import { format } from '../../../tools/format/format';
+             import { Admin } from '../../../apps/admin/admin';
+             import { B } from './b';
+             import { Gone } from './gone';
+
+             export class A {}
+",
+        )
+        .unwrap();
+        std::fs::write(
+            src.join("b.ts"),
+            "export class B {}
+",
+        )
+        .unwrap();
+        std::fs::write(
+            tools.join("format.ts"),
+            "export function format() {}
+",
+        )
+        .unwrap();
+        std::fs::write(
+            admin.join("admin.ts"),
+            "export class Admin {}
+",
+        )
+        .unwrap();
+
+        let root = temp_dir.path().canonicalize().unwrap();
+        let result = scan_and_parse_files(&root, &ScanOptions::default(), false).unwrap();
+        let found = out_of_scope_imports(
+            &result.file_imports,
+            &normalize_path(&root),
+            &["apps/web", "apps/mobile", "libs"],
+        );
 
-        assert_eq!(imports.len(), 1);
-        assert_eq!(imports[0].name, "Bar");
+        let reported: Vec<(&str, &str)> = found
+            .iter()
+            .map(|item| (item.import.name.as_str(), item.suggested_dir.as_str()))
+            .collect();
+        assert_eq!(reported, [("Admin", "apps/admin"), ("format", "tools")]);
+        assert!(
+            found
+                .iter()
+                .all(|item| item.file.ends_with("libs/shared/src/a.ts"))
+        );
     }
 
     #[test]
-    fn test_skip_import_inside_multiline_comment() {
-        let content = r#"/*
-import { Foo } from './foo';
-import { Baz } from './baz';
-*/
-import { Bar } from './bar';"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/index.ts";
+    fn test_include_tests_scans_test_helpers() {
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(src.join("mocks")).unwrap();
+        std::fs::write(src.join("app.ts"), "export class App {}\n").unwrap();
+        std::fs::write(src.join("app.spec.ts"), "export const fixture = {};\n").unwrap();
+        std::fs::write(src.join("user.mock.ts"), "export class UserMock {}\n").unwrap();
+        std::fs::write(src.join("setup-test.ts"), "export function setup() {}\n").unwrap();
+        std::fs::write(src.join("mocks/api.ts"), "export class ApiMock {}\n").unwrap();
+
+        let names = |include_tests: bool| {
+            let options = ScanOptions {
+                include_tests,
+                ..ScanOptions::default()
+            };
+            let result = scan_and_parse_files(temp_dir.path(), &options, false).unwrap();
+            let mut names: Vec<String> = result.entities.into_values().map(|e| e.name).collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(names(false), ["App", "fixture"]);
+        assert_eq!(
+            names(true),
+            ["ApiMock", "App", "UserMock", "fixture", "setup"]
+        );
+    }
 
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+    #[test]
+    fn test_explicit_files_are_analyzed_without_scanning() {
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("a.ts"), "export class A {}\n").unwrap();
+        std::fs::write(
+            src.join("b.ts"),
+            "import { C } from './c';\n\nexport const b = new C();\n",
+        )
+        .unwrap();
+        std::fs::write(src.join("c.ts"), "export class C {}\n").unwrap();
+        std::fs::write(src.join("notes.md"), "# Notes\n").unwrap();
+
+        let options = ScanOptions {
+            files: vec![
+                "libs/shared/src/a.ts".to_string(),
+                "libs/shared/src/b.ts".to_string(),
+                "libs/shared/src/notes.md".to_string(),
+            ],
+            ..ScanOptions::default()
+        };
+        let result = scan_and_parse_files(temp_dir.path(), &options, false).unwrap();
+
+        let mut declared: Vec<&str> = result
+            .entities
+            .values()
+            .filter(|e| !matches!(e.entity_type, EntityType::Unknown))
+            .map(|e| e.name.as_str())
+            .collect();
+        declared.sort();
+        assert_eq!(declared, ["A", "b"]);
+        assert_eq!(result.file_imports.len(), 2);
+
+        // The import of `C` still resolves against the project root
+        let c = result.entities.values().find(|e| e.name == "C").unwrap();
+        assert!(c.used);
+        assert!(c.file_path.ends_with("libs/shared/src/c.ts"));
+    }
 
-        assert_eq!(imports.len(), 1);
-        assert_eq!(imports[0].name, "Bar");
+    #[test]
+    fn test_export_imported_only_by_spec_is_test_only() {
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("helper.ts"), "export function helper() {}\n").unwrap();
+        std::fs::write(
+            src.join("helper.spec.ts"),
+            "import { helper } from './helper';\n\ndescribe('helper', () => helper());\n",
+        )
+        .unwrap();
+
+        let result = scan_and_parse_files(temp_dir.path(), &ScanOptions::default(), false).unwrap();
+        let helper = result
+            .entities
+            .values()
+            .find(|e| e.name == "helper")
+            .unwrap();
+
+        assert!(matches!(helper.entity_type, EntityType::Function));
+        assert!(!helper.used);
+        assert!(helper.used_in_tests);
+        assert!(helper.is_test_only());
     }
 
     #[test]
-    fn test_extract_angular_lazy_loaded_import() {
-        let content = r#"const routes: Routes = [
-    {
-        path: 'auth',
-        loadChildren: () => import('./auth/auth.module').then(m => m.AuthModule)
+    fn test_unresolved_imports_are_counted_as_placeholders() {
+        use super::analysis::entity_counts;
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(
+            src.join("service.ts"),
+            "import { Missing } from './missing';\n\nexport class Service {}\n",
+        )
+        .unwrap();
+
+        let result = scan_and_parse_files(temp_dir.path(), &ScanOptions::default(), false).unwrap();
+        let counts = entity_counts(&result.entities);
+        assert_eq!(counts.defined, 1);
+        assert_eq!(counts.placeholders, 1);
+
+        let defined: Vec<&str> = result
+            .entities
+            .values()
+            .filter(|e| !e.is_placeholder())
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(defined, ["Service"]);
     }
-];"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/app-routing.module.ts";
 
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+    #[test]
+    fn test_unused_internal_declarations_are_recorded_when_enabled() {
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(
+            src.join("format.ts"),
+            "function pad(value: string) {\n  return ` ${value}`;\n}\n\n\
+             function unusedHelper() {\n  return 1;\n}\n\n\
+             const LIMIT = 3;\n\n\
+             export function format(value: string) {\n  return pad(value).slice(0, LIMIT);\n}\n",
+        )
+        .unwrap();
+
+        let unused_names = |options: &ScanOptions| {
+            let result = scan_and_parse_files(temp_dir.path(), options, false).unwrap();
+            let mut names: Vec<(String, bool)> = result
+                .entities
+                .into_values()
+                .filter(|e| !e.used)
+                .map(|e| (e.name, e.exported))
+                .collect();
+            names.sort();
+            names
+        };
 
-        assert_eq!(imports.len(), 1);
-        assert_eq!(imports[0].name, "AuthModule");
-        assert!(imports[0].path.contains("auth/auth.module"));
+        assert_eq!(
+            unused_names(&ScanOptions::default()),
+            [("format".to_string(), true)]
+        );
+
+        let options = ScanOptions {
+            include_internal: true,
+            ..ScanOptions::default()
+        };
+        assert_eq!(
+            unused_names(&options),
+            [
+                ("format".to_string(), true),
+                ("unusedHelper".to_string(), false)
+            ]
+        );
     }
 
     #[test]
-    fn test_extract_multiple_angular_lazy_loaded_imports() {
-        let content = r#"const routes: Routes = [
-    {
-        path: 'auth',
-        loadChildren: () => import('./auth/auth.module').then(m => m.AuthModule)
-    },
-    {
-        path: 'dashboard',
-        loadChildren: () => import('./dashboard/dashboard.module').then(mod => mod.DashboardModule)
-    }
-];"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/app-routing.module.ts";
+    fn test_only_unreferenced_files_without_exports_are_orphans() {
+        use super::analysis::orphan_files;
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("orphan.ts"), "console.log('leftover');\n").unwrap();
+        std::fs::write(src.join("setup.ts"), "window.ready = true;\n").unwrap();
+        std::fs::write(
+            src.join("app.ts"),
+            "export class App {\n  load() {\n    return import('./setup');\n  }\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            src.join("app.spec.ts"),
+            "import { App } from './app';\n\ndescribe('App', () => new App());\n",
+        )
+        .unwrap();
+
+        let result = scan_and_parse_files(temp_dir.path(), &ScanOptions::default(), false).unwrap();
+        let orphans = orphan_files(
+            &result.entities,
+            &result.file_imports,
+            &result.file_re_exports,
+            &[],
+        );
 
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+        assert_eq!(orphans.len(), 1);
+        assert!(orphans[0].ends_with("libs/shared/src/orphan.ts"));
+    }
 
-        assert_eq!(imports.len(), 2);
-        assert_eq!(imports[0].name, "AuthModule");
-        assert_eq!(imports[1].name, "DashboardModule");
+    #[test]
+    fn test_config_dirs_are_scanned_unless_overridden() {
+        use super::{ScanOptions, scan_and_parse_files};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        for (dir, name) in [("packages/core", "Core"), ("tools", "Tool")] {
+            let dir = temp_dir.path().join(dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(
+                dir.join("index.ts"),
+                format!("export class {} {{}}\n", name),
+            )
+            .unwrap();
+        }
+        std::fs::write(
+            temp_dir.path().join(".nxalyzer.toml"),
+            "dirs = [\"packages\"]\n",
+        )
+        .unwrap();
+
+        let names = |options: &ScanOptions| {
+            let result = scan_and_parse_files(temp_dir.path(), options, false).unwrap();
+            let mut names: Vec<String> = result.entities.into_values().map(|e| e.name).collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(names(&ScanOptions::default()), ["Core"]);
+
+        let options = ScanOptions {
+            dirs: vec!["tools".to_string()],
+            ..ScanOptions::default()
+        };
+        assert_eq!(names(&options), ["Tool"]);
     }
 
     #[test]
-    fn test_extract_lazy_import_with_different_param_names() {
-        let content = r#"loadChildren: () => import('./users/users.module').then(module => module.UsersModule)"#;
-        let root_path = Path::new("/project");
-        let file_path = "/project/src/app-routing.module.ts";
+    fn test_unused_returns_the_number_of_unused_entities() {
+        use super::{OutputFormat, ScanOptions, SortKey, SortOrder, UnusedQuery, unused};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(
+            src.join("a.ts"),
+            "import { B } from './b';\n\nexport class A {}\n",
+        )
+        .unwrap();
+        std::fs::write(src.join("b.ts"), "export class B {}\n").unwrap();
+
+        let count_unused = || {
+            unused(
+                temp_dir.path(),
+                &ScanOptions::default(),
+                UnusedQuery::default(),
+                OutputFormat::Json,
+                SortOrder {
+                    key: SortKey::File,
+                    reverse: false,
+                },
+                false,
+                &mut std::io::sink(),
+            )
+            .unwrap()
+        };
+
+        assert_eq!(count_unused(), 1);
+
+        // Once `A` is used nothing is left; the placeholder for the missing `C` is not counted
+        std::fs::write(
+            src.join("main.ts"),
+            "import { A } from './a';\nimport { C } from './c';\n",
+        )
+        .unwrap();
+        assert_eq!(count_unused(), 0);
+    }
 
-        let parser = Parser::new(root_path);
-        let imports = parser.extract_imports(content, file_path);
+    #[test]
+    fn test_entities_are_attributed_to_their_nx_project() {
+        use super::{
+            OutputFormat, ScanOptions, SortKey, SortOrder, UnusedQuery, scan_and_parse_files,
+            unused,
+        };
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        for (project, file, content) in [
+            (
+                "ui",
+                "button.ts",
+                "export class Button {}\nexport class Icon {}\n",
+            ),
+            (
+                "data",
+                "store.ts",
+                "import { Button } from '../../ui/src/button';\n\nexport class Store {}\n",
+            ),
+        ] {
+            let dir = temp_dir.path().join("libs").join(project);
+            std::fs::create_dir_all(dir.join("src")).unwrap();
+            std::fs::write(
+                dir.join("project.json"),
+                format!(
+                    r#"{{"name": "lib-{}", "tags": ["scope:{}"]}}"#,
+                    project, project
+                ),
+            )
+            .unwrap();
+            std::fs::write(dir.join("src").join(file), content).unwrap();
+        }
 
-        assert_eq!(imports.len(), 1);
-        assert_eq!(imports[0].name, "UsersModule");
+        let result = scan_and_parse_files(temp_dir.path(), &ScanOptions::default(), false).unwrap();
+        let project_of = |name: &str| {
+            result
+                .entities
+                .values()
+                .find(|entity| entity.name == name)
+                .and_then(|entity| entity.project.clone())
+        };
+        assert_eq!(project_of("Button").as_deref(), Some("lib-ui"));
+        assert_eq!(project_of("Store").as_deref(), Some("lib-data"));
+
+        let unused_in = |project: &str| {
+            let mut out = Vec::new();
+            unused(
+                temp_dir.path(),
+                &ScanOptions::default(),
+                UnusedQuery {
+                    project: Some(project),
+                    ..UnusedQuery::default()
+                },
+                OutputFormat::Json,
+                SortOrder {
+                    key: SortKey::Name,
+                    reverse: false,
+                },
+                false,
+                &mut out,
+            )
+            .map(|_| String::from_utf8(out).unwrap())
+        };
+        let ui = unused_in("lib-ui").unwrap();
+        assert!(
+            ui.contains("\"Icon\"") && !ui.contains("\"Store\""),
+            "{}",
+            ui
+        );
+        assert!(unused_in("lib-missing").is_err());
     }
 
     #[test]
-    fn test_is_test_file_spec_ts() {
-        assert!(super::is_test_file("/path/to/foo.spec.ts"));
-        assert!(super::is_test_file("foo.spec.ts"));
+    fn test_color_never_emits_no_escape_sequences() {
+        use super::{
+            ColorChoice, OutputFormat, ScanOptions, SortKey, SortOrder, UnusedQuery, unused,
+        };
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("a.ts"), "export class A {}\n").unwrap();
+
+        let text_output = |color: ColorChoice, flat: bool| {
+            let mut out = Vec::new();
+            unused(
+                temp_dir.path(),
+                &ScanOptions {
+                    color,
+                    ..ScanOptions::default()
+                },
+                UnusedQuery::default(),
+                OutputFormat::Text,
+                SortOrder {
+                    key: SortKey::File,
+                    reverse: false,
+                },
+                flat,
+                &mut out,
+            )
+            .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        for flat in [false, true] {
+            let plain = text_output(ColorChoice::Never, flat);
+            assert!(plain.contains("class A") || plain.contains("Name: A"));
+            assert!(!plain.contains('\x1b'));
+            assert!(text_output(ColorChoice::Always, flat).contains("\x1b[31m"));
+        }
     }
 
     #[test]
-    fn test_is_test_file_test_ts() {
-        assert!(super::is_test_file("/path/to/foo.test.ts"));
-        assert!(super::is_test_file("foo.test.ts"));
+    fn test_output_writer_creates_the_file_and_its_directories() {
+        use super::{
+            OutputFormat, ScanOptions, SortKey, SortOrder, UnusedQuery, output_writer, unused,
+        };
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("a.ts"), "export class A {}\n").unwrap();
+
+        let output = temp_dir.path().join("reports/ci/unused.json");
+        let mut out = output_writer(Some(&output)).unwrap();
+        let count = unused(
+            temp_dir.path(),
+            &ScanOptions::default(),
+            UnusedQuery::default(),
+            OutputFormat::Json,
+            SortOrder {
+                key: SortKey::File,
+                reverse: false,
+            },
+            false,
+            &mut out,
+        )
+        .unwrap();
+        drop(out);
+
+        assert_eq!(count, 1);
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+        assert_eq!(written[0]["name"], "A");
+        assert_eq!(written.as_array().unwrap().len(), 1);
     }
 
     #[test]
-    fn test_is_test_file_non_test_files() {
-        assert!(!super::is_test_file("/path/to/foo.ts"));
-        assert!(!super::is_test_file("/path/to/foo.spec.tsx"));
-        assert!(!super::is_test_file("/path/to/foo.test.tsx"));
-        assert!(!super::is_test_file("/path/to/spec.ts.bak"));
+    fn test_unused_output_is_grouped_by_file() {
+        use super::export::sort_entities;
+        use super::{
+            ColorChoice, Palette, ScanOptions, SortKey, SortOrder, format_unused_by_file,
+            scan_and_parse_files,
+        };
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(
+            src.join("a.ts"),
+            "export class Alpha {}\n\nexport function helper() {}\n",
+        )
+        .unwrap();
+        std::fs::write(src.join("b.ts"), "export const beta = 1;\n").unwrap();
+
+        let result = scan_and_parse_files(temp_dir.path(), &ScanOptions::default(), false).unwrap();
+        let mut unused: Vec<&crate::Entity> =
+            result.entities.values().filter(|e| !e.used).collect();
+        sort_entities(
+            &mut unused,
+            SortOrder {
+                key: SortKey::File,
+                reverse: false,
+            },
+        );
+
+        let output = format_unused_by_file(&unused, Palette::new(ColorChoice::Never));
+        let lines: Vec<&str> = output.lines().collect();
+        let src_path = normalize_path(&src.canonicalize().unwrap());
+        assert_eq!(
+            lines,
+            [
+                format!("{}/a.ts (2 unused)", src_path).as_str(),
+                "  class Alpha (line 1)",
+                "  function helper (line 3)",
+                "",
+                format!("{}/b.ts (1 unused)", src_path).as_str(),
+                "  const beta (line 1)",
+            ]
+        );
     }
 
     #[test]
-    fn test_find_test_files_in_directories() {
-        use std::collections::HashSet;
-        use std::fs::{self, File};
+    fn test_changed_source_paths_select_entities_of_changed_files() {
+        use super::{DiffRange, ScanOptions, changed_source_paths, scan_and_parse_files};
+        use crate::Entity;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let src = root.join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(
+            src.join("a.ts"),
+            "import { B } from './b';\n\nexport class A {}\n",
+        )
+        .unwrap();
+        std::fs::write(src.join("b.ts"), "export class B {}\n").unwrap();
+
+        let repo = git2::Repository::init(&root).unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[])
+            .unwrap();
+
+        std::fs::write(
+            src.join("a.ts"),
+            "import { B } from './b';\n\nexport class A {}\nexport const a = 1;\n",
+        )
+        .unwrap();
+
+        let range = DiffRange {
+            include_working_tree: true,
+            ..DiffRange::new("HEAD")
+        };
+        let options = ScanOptions::default();
+        let changed = changed_source_paths(&root, &options, &range).unwrap();
+        let result = scan_and_parse_files(&root, &options, false).unwrap();
+
+        let mut shown: Vec<&Entity> = result
+            .entities
+            .values()
+            .filter(|entity| changed.contains(&entity.file_path))
+            .collect();
+        shown.sort_by(|a, b| a.name.cmp(&b.name));
+        let names: Vec<&str> = shown.iter().map(|entity| entity.name.as_str()).collect();
+        assert_eq!(names, ["A", "a"]);
+
+        // `B` is not listed, but the dependency on it still resolves to its declaration
+        let b_path = normalize_path(&src.join("b.ts"));
+        assert_eq!(shown[0].deps[0].path, b_path);
+        let b = result.entities.get(&shown[0].deps[0].id).unwrap();
+        assert_eq!(b.entity_type, EntityType::Class);
+    }
 
-        // Create a temp directory with test files
-        let temp_dir = std::env::temp_dir().join("sting_test_find_tests");
-        let _ = fs::remove_dir_all(&temp_dir);
-        fs::create_dir_all(&temp_dir).unwrap();
+    #[cfg(unix)]
+    #[test]
+    fn test_analyze_changes_matches_changed_files_to_entities() {
+        use super::{ChangeType, DiffRange, ScanOptions, analyze_changes};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("repo");
+        let src = root.join("libs/shared/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("a.ts"), "export class A {}\n").unwrap();
+        std::fs::write(src.join("b.ts"), "export class B {}\n").unwrap();
+
+        let repo = git2::Repository::init(&root).unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[])
+            .unwrap();
+
+        std::fs::write(src.join("a.ts"), "export class A {}\nexport const a = 1;\n").unwrap();
+        std::fs::write(src.join("c.ts"), "export class C {}\n").unwrap();
+
+        // The project is reached through a symlink, the repository is not
+        let link = temp_dir.path().join("link");
+        std::os::unix::fs::symlink(&root, &link).unwrap();
+        let range = DiffRange {
+            include_working_tree: true,
+            ..DiffRange::new("HEAD")
+        };
+        let analysis = analyze_changes(&link, &ScanOptions::default(), &range).unwrap();
+
+        assert_eq!(analysis.repo_root, root.canonicalize().unwrap());
+        let mut changes: Vec<(&str, ChangeType)> = analysis
+            .changed_files
+            .iter()
+            .map(|cf| (cf.path.rsplit('/').next().unwrap(), cf.change_type.clone()))
+            .collect();
+        changes.sort_by_key(|(name, _)| *name);
+        assert_eq!(
+            changes,
+            [("a.ts", ChangeType::Modified), ("c.ts", ChangeType::Added)]
+        );
 
-        // Create test files
-        File::create(temp_dir.join("foo.spec.ts")).unwrap();
-        File::create(temp_dir.join("bar.test.ts")).unwrap();
-        File::create(temp_dir.join("baz.ts")).unwrap();
+        let mut names: Vec<&str> = analysis
+            .changed_entities()
+            .map(|entity| entity.name.as_str())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["A", "C", "a"]);
+        assert_eq!(analysis.entities.len(), 4);
+    }
 
-        let mut dirs: HashSet<String> = HashSet::new();
-        dirs.insert(temp_dir.to_string_lossy().to_string());
+    #[test]
+    fn test_source_changes_skip_other_files_and_tests() {
+        use super::git::{ChangeType, ChangedFile};
+        use super::{ScanOptions, source_changes};
 
-        let result = super::find_test_files_in_directories(&dirs);
+        let changed = [
+            ChangedFile::new("/repo/README.md".to_string(), ChangeType::Modified),
+            ChangedFile::new("/repo/libs/foo.spec.ts".to_string(), ChangeType::Modified),
+            ChangedFile::new("/repo/libs/foo.ts".to_string(), ChangeType::Modified),
+            ChangedFile::new("/repo/libs/types.d.ts".to_string(), ChangeType::Added),
+        ];
 
-        assert_eq!(result.len(), 2);
-        assert!(result.iter().any(|p| p.ends_with("foo.spec.ts")));
-        assert!(result.iter().any(|p| p.ends_with("bar.test.ts")));
-        assert!(!result.iter().any(|p| p.ends_with("baz.ts")));
+        let sources = source_changes(&ScanOptions::default(), &changed);
 
-        // Cleanup
-        let _ = fs::remove_dir_all(&temp_dir);
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].path, "/repo/libs/foo.ts");
     }
 
     #[test]
@@ -791,4 +5079,118 @@ import { Bar } from './bar';"#;
 
         assert!(result.is_empty());
     }
+
+    /// A project of three components and a button, held in memory.
+    fn memory_project() -> super::filesystem::MemoryFileSystem {
+        super::filesystem::MemoryFileSystem::new()
+            .with_file(
+                "/project/.nxalyzer.toml",
+                "dirs = [\"libs\"]\naliases = [\"@shared/=libs/shared/src\"]\n",
+            )
+            .with_file("/project/libs/shared/project.json", r#"{"name": "shared"}"#)
+            .with_file(
+                "/project/libs/shared/src/button.ts",
+                "export class Button {}\n",
+            )
+            .with_file(
+                "/project/libs/shared/src/card.component.ts",
+                "import { Button } from '@shared/button';
+
+@Component({ selector: 'app-card', template: '<app-badge></app-badge>' })
+export class CardComponent {}
+",
+            )
+            .with_file(
+                "/project/libs/shared/src/badge.component.ts",
+                "@Component({ selector: 'app-badge', templateUrl: './badge.component.html' })
+export class BadgeComponent {}
+",
+            )
+            .with_file(
+                "/project/libs/shared/src/badge.component.html",
+                "<span>{{ label }}</span>\n",
+            )
+    }
+
+    #[test]
+    fn test_scan_and_parse_files_reads_an_in_memory_tree() {
+        use super::cache::CACHE_FILE_NAME;
+        use super::entity::{Usage, generate_entity_id};
+        use super::filesystem::FileSystem;
+        use super::report::{Reporter, Verbosity};
+        use super::{ScanOptions, scan_and_parse_files_with};
+
+        let file_system = memory_project();
+        let options = ScanOptions {
+            scan_templates: true,
+            ..ScanOptions::default()
+        };
+        let mut output = Vec::new();
+        let result = scan_and_parse_files_with(
+            &file_system,
+            Path::new("/project"),
+            &options,
+            &mut Reporter::new(Verbosity::Quiet, &mut output),
+        )
+        .unwrap();
+
+        let src = "/project/libs/shared/src";
+        let button = format!("{}/button.ts", src);
+        let card = format!("{}/card.component.ts", src);
+        let badge = format!("{}/badge.component.ts", src);
+
+        assert_eq!(result.entities.len(), 3);
+        assert_eq!(result.file_imports[&card][0].path, button);
+
+        let button_entity = &result.entities[&generate_entity_id(&button, "Button")];
+        assert_eq!(button_entity.used_by, Some(Usage::Import(card.clone())));
+        assert_eq!(button_entity.project.as_deref(), Some("shared"));
+
+        assert_eq!(
+            result.entities[&generate_entity_id(&badge, "BadgeComponent")].used_by,
+            Some(Usage::Template {
+                component: "CardComponent".to_string(),
+                file: card,
+            })
+        );
+        assert!(file_system.is_file(&Path::new("/project").join(CACHE_FILE_NAME)));
+    }
+
+    #[test]
+    fn test_rescan_of_in_memory_tree_picks_up_changed_files() {
+        use super::entity::generate_entity_id;
+        use super::filesystem::FileSystem;
+        use super::report::{Reporter, Verbosity};
+        use super::{ScanOptions, scan_and_parse_files_with};
+
+        let file_system = memory_project();
+        let button = "/project/libs/shared/src/button.ts";
+        let scan = || {
+            let mut output = Vec::new();
+            scan_and_parse_files_with(
+                &file_system,
+                Path::new("/project"),
+                &ScanOptions::default(),
+                &mut Reporter::new(Verbosity::Quiet, &mut output),
+            )
+            .unwrap()
+        };
+
+        assert_eq!(scan().entities.len(), 3);
+
+        file_system
+            .write(
+                Path::new(button),
+                b"export class Button {}\nexport class Icon {}\n",
+            )
+            .unwrap();
+        let result = scan();
+
+        assert_eq!(result.entities.len(), 4);
+        assert!(
+            result
+                .entities
+                .contains_key(&generate_entity_id(button, "Icon"))
+        );
+    }
 }
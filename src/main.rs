@@ -5,6 +5,10 @@ use std::path::Path;
 use anyhow::{Context, Result};
 use args::{Commands, StingArgs};
 use clap::Parser;
+use sting::{
+    ColorChoice, DiffRange, EntityFilter, Neighborhood, ScanOptions, SortOrder, UnusedQuery,
+    Verbosity,
+};
 
 fn canonicalize_path(path_str: &str) -> Result<std::path::PathBuf> {
     let path = Path::new(path_str);
@@ -12,49 +16,378 @@ fn canonicalize_path(path_str: &str) -> Result<std::path::PathBuf> {
         .with_context(|| format!("Unable to resolve path: {}", path_str))
 }
 
+/// The given project path, or the root found above the current directory.
+fn project_root(path: Option<&str>) -> Result<std::path::PathBuf> {
+    match path {
+        Some(path) => canonicalize_path(path),
+        None => {
+            let current_dir =
+                std::env::current_dir().context("Unable to read the current directory")?;
+            Ok(sting::find_project_root(&current_dir))
+        }
+    }
+}
+
+/// Options for a command writing to `output`: files only get colors on request.
+fn output_options(options: &ScanOptions, output: Option<&Path>) -> ScanOptions {
+    match (output, options.color) {
+        (Some(_), ColorChoice::Auto) => ScanOptions {
+            color: ColorChoice::Never,
+            ..options.clone()
+        },
+        _ => options.clone(),
+    }
+}
+
 fn main() -> Result<()> {
     let cli = StingArgs::parse();
+    let options = ScanOptions {
+        aliases: cli.aliases.clone(),
+        internal_scopes: cli.internal_scopes.clone(),
+        include_external: cli.include_external,
+        include_internal: cli.include_internal,
+        verbosity: if cli.quiet {
+            Verbosity::Quiet
+        } else {
+            Verbosity::Normal
+        },
+        files: cli.files.clone(),
+        max_depth: cli.max_depth,
+        follow_symlinks: cli.follow_symlinks,
+        walk_threads: cli.walk_threads,
+        dirs: cli.dirs.clone(),
+        excludes: cli.excludes.clone(),
+        include_tests: cli.include_tests,
+        scan_templates: cli.scan_templates,
+        color: cli.color,
+        ..ScanOptions::default()
+    };
 
     match &cli.command {
         Commands::QueryAll(args) => {
-            let path = canonicalize_path(&args.path)?;
+            let path = project_root(args.path.as_deref())?;
+
+            let changed_since = args.changed_only.then(|| DiffRange::new(&args.since));
+            let mut out = sting::output_writer(args.output.as_deref())?;
 
-            sting::query_all(&path)
-                .with_context(|| format!("Unable to query in path: {}", path.display()))?
+            sting::query_all(
+                &path,
+                &output_options(&options, args.output.as_deref()),
+                EntityFilter {
+                    types: &args.types,
+                    exclude_placeholders: args.exclude_placeholders,
+                    changed_since: changed_since.as_ref(),
+                    project: args.project.as_deref(),
+                },
+                args.format,
+                SortOrder {
+                    key: args.sort_by,
+                    reverse: args.reverse,
+                },
+                &mut out,
+            )
+            .with_context(|| format!("Unable to query in path: {}", path.display()))?
         }
         Commands::Query(args) => {
             let path = canonicalize_path(&args.path)?;
 
-            sting::query(&path, &args.query)
-                .with_context(|| format!("Unable to query in path: {}", path.display()))?
+            match (&args.name, &args.query) {
+                (Some(name), _) => sting::query_by_name(&path, &options, name),
+                (None, Some(query)) => sting::query(&path, &options, query),
+                (None, None) => unreachable!("clap requires an id or --name"),
+            }
+            .with_context(|| format!("Unable to query in path: {}", path.display()))?
         }
         Commands::Unused(args) => {
-            let path = canonicalize_path(&args.path)?;
+            let path = project_root(args.path.as_deref())?;
+            let mut out = sting::output_writer(args.output.as_deref())?;
 
-            sting::unused(&path).with_context(|| {
+            let unused = sting::unused(
+                &path,
+                &ScanOptions {
+                    entry_points: args.entry_points.clone(),
+                    ..output_options(&options, args.output.as_deref())
+                },
+                UnusedQuery {
+                    types: &args.types,
+                    exclude_test_only: args.exclude_test_only,
+                    max_propagation_depth: args.max_propagation_depth,
+                    project: args.project.as_deref(),
+                },
+                args.format,
+                SortOrder {
+                    key: args.sort_by,
+                    reverse: args.reverse,
+                },
+                args.flat,
+                &mut out,
+            )
+            .with_context(|| {
                 format!("Unable to find unused entities in path: {}", path.display())
+            })?;
+
+            // --fail-on-unused allows no unused entity, --max-unused sets a budget
+            let max_unused = args.max_unused.or(args.fail_on_unused.then_some(0));
+            if let Some(max) = max_unused
+                && unused > max
+            {
+                eprintln!("Error: {} unused entities (max {})", unused, max);
+                std::process::exit(1);
+            }
+        }
+        Commands::UnusedImports(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            sting::unused_imports(&path, &options).with_context(|| {
+                format!("Unable to find unused imports in path: {}", path.display())
+            })?
+        }
+        Commands::DeadCode(args) => {
+            let path = project_root(args.path.as_deref())?;
+            let options = ScanOptions {
+                entry_points: args.entry_points.clone(),
+                ..options.clone()
+            };
+
+            sting::dead(&path, &options)
+                .with_context(|| format!("Unable to find dead code in path: {}", path.display()))?
+        }
+        Commands::Deletable(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            let options = ScanOptions {
+                entry_points: args.entry_points.clone(),
+                ..options.clone()
+            };
+
+            sting::deletable(&path, &options, args.exclude_tests).with_context(|| {
+                format!("Unable to find deletable files in path: {}", path.display())
+            })?
+        }
+        Commands::Barrels(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            sting::barrels(&path, &options).with_context(|| {
+                format!("Unable to find barrel files in path: {}", path.display())
+            })?
+        }
+        Commands::Orphans(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            sting::orphans(&path, &options).with_context(|| {
+                format!("Unable to find orphan files in path: {}", path.display())
+            })?
+        }
+        Commands::ListFiles(args) => {
+            let path = project_root(args.path.as_deref())?;
+            let mut out = sting::output_writer(None)?;
+
+            sting::list_files(&path, &options, &mut out)
+                .with_context(|| format!("Unable to list files in path: {}", path.display()))?;
+        }
+        Commands::Metrics(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            sting::metrics(&path, &options, args.top).with_context(|| {
+                format!("Unable to compute metrics for path: {}", path.display())
+            })?
+        }
+        Commands::Lint(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            let violations =
+                sting::lint(&path, &options, args.max_dependencies, args.max_dependents)
+                    .with_context(|| format!("Unable to lint path: {}", path.display()))?;
+            if violations > 0 {
+                std::process::exit(1);
+            }
+        }
+        Commands::Stats(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            sting::stats(&path, &options, args.format)
+                .with_context(|| format!("Unable to compute stats for path: {}", path.display()))?
+        }
+        Commands::Distribution(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            sting::distribution(&path, &options, args.depth, args.format).with_context(|| {
+                format!(
+                    "Unable to compute the distribution for path: {}",
+                    path.display()
+                )
+            })?
+        }
+        Commands::SelfImports(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            sting::self_imports(&path, &options).with_context(|| {
+                format!("Unable to find self-imports in path: {}", path.display())
             })?
         }
+        Commands::Validate(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            let broken = sting::validate(&path, &options).with_context(|| {
+                format!("Unable to validate imports in path: {}", path.display())
+            })?;
+            if broken > 0 {
+                std::process::exit(1);
+            }
+        }
+        Commands::OutOfScope(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            sting::out_of_scope(&path, &options).with_context(|| {
+                format!(
+                    "Unable to find imports outside the scanned directories in path: {}",
+                    path.display()
+                )
+            })?
+        }
+        Commands::Externals(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            sting::externals(&path, &options).with_context(|| {
+                format!(
+                    "Unable to list external packages in path: {}",
+                    path.display()
+                )
+            })?
+        }
+        Commands::EntityExternals(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            sting::entity_externals(&path, &options, args.format).with_context(|| {
+                format!(
+                    "Unable to list external packages per entity in path: {}",
+                    path.display()
+                )
+            })?
+        }
+        Commands::ArchCheck(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            let violations = sting::arch_check(&path, &options, &args.layers)
+                .with_context(|| format!("Unable to check layers in path: {}", path.display()))?;
+            if violations > 0 {
+                std::process::exit(1);
+            }
+        }
         Commands::Graph(args) => {
-            let path = canonicalize_path(&args.path)?;
+            let path = project_root(args.path.as_deref())?;
+
+            let focus = args.entity.as_deref().map(|entity_id| Neighborhood {
+                entity_id,
+                depth: args.depth,
+            });
+
+            let json =
+                sting::graph_json(&path, &options, args.adjacency, focus).with_context(|| {
+                    format!("Unable to generate graph for path: {}", path.display())
+                })?;
 
-            let json = sting::graph_json(&path).with_context(|| {
-                format!("Unable to generate graph for path: {}", path.display())
+            sting::write_output(args.output.as_deref(), &format!("{}\n", json))?;
+        }
+        Commands::Mermaid(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            let focus = args.entity.as_deref().map(|entity_id| Neighborhood {
+                entity_id,
+                depth: args.depth,
+            });
+
+            let mermaid = sting::graph_mermaid(&path, &options, focus).with_context(|| {
+                format!(
+                    "Unable to generate mermaid diagram for path: {}",
+                    path.display()
+                )
             })?;
 
-            println!("{}", json);
+            sting::write_output(args.output.as_deref(), &mermaid)?;
         }
-        Commands::Affected(args) => {
-            let path = canonicalize_path(&args.path)?;
+        Commands::Tree(args) => {
+            let path = project_root(args.path.as_deref())?;
 
-            sting::affected(&path, &args.base, args.transitive, args.paths, args.tests).with_context(
-                || {
+            let tree =
+                sting::dependency_tree(&path, &options, args.id.as_deref(), args.name.as_deref())
+                    .with_context(|| {
                     format!(
-                        "Unable to find affected entities in path: {}",
+                        "Unable to build dependency tree for path: {}",
                         path.display()
                     )
-                },
-            )?;
+                })?;
+
+            sting::write_output(args.output.as_deref(), &tree)?;
+        }
+        Commands::WhyUsed(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            let reasons =
+                sting::why_used(&path, &options, args.id.as_deref(), args.name.as_deref())
+                    .with_context(|| {
+                        format!(
+                            "Unable to explain entity usage for path: {}",
+                            path.display()
+                        )
+                    })?;
+
+            println!("{}", reasons);
+        }
+        Commands::Coupling(args) => {
+            let path = project_root(args.path.as_deref())?;
+
+            let csv = sting::coupling_csv(&path, &options).with_context(|| {
+                format!(
+                    "Unable to compute coupling matrix for path: {}",
+                    path.display()
+                )
+            })?;
+
+            sting::write_output(args.output.as_deref(), &csv)?;
+        }
+        Commands::Affected(args) => {
+            let path = project_root(args.path.as_deref())?;
+            let base = match (&args.base, &args.since_tag) {
+                (Some(base), _) => base.clone(),
+                (None, Some(pattern)) => sting::latest_tag(&path, pattern)?,
+                (None, None) => unreachable!("clap requires --base or --since-tag"),
+            };
+
+            let range = DiffRange {
+                base_ref: &base,
+                head_ref: args.head.as_deref(),
+                use_merge_base: !args.no_merge_base,
+                include_working_tree: args.working_tree,
+                merge_base_fallback: args.merge_base_fallback,
+            };
+
+            sting::affected(
+                &path,
+                &options,
+                &range,
+                args.transitive,
+                args.paths,
+                args.tests,
+            )
+            .with_context(|| {
+                format!(
+                    "Unable to find affected entities in path: {}",
+                    path.display()
+                )
+            })?;
+        }
+        Commands::Diff(args) => {
+            let mut out = sting::output_writer(args.output.as_deref())?;
+
+            sting::diff(
+                &args.before,
+                &args.after,
+                &output_options(&options, args.output.as_deref()),
+                args.format,
+                &mut out,
+            )
+            .context("Unable to compare the snapshots")?;
         }
     }
 
@@ -0,0 +1,48 @@
+//! TypeScript parsing primitives, usable without the scanning and reporting
+//! layer of the commands.
+//!
+//! ```
+//! use std::path::Path;
+//!
+//! let content = "import { Foo, Bar as Baz } from './foo';\nimport { map } from 'rxjs';";
+//! let imports = sting::parse::extract_imports(Path::new("/project"), content, "/project/src/app.ts");
+//!
+//! let names: Vec<&str> = imports.iter().map(|import| import.name.as_str()).collect();
+//! assert_eq!(names, ["Foo", "Bar"]);
+//! // Imports of files that do not exist are marked as unresolved
+//! assert_eq!(imports[0].path, "unresolved:/project/src/./foo");
+//! ```
+
+use std::path::Path;
+
+use anyhow::Result;
+
+pub use crate::entity::{Entity, EntityType, ImportInfo, ReExport, generate_entity_id};
+pub use crate::parser::{FileParseResult, Parser, PathAlias, strip_comments};
+
+/// Extracts the imports of `content`, resolving them as if it was read from
+/// `file_path`. External packages are skipped.
+pub fn extract_imports(root_path: &Path, content: &str, file_path: &str) -> Vec<ImportInfo> {
+    Parser::new(root_path).extract_imports(content, file_path)
+}
+
+/// Returns the name declared after `keyword`, e.g. `Foo` for `export class Foo {}`
+/// and `class`.
+pub fn extract_export_name(line: &str, keyword: &str) -> Option<String> {
+    crate::parser::extract_export_name(line, keyword)
+}
+
+/// Reads and parses a TypeScript file.
+pub fn parse_file(root_path: &Path, file_path: &str) -> Result<FileParseResult> {
+    Parser::new(root_path).parse(file_path)
+}
+
+/// Resolves an import source as written in `importing_file`, see
+/// [`Parser::resolve_import_path`].
+pub fn resolve_import_path(
+    root_path: &Path,
+    importing_file: &str,
+    import_source: &str,
+) -> Option<String> {
+    Parser::new(root_path).resolve_import_path(importing_file, import_source)
+}
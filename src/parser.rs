@@ -1,13 +1,18 @@
-use std::fs;
-use std::io::Read;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::str::FromStr;
 use std::sync::LazyLock;
 
 use anyhow::Result;
 use regex::Regex;
 
-use crate::entity::{Entity, EntityType, ImportInfo};
+use crate::entity::{
+    Entity, EntityType, ImportInfo, ReExport, Usage, content_hash, normalize_path,
+};
+use crate::filesystem::{FileSystem, RealFileSystem};
+use crate::scanner::DEFAULT_EXTENSIONS;
 
 // Pre-compiled regexes for import parsing
 static NORMALIZE_RE: LazyLock<Regex> =
@@ -19,148 +24,561 @@ static NAMED_IMPORT_RE: LazyLock<Regex> =
 static DEFAULT_IMPORT_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r#"import\s+(\w+)\s+from\s*['"]([^'"]+)['"]"#).unwrap());
 
+// Lazy-loaded import picking a member: `import('./a').then(m => m.AModule)`, also
+// spread over lines, with `(m)`, `(m: any)`, `async m =>` or a `{ return m.AModule; }`
+// body, capturing the module specifier and the member
 static LAZY_IMPORT_RE: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r#"import\s*\(\s*['"]([^'"]+)['"]\s*\)\.then\s*\(\s*\w+\s*=>\s*\w+\.(\w+)\s*\)"#)
-        .unwrap()
+    Regex::new(concat!(
+        r#"import\s*\(\s*['"]([^'"]+)['"]\s*,?\s*\)\s*\.then\s*\(\s*(?:async\s*)?"#,
+        r#"(?:[\w$]+|\(\s*[\w$]+\s*(?::[^)]*)?,?\s*\))\s*=>\s*"#,
+        r#"(?:\{\s*return\s+)?[\w$]+\s*\.\s*([\w$]+)"#
+    ))
+    .unwrap()
 });
 
-pub(crate) struct FileParseResult {
+// Any dynamic import call such as `await import('./x')`
+static DYNAMIC_IMPORT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\bimport\s*\(\s*['"]([^'"]+)['"]\s*\)"#).unwrap());
+
+// Dynamic import destructured into names: `const { Foo, Bar: Baz } = await import('./x')`
+static DESTRUCTURED_DYNAMIC_IMPORT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\{([^}]*)\}\s*=\s*(?:await\s+)?(import\s*\(\s*['"]([^'"]+)['"]\s*\))"#).unwrap()
+});
+
+// Namespace import: `import * as models from './models'`
+static NAMESPACE_IMPORT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"import\s+(?:type\s+)?\*\s*as\s+([\w$]+)\s+from\s*['"]([^'"]+)['"]"#).unwrap()
+});
+
+// CommonJS interop import: `import Foo = require('./foo')`
+static IMPORT_REQUIRE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"import\s+([\w$]+)\s*=\s*require\s*\(\s*['"]([^'"]+)['"]\s*\)"#).unwrap()
+});
+
+// CommonJS interop export of a single identifier on its own line: `export = Foo;`
+static EXPORT_ASSIGNMENT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?m)^[ \t]*export\s*=\s*([A-Za-z_$][\w$]*)[ \t]*;?[ \t]*$"#).unwrap()
+});
+
+// Export list: `export { a as b, c }`, capturing the braced names. A list followed
+// by `from` (captured as well) is a re-export instead
+static EXPORT_LIST_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\bexport\s*(?:type\s+)?\{([^}]*)\}(\s*from\b)?"#).unwrap());
+
+// Re-export from another module: `export * from './a'`, `export * as ns from './a'`
+// or `export { A, B as C } from './a'`, capturing the namespace or the braced names
+static RE_EXPORT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"\bexport\s*(?:type\s+)?(?:\*(?:\s+as\s+([\w$]+))?|\{([^}]*)\})\s*from\s*['"]([^'"]+)['"]"#,
+    )
+    .unwrap()
+});
+
+// A complete static import statement, possibly spanning several lines
+static IMPORT_STATEMENT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r#"\bimport\s+(?:type\s+)?[\w$*{},\s]*?\bfrom\s*['"][^'"]+['"]"#,
+        r#"|\bimport\s+[\w$]+\s*=\s*require\s*\(\s*['"][^'"]+['"]\s*\)"#
+    ))
+    .unwrap()
+});
+
+// Start of a decorator call such as `@Component(`, up to its opening parenthesis
+static DECORATOR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?m)^\s*@[A-Za-z_$][\w$]*\s*\("#).unwrap());
+
+static STRING_LITERAL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"'[^'\n]*'|"[^"\n]*"|`[^`]*`"#).unwrap());
+
+static IDENTIFIER_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[A-Za-z_$][\w$]*").unwrap());
+
+// Opening JSX element such as `<Foo`, `<Foo />` or `<Foo.Item>`, capturing `Foo`
+static JSX_ELEMENT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"<([A-Za-z_$][\w$]*)(?:\.[A-Za-z_$][\w$]*)*(?:[\s/>]|$)").unwrap()
+});
+
+// Keyword starting an `extends` or `implements` clause
+static HERITAGE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(?:extends|implements)\b").unwrap());
+
+// Module specifier of any import form: `from '...'`, `import '...'`, `import('...')`
+// and `require('...')`
+static IMPORT_SOURCE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?:\bfrom|\bimport|\brequire)\s*\(?\s*['"]([^'"]+)['"]"#).unwrap()
+});
+
+/// Reads a source file, reporting where the content stops being valid UTF-8.
+pub(crate) fn read_source(file_system: &dyn FileSystem, file_path: &str) -> Result<String> {
+    let content = file_system.read(Path::new(file_path))?;
+    String::from_utf8(content).map_err(|e| {
+        anyhow::anyhow!(
+            "not valid UTF-8 (invalid byte at offset {})",
+            e.utf8_error().valid_up_to()
+        )
+    })
+}
+
+/// Entities, imports and re-exports found in a single file.
+pub struct FileParseResult {
     pub entities: Vec<Entity>,
     pub imports: Vec<ImportInfo>,
+    /// Entities re-exported from other modules (`export ... from`)
+    pub re_exports: Vec<ReExport>,
+}
+
+/// An import source that resolves to a file missing on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenImport {
+    /// The module specifier as written, e.g. `./missing`
+    pub source: String,
+    /// Where the resolver looked for it
+    pub resolved_path: String,
+}
+
+/// Maps an import prefix such as `@core/` to a directory relative to the project root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathAlias {
+    pub prefix: String,
+    pub path: PathBuf,
+}
+
+impl FromStr for PathAlias {
+    type Err = String;
+
+    /// Parses `PREFIX=PATH`, e.g. `@core/=libs/core/src`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((prefix, path)) if !prefix.trim().is_empty() && !path.trim().is_empty() => {
+                Ok(PathAlias {
+                    prefix: prefix.trim().to_string(),
+                    path: PathBuf::from(path.trim()),
+                })
+            }
+            _ => Err(format!("invalid alias '{}', expected PREFIX=PATH", s)),
+        }
+    }
+}
+
+/// Classifies declarations whose line matches `pattern` as `entity_type`, before the
+/// built-in classification by keyword.
+#[derive(Debug, Clone)]
+pub struct EntityTypeRule {
+    pub entity_type: EntityType,
+    pub pattern: Regex,
+}
+
+impl PartialEq for EntityTypeRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.entity_type == other.entity_type && self.pattern.as_str() == other.pattern.as_str()
+    }
+}
+
+impl Eq for EntityTypeRule {}
+
+impl FromStr for EntityTypeRule {
+    type Err = String;
+
+    /// Parses `LABEL=REGEX`, e.g. `store=createStore\(`. A label naming a built-in
+    /// type (`class`, `const`, ...) assigns that type, any other a custom one.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((label, pattern)) = s
+            .split_once('=')
+            .filter(|(label, pattern)| !label.trim().is_empty() && !pattern.is_empty())
+        else {
+            return Err(format!(
+                "invalid entity type rule '{}', expected LABEL=REGEX",
+                s
+            ));
+        };
+
+        let label = label.trim();
+        let pattern = Regex::new(pattern)
+            .map_err(|e| format!("invalid pattern in entity type rule '{}': {}", s, e))?;
+        Ok(EntityTypeRule {
+            entity_type: label
+                .parse()
+                .unwrap_or_else(|_| EntityType::Custom(label.to_string())),
+            pattern,
+        })
+    }
 }
 
-pub(crate) struct Parser<'a> {
+/// Name recorded for imports that use a whole file rather than specific names,
+/// such as bare dynamic imports.
+pub(crate) const WHOLE_FILE_IMPORT: &str = "*";
+
+/// Path prefix of imports from external packages, e.g. `external:@angular/core`.
+pub(crate) const EXTERNAL_PREFIX: &str = "external:";
+
+/// Path prefix of relative or aliased imports whose file could not be found, e.g.
+/// `unresolved:/project/libs/missing`.
+pub(crate) const UNRESOLVED_PREFIX: &str = "unresolved:";
+
+/// Aliases that are always resolved, after any configured ones.
+const DEFAULT_ALIASES: &[(&str, &str)] = &[("@awork/", "libs/shared/src/lib")];
+
+/// Parses TypeScript sources, resolving import paths against the project root.
+pub struct Parser<'a> {
     root_path: &'a Path,
+    file_system: &'a dyn FileSystem,
+    aliases: &'a [PathAlias],
+    internal_scopes: &'a [String],
+    include_external: bool,
+    extensions: &'a [String],
+    include_internal: bool,
+    entity_type_rules: &'a [EntityTypeRule],
 }
 
 impl<'a> Parser<'a> {
     pub fn new(root_path: &'a Path) -> Self {
-        Parser { root_path }
+        Parser {
+            root_path,
+            file_system: &RealFileSystem,
+            aliases: &[],
+            internal_scopes: &[],
+            include_external: false,
+            extensions: &[],
+            include_internal: false,
+            entity_type_rules: &[],
+        }
+    }
+
+    /// Reads sources and resolves imports against `file_system` instead of the
+    /// real file system.
+    pub(crate) fn with_file_system(mut self, file_system: &'a dyn FileSystem) -> Self {
+        self.file_system = file_system;
+        self
+    }
+
+    /// Tries `aliases` in order before the default ones when resolving imports.
+    pub fn with_aliases(mut self, aliases: &'a [PathAlias]) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Treats imports from these package scopes (e.g. `@myorg`) as project code: when
+    /// no alias resolves them they are skipped rather than recorded as external.
+    pub fn with_internal_scopes(mut self, internal_scopes: &'a [String]) -> Self {
+        self.internal_scopes = internal_scopes;
+        self
+    }
+
+    /// Records imports of external packages with an `external:<package>` path
+    /// instead of skipping them.
+    pub fn with_external_packages(mut self, include_external: bool) -> Self {
+        self.include_external = include_external;
+        self
+    }
+
+    /// Tries these file extensions (without the dot) instead of `.ts` and `.tsx`
+    /// when resolving imports. An empty list keeps the defaults.
+    pub fn with_extensions(mut self, extensions: &'a [String]) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Also records top-level declarations that are not exported, with `exported`
+    /// set to false, so unused internal code can be reported.
+    pub fn with_internal_declarations(mut self, include_internal: bool) -> Self {
+        self.include_internal = include_internal;
+        self
+    }
+
+    /// Classifies declarations whose line matches one of the `rules` by the first
+    /// matching rule instead of by their keyword.
+    pub fn with_entity_type_rules(mut self, rules: &'a [EntityTypeRule]) -> Self {
+        self.entity_type_rules = rules;
+        self
+    }
+
+    /// The type assigned by the first entity type rule matching the declaration line.
+    fn custom_entity_type(&self, line: &str) -> Option<EntityType> {
+        self.entity_type_rules
+            .iter()
+            .find(|rule| rule.pattern.is_match(line))
+            .map(|rule| rule.entity_type.clone())
+    }
+
+    fn extensions(&self) -> Vec<&str> {
+        if self.extensions.is_empty() {
+            DEFAULT_EXTENSIONS.to_vec()
+        } else {
+            self.extensions.iter().map(String::as_str).collect()
+        }
+    }
+
+    /// Describes the settings that influence parse results, so cached results
+    /// produced with different settings can be discarded.
+    pub fn settings_fingerprint(&self) -> String {
+        let mut settings: Vec<String> = self
+            .aliases
+            .iter()
+            .map(|alias| format!("{}={}", alias.prefix, alias.path.display()))
+            .collect();
+        settings.extend(
+            self.internal_scopes
+                .iter()
+                .map(|scope| format!("scope:{}", scope)),
+        );
+        if self.include_external {
+            settings.push("external".to_string());
+        }
+        settings.extend(
+            self.extensions
+                .iter()
+                .map(|extension| format!("ext:{}", extension)),
+        );
+        if self.include_internal {
+            settings.push("internal".to_string());
+        }
+        settings.extend(
+            self.entity_type_rules
+                .iter()
+                .map(|rule| format!("type:{}={}", rule.entity_type, rule.pattern.as_str())),
+        );
+        settings.join(";")
     }
 
+    /// Reads and parses a file. Files that are not valid UTF-8 are an error, which
+    /// the scan reports before skipping the file.
     pub fn parse(&self, file_path: &str) -> Result<FileParseResult> {
-        let mut file = fs::File::open(file_path)?;
-        let mut content = String::new();
-        file.read_to_string(&mut content)?;
+        let content = read_source(self.file_system, file_path)?;
+        Ok(self.parse_content(&content, file_path))
+    }
 
+    /// Parses already-loaded file content as if it was read from `file_path`. A
+    /// leading byte order mark is ignored.
+    pub fn parse_content(&self, content: &str, file_path: &str) -> FileParseResult {
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
         let mut entities = Vec::new();
 
         // Extract all imports from the file (shared by all entities in this file)
-        let imports = self.extract_imports(&content, file_path);
+        let imports = self.extract_imports(content, file_path);
         let deps = Rc::new(imports.clone());
 
         // Strip comments before parsing exports
-        let content_without_comments = strip_comments(&content);
+        let content_without_comments = strip_comments(content);
+        let mut overload_lines: HashMap<String, Vec<usize>> = HashMap::new();
 
-        for line in content_without_comments.lines() {
+        for (line_index, line) in declaration_lines(&content_without_comments) {
             let trimmed = line.trim();
 
             if trimmed.is_empty() {
                 continue;
             }
 
-            // Check for exported classes
-            if trimmed.contains("export") && trimmed.contains("class") {
-                if let Some(name) = extract_export_name(trimmed, "class") {
-                    entities.push(Entity::new(
-                        name,
-                        EntityType::Class,
-                        file_path.to_string(),
-                        Rc::clone(&deps),
-                    ));
-                }
+            // `export = Foo` exports a declaration made elsewhere in the file, which
+            // is where the entity is reported
+            if let Some(caps) = EXPORT_ASSIGNMENT_RE.captures(trimmed) {
+                let name = caps[1].to_string();
+                let (entity_type, declaration_line) =
+                    local_declaration(&content_without_comments, &name);
+                let mut entity =
+                    Entity::new(name, entity_type, file_path.to_string(), Rc::clone(&deps));
+                entity.line = declaration_line.unwrap_or(line_index + 1);
+                entities.push(entity);
+                continue;
             }
 
-            // Check for exported enums
-            if trimmed.contains("export") && trimmed.contains("enum") {
-                if let Some(name) = extract_export_name(trimmed, "enum") {
-                    entities.push(Entity::new(
-                        name,
-                        EntityType::Enum,
-                        file_path.to_string(),
-                        Rc::clone(&deps),
-                    ));
+            // Classify by the declaration keyword that follows `export`, so each
+            // declared name yields exactly one entity
+            let (keyword, declaration, exported) = match exported_declaration(trimmed) {
+                Some((keyword, declaration)) => (keyword, declaration, true),
+                None => match internal_declaration(&line).filter(|_| self.include_internal) {
+                    Some((keyword, declaration)) => (keyword, declaration, false),
+                    None => continue,
+                },
+            };
+
+            let custom_type = self.custom_entity_type(trimmed);
+
+            if matches!(keyword, "const" | "let" | "var") {
+                for (name, entity_type) in declared_variables(&declaration[keyword.len()..]) {
+                    let entity_type = custom_type.clone().unwrap_or(entity_type);
+                    let mut entity =
+                        Entity::new(name, entity_type, file_path.to_string(), Rc::clone(&deps));
+                    entity.line = line_index + 1;
+                    entity.exported = exported;
+                    entities.push(entity);
                 }
+                continue;
             }
 
-            // Check for exported types
-            if trimmed.contains("export") && trimmed.contains("type") && !trimmed.contains("typeof")
+            let Some(name) = extract_export_name(declaration, keyword) else {
+                continue;
+            };
+
+            let entity_type = custom_type.unwrap_or(match keyword {
+                "class" => EntityType::Class,
+                "enum" => EntityType::Enum,
+                "type" => EntityType::Type,
+                "interface" => EntityType::Interface,
+                "namespace" | "module" => EntityType::Namespace,
+                _ => EntityType::Function,
+            });
+
+            // Overload signatures, as in `export declare function f(a: string): void;`
+            // followed by another `f`, declare the same function again
+            if entity_type == EntityType::Function
+                && entities
+                    .iter()
+                    .any(|e| e.name == name && e.entity_type == EntityType::Function)
             {
-                if let Some(name) = extract_export_name(trimmed, "type") {
-                    entities.push(Entity::new(
-                        name,
-                        EntityType::Type,
-                        file_path.to_string(),
-                        Rc::clone(&deps),
-                    ));
-                }
+                overload_lines.entry(name).or_default().push(line_index + 1);
+                continue;
             }
 
-            // Check for exported interfaces
-            if trimmed.contains("export") && trimmed.contains("interface") {
-                if let Some(name) = extract_export_name(trimmed, "interface") {
-                    entities.push(Entity::new(
-                        name,
-                        EntityType::Interface,
-                        file_path.to_string(),
-                        Rc::clone(&deps),
-                    ));
-                }
-            }
+            let mut entity =
+                Entity::new(name, entity_type, file_path.to_string(), Rc::clone(&deps));
+            entity.line = line_index + 1;
+            entity.exported = exported;
+            entities.push(entity);
+        }
 
-            // Check for exported functions
-            if trimmed.contains("export") && trimmed.contains("function") {
-                if let Some(name) = extract_export_name(trimmed, "function") {
-                    entities.push(Entity::new(
-                        name,
-                        EntityType::Function,
-                        file_path.to_string(),
-                        Rc::clone(&deps),
-                    ));
-                }
+        // `export { internalFoo as Foo }` exports a local declaration under a new
+        // name, which is recorded where it is declared; the local binding is used
+        for (local, exported_name, line) in local_exports(&content_without_comments) {
+            if local != exported_name
+                && let Some(entity) = entities.iter_mut().find(|entity| entity.name == local)
+            {
+                entity.mark_used(Usage::Local);
+                entity.used_locally = true;
             }
-
-            // Check for export const/let/var function expressions
-            if trimmed.starts_with("export const")
-                || trimmed.starts_with("export let")
-                || trimmed.starts_with("export var")
+            if let Some(entity) = entities
+                .iter_mut()
+                .find(|entity| entity.name == exported_name)
             {
-                let keyword = if trimmed.starts_with("export const") {
-                    "const"
-                } else if trimmed.starts_with("export let") {
-                    "let"
-                } else {
-                    "var"
-                };
+                entity.exported = true;
+                continue;
+            }
+            let (entity_type, declaration_line) =
+                local_declaration(&content_without_comments, &local);
+            let mut entity = Entity::new(
+                exported_name,
+                entity_type,
+                file_path.to_string(),
+                Rc::clone(&deps),
+            );
+            entity.line = declaration_line.unwrap_or(line);
+            entities.push(entity);
+        }
 
-                if let Some(name) = extract_export_name(trimmed, keyword) {
-                    if trimmed.contains("=>") || trimmed.contains("= function") {
-                        entities.push(Entity::new(
-                            name,
-                            EntityType::Function,
-                            file_path.to_string(),
-                            Rc::clone(&deps),
-                        ));
-                    } else {
-                        entities.push(Entity::new(
-                            name,
-                            EntityType::Const,
-                            file_path.to_string(),
-                            Rc::clone(&deps),
-                        ));
-                    }
-                }
+        // A declaration exported elsewhere in the file (e.g. by `export = Foo`) is
+        // already recorded as exported
+        let exported_names: HashSet<String> = entities
+            .iter()
+            .filter(|entity| entity.exported)
+            .map(|entity| entity.name.clone())
+            .collect();
+        entities.retain(|entity| entity.exported || !exported_names.contains(&entity.name));
+
+        // Classes and interfaces depend on what they extend or implement, which may be
+        // declared in the same file rather than imported
+        let lines: Vec<&str> = content_without_comments.lines().collect();
+        let declared: HashSet<String> = entities.iter().map(|e| e.name.clone()).collect();
+        for entity in &mut entities {
+            if !matches!(
+                entity.entity_type,
+                EntityType::Class | EntityType::Interface
+            ) {
+                continue;
             }
+            let bases = heritage_names(&declaration_header(&lines, entity.line))
+                .into_iter()
+                .filter_map(|base| {
+                    let imported = imports
+                        .iter()
+                        .find(|import| import.local_name() == base)
+                        .cloned();
+                    imported.or_else(|| {
+                        (declared.contains(&base) && base != entity.name)
+                            .then(|| ImportInfo::new(base, file_path.to_string()))
+                    })
+                })
+                .collect();
+            entity.set_bases(bases);
+        }
+
+        // Check if exported entities are used locally in the same file, either
+        // anywhere in its code or in decorator metadata such as `providers: [...]`
+        let decorator_refs = decorator_references(&content_without_comments);
+        let jsx_refs = jsx_element_names(&content_without_comments, file_path);
+        // The declaration's text, without comments, identifies changes to it
+        let code = mask_string_literals(&content_without_comments);
+        let code_lines: Vec<&str> = code.lines().collect();
+        for entity in &mut entities {
+            let start = entity.line.saturating_sub(1);
+            let end = declaration_end(&code_lines, start);
+            let declaration = lines.get(start..=end.max(start)).unwrap_or_default();
+            entity.content_hash = Some(content_hash(&declaration.join("\n")));
         }
 
-        // Check if exported entities are used locally in the same file
+        // `export = Foo` and `export { Foo }` are not references to `Foo`
+        let code = EXPORT_ASSIGNMENT_RE.replace_all(&code, "");
+        let code = EXPORT_LIST_RE.replace_all(&code, |caps: &regex::Captures| {
+            if caps.get(2).is_some() {
+                caps[0].to_string()
+            } else {
+                // Keep the line breaks of a list spanning lines, so line numbers hold
+                caps[0].matches('\n').collect()
+            }
+        });
         for entity in &mut entities {
-            if is_entity_used_locally(&content, &entity.name) {
-                entity.used = true;
+            let mut declarations = vec![entity.line];
+            if let Some(lines) = overload_lines.get(&entity.name) {
+                declarations.extend(lines);
+            }
+            if decorator_refs.contains(&entity.name)
+                || jsx_refs.contains(&entity.name)
+                || is_entity_used_locally(&code, &entity.name, &declarations)
+            {
+                entity.mark_used(Usage::Local);
+                entity.used_locally = true;
+            }
+        }
+
+        let re_exports = self.extract_re_exports(&content_without_comments, file_path);
+
+        FileParseResult {
+            entities,
+            imports,
+            re_exports,
+        }
+    }
+
+    /// Extracts `export ... from` statements. Named re-exports yield one entry per
+    /// name, `export *` yields a single entry exporting and re-exporting `*`, and
+    /// `export * as ns` one exporting `ns` from `*`.
+    pub fn extract_re_exports(&self, content: &str, file_path: &str) -> Vec<ReExport> {
+        let mut re_exports = Vec::new();
+
+        for cap in RE_EXPORT_RE.captures_iter(content) {
+            let Some(resolved_path) = self.resolve_import_path(file_path, &cap[3]) else {
+                continue;
+            };
+
+            let Some(names) = cap.get(2) else {
+                let exported_name = cap.get(1).map_or("*", |namespace| namespace.as_str());
+                re_exports.push(ReExport::new(exported_name, "*", resolved_path));
+                continue;
+            };
+
+            for name_part in names.as_str().split(',') {
+                let name_part = name_part.trim();
+                let name_part = name_part.strip_prefix("type ").unwrap_or(name_part).trim();
+                if name_part.is_empty() {
+                    continue;
+                }
+
+                let (name, exported_name) = match name_part.split_once(" as ") {
+                    Some((name, alias)) => (name.trim(), alias.trim()),
+                    None => (name_part, name_part),
+                };
+                re_exports.push(ReExport::new(exported_name, name, resolved_path.clone()));
             }
         }
 
-        Ok(FileParseResult { entities, imports })
+        re_exports
     }
 
     pub fn extract_imports(&self, content: &str, file_path: &str) -> Vec<ImportInfo> {
@@ -172,7 +590,7 @@ impl<'a> Parser<'a> {
         // Normalize content: collapse multiline imports into single lines
         let normalized_content =
             NORMALIZE_RE.replace_all(&content_without_comments, |caps: &regex::Captures| {
-                let names = caps[1].replace('\n', " ").replace('\r', " ");
+                let names = caps[1].replace(['\n', '\r'], " ");
                 format!("import {{{}}} from", names)
             });
 
@@ -180,7 +598,7 @@ impl<'a> Parser<'a> {
             let names_str = &cap[1];
             let import_path = cap[2].to_string();
 
-            let resolved_path = match resolve_import_path(file_path, &import_path, self.root_path) {
+            let resolved_path = match self.resolve_import_path(file_path, &import_path) {
                 Some(path) => path,
                 None => continue,
             };
@@ -191,13 +609,14 @@ impl<'a> Parser<'a> {
                     continue;
                 }
 
-                let name = if let Some(pos) = name_part.find(" as ") {
-                    name_part[..pos].trim().to_string()
-                } else {
-                    name_part.to_string()
+                let import = match name_part.split_once(" as ") {
+                    Some((name, alias)) => {
+                        ImportInfo::new(name.trim().to_string(), resolved_path.clone())
+                            .with_alias(alias.trim().to_string())
+                    }
+                    None => ImportInfo::new(name_part.to_string(), resolved_path.clone()),
                 };
-
-                imports.push(ImportInfo::new(name, resolved_path.clone()));
+                imports.push(import);
             }
         }
 
@@ -209,183 +628,1421 @@ impl<'a> Parser<'a> {
                 continue;
             }
 
-            if let Some(resolved_path) =
-                resolve_import_path(file_path, &import_path, self.root_path)
-            {
+            if let Some(resolved_path) = self.resolve_import_path(file_path, &import_path) {
+                imports.push(ImportInfo::new(name, resolved_path));
+            }
+        }
+
+        // Handle CommonJS interop imports (`import Foo = require('./foo')`)
+        for cap in IMPORT_REQUIRE_RE.captures_iter(&normalized_content) {
+            let name = cap[1].to_string();
+            let import_path = cap[2].to_string();
+
+            if let Some(resolved_path) = self.resolve_import_path(file_path, &import_path) {
                 imports.push(ImportInfo::new(name, resolved_path));
             }
         }
 
+        // Namespace imports use the members accessed on them (`models.Color.Red`,
+        // `models['Color']`), or the whole file when the namespace itself is passed around
+        for cap in NAMESPACE_IMPORT_RE.captures_iter(&normalized_content) {
+            let Some(resolved_path) = self.resolve_import_path(file_path, &cap[2]) else {
+                continue;
+            };
+            let statement = cap.get(0).unwrap().range();
+
+            let (members, used_whole) = namespace_members(&normalized_content, &cap[1], statement);
+            for member in members {
+                imports.push(ImportInfo::new(member, resolved_path.clone()));
+            }
+            if used_whole {
+                imports.push(ImportInfo::new(
+                    WHOLE_FILE_IMPORT.to_string(),
+                    resolved_path,
+                ));
+            }
+        }
+
+        // Dynamic imports whose used names are known, keyed by where the `import(` starts
+        let mut named_dynamic_imports: HashSet<usize> = HashSet::new();
+
         // Handle Angular lazy-loaded imports
         for cap in LAZY_IMPORT_RE.captures_iter(&normalized_content) {
+            named_dynamic_imports.insert(cap.get(0).unwrap().start());
             let import_path = cap[1].to_string();
             let name = cap[2].to_string();
 
-            if let Some(resolved_path) =
-                resolve_import_path(file_path, &import_path, self.root_path)
-            {
+            if let Some(resolved_path) = self.resolve_import_path(file_path, &import_path) {
                 imports.push(ImportInfo::new(name, resolved_path));
             }
         }
 
-        imports
-    }
-}
-
-/// Strips single-line (//) and multi-line (/* */) comments from content.
-/// Preserves strings so that comment-like patterns inside strings are not stripped.
-pub(crate) fn strip_comments(content: &str) -> String {
-    let mut result = String::with_capacity(content.len());
-    let mut chars = content.chars().peekable();
-    let mut in_string: Option<char> = None;
-
-    while let Some(c) = chars.next() {
-        if in_string.is_none() && (c == '"' || c == '\'' || c == '`') {
-            in_string = Some(c);
-            result.push(c);
-            continue;
-        }
+        // Handle dynamic imports destructured into names
+        for cap in DESTRUCTURED_DYNAMIC_IMPORT_RE.captures_iter(&normalized_content) {
+            named_dynamic_imports.insert(cap.get(2).unwrap().start());
+            let Some(resolved_path) = self.resolve_import_path(file_path, &cap[3]) else {
+                continue;
+            };
 
-        if let Some(quote) = in_string {
-            result.push(c);
-            if c == '\\' {
-                if let Some(&next) = chars.peek() {
-                    result.push(next);
-                    chars.next();
+            for binding in cap[1].split(',') {
+                let (name, alias) = match binding.split_once(':') {
+                    Some((name, alias)) => (name.trim(), Some(alias.trim())),
+                    None => (binding.trim(), None),
+                };
+                if name.is_empty() {
+                    continue;
                 }
-            } else if c == quote {
-                in_string = None;
+                let import = ImportInfo::new(name.to_string(), resolved_path.clone());
+                imports.push(match alias.filter(|alias| !alias.is_empty()) {
+                    Some(alias) => import.with_alias(alias.to_string()),
+                    None => import,
+                });
             }
-            continue;
         }
 
-        if c == '/' {
-            if let Some(&next) = chars.peek() {
-                if next == '/' {
-                    chars.next();
-                    while let Some(&ch) = chars.peek() {
-                        if ch == '\n' {
-                            break;
-                        }
-                        chars.next();
-                    }
-                    continue;
-                } else if next == '*' {
-                    chars.next();
-                    while let Some(ch) = chars.next() {
-                        if ch == '*' {
-                            if let Some(&peek) = chars.peek() {
-                                if peek == '/' {
-                                    chars.next();
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                    continue;
-                }
+        // Any other dynamic import may use everything the file exports
+        for cap in DYNAMIC_IMPORT_RE.captures_iter(&normalized_content) {
+            if named_dynamic_imports.contains(&cap.get(0).unwrap().start()) {
+                continue;
+            }
+
+            if let Some(resolved_path) = self.resolve_import_path(file_path, &cap[1]) {
+                imports.push(ImportInfo::new(
+                    WHOLE_FILE_IMPORT.to_string(),
+                    resolved_path,
+                ));
             }
         }
 
-        result.push(c);
+        imports
     }
 
-    result
-}
+    /// Returns the imports of the file whose local name is never referenced outside
+    /// of import statements, including type positions and JSX.
+    pub fn unused_imports(&self, content: &str, file_path: &str) -> Vec<ImportInfo> {
+        let content_without_comments = strip_comments(content);
+        let jsx_refs = jsx_element_names(&content_without_comments, file_path);
+        let code = mask_string_literals(&content_without_comments);
+        let body = IMPORT_STATEMENT_RE.replace_all(&code, "");
 
-fn extract_export_name(line: &str, keyword: &str) -> Option<String> {
-    let mut search_start = 0;
+        self.extract_imports(content, file_path)
+            .into_iter()
+            .filter(|import| {
+                if import.name == WHOLE_FILE_IMPORT {
+                    return false;
+                }
+                let local_name = import.local_name();
+                !jsx_refs.contains(local_name) && count_word_occurrences(&body, local_name) == 0
+            })
+            .collect()
+    }
 
-    while let Some(relative_pos) = line[search_start..].find(keyword) {
-        let pos = search_start + relative_pos;
+    /// Returns the import sources of the file, in order and once each, that resolve
+    /// to a file missing on disk. External packages and sources the parser does not
+    /// resolve are skipped.
+    pub fn broken_imports(&self, content: &str, file_path: &str) -> Vec<BrokenImport> {
+        let content_without_comments = strip_comments(content);
+        let mut seen: HashSet<&str> = HashSet::new();
 
-        // Check that keyword is not part of another word:
-        // - preceded by non-alphanumeric (or start of string)
-        // - followed by whitespace
-        let char_before_ok = pos == 0 || {
-            let prev_char = line[..pos].chars().last().unwrap();
-            !prev_char.is_alphanumeric() && prev_char != '_'
-        };
+        IMPORT_SOURCE_RE
+            .captures_iter(&content_without_comments)
+            .filter_map(|cap| {
+                let source = cap.get(1).unwrap().as_str();
+                if !seen.insert(source) {
+                    return None;
+                }
 
-        let after_keyword = &line[pos + keyword.len()..];
-        let char_after_ok = after_keyword.starts_with(|c: char| c.is_whitespace());
+                let resolved = self.resolve_import_path(file_path, source)?;
+                let resolved_path = match resolved.strip_prefix(UNRESOLVED_PREFIX) {
+                    Some(path) => path.to_string(),
+                    None if resolved.starts_with(EXTERNAL_PREFIX)
+                        || self.file_system.exists(Path::new(&resolved)) =>
+                    {
+                        return None;
+                    }
+                    None => resolved,
+                };
+                Some(BrokenImport {
+                    source: source.to_string(),
+                    resolved_path,
+                })
+            })
+            .collect()
+    }
 
-        if char_before_ok && char_after_ok {
-            let identifier: String = after_keyword
-                .trim_start()
-                .chars()
-                .take_while(|c| c.is_alphanumeric() || *c == '_')
-                .collect();
+    /// Resolves the import sources of the file, in order and once each, so the parse
+    /// cache can tell when they would resolve differently than when it was filled.
+    pub(crate) fn import_resolutions(
+        &self,
+        content: &str,
+        file_path: &str,
+    ) -> Vec<(String, Option<String>)> {
+        let content_without_comments = strip_comments(content);
+        let mut seen: HashSet<&str> = HashSet::new();
 
-            if !identifier.is_empty() {
-                return Some(identifier);
-            }
-        }
+        IMPORT_SOURCE_RE
+            .captures_iter(&content_without_comments)
+            .map(|cap| cap.get(1).unwrap().as_str())
+            .filter(|source| seen.insert(source))
+            .map(|source| {
+                (
+                    source.to_string(),
+                    self.resolve_import_path(file_path, source),
+                )
+            })
+            .collect()
+    }
 
-        // Continue searching after this position
-        search_start = pos + keyword.len();
+    fn is_internal_scope(&self, import_source: &str) -> bool {
+        package_scope(import_source).is_some_and(|scope| {
+            self.internal_scopes
+                .iter()
+                .any(|internal| internal.trim_end_matches('/') == scope)
+        })
     }
 
-    None
-}
+    /// Whether `resolved_path` is a file other than a source or JSON module, such as
+    /// an `.svg` asset: importing it is a dependency but declares no entity.
+    pub(crate) fn is_asset(&self, resolved_path: &str) -> bool {
+        if resolved_path.starts_with(EXTERNAL_PREFIX)
+            || resolved_path.starts_with(UNRESOLVED_PREFIX)
+        {
+            return false;
+        }
 
-fn resolve_import_path(
-    importing_file: &str,
-    import_source: &str,
-    root_path: &Path,
-) -> Option<String> {
-    let base_path = if import_source.starts_with("@awork/") {
-        let rest = &import_source[7..];
-        root_path.join("libs/shared/src/lib").join(rest)
-    } else if import_source.starts_with("./") || import_source.starts_with("../") {
-        let importing_dir = Path::new(importing_file).parent()?;
-        importing_dir.join(import_source)
-    } else {
-        return None;
-    };
+        let file_name = resolved_path.rsplit('/').next().unwrap_or(resolved_path);
+        file_name.rsplit_once('.').is_some_and(|(_, extension)| {
+            extension != "json" && !self.extensions().contains(&extension)
+        })
+    }
 
-    let extensions = [".ts", ".tsx", "/index.ts", "/index.tsx"];
+    /// Resolves an import source to a file path: aliases first, then relative paths
+    /// (trying the configured extensions, `.json`, the `.mts`/`.cts` source of a
+    /// `.mjs`/`.cjs` import and the `index` variants). Files that
+    /// cannot be found resolve to `unresolved:<path>` unless the import names an
+    /// extension. External packages resolve to `external:<package>` when enabled and
+    /// to `None` otherwise. Loader suffixes such as `?raw` or `#hash` are ignored.
+    pub fn resolve_import_path(&self, importing_file: &str, import_source: &str) -> Option<String> {
+        let import_source = strip_resource_query(import_source);
+        let configured = self
+            .aliases
+            .iter()
+            .map(|alias| (alias.prefix.as_str(), alias.path.as_path()));
+        let defaults = DEFAULT_ALIASES
+            .iter()
+            .map(|(prefix, path)| (*prefix, Path::new(*path)));
+        let alias_target = configured.chain(defaults).find_map(|(prefix, path)| {
+            strip_alias_prefix(import_source, prefix)
+                .map(|rest| self.root_path.join(path).join(rest))
+        });
 
-    for ext in &extensions {
-        let full_path = if ext.starts_with('/') {
-            base_path.join(&ext[1..])
+        let base_path = if let Some(aliased) = alias_target {
+            aliased
+        } else if import_source.starts_with("./") || import_source.starts_with("../") {
+            let importing_dir = Path::new(importing_file).parent()?;
+            importing_dir.join(import_source)
+        } else if self.include_external && !self.is_internal_scope(import_source) {
+            return Some(format!(
+                "{}{}",
+                EXTERNAL_PREFIX,
+                package_name(import_source)
+            ));
         } else {
-            let path_str = base_path.to_string_lossy();
-            Path::new(&format!("{}{}", path_str, ext)).to_path_buf()
+            return None;
         };
 
-        if full_path.exists() {
-            return full_path
-                .canonicalize()
-                .ok()?
-                .to_str()
-                .map(|s| s.to_string());
+        let extensions = self.extensions();
+        // ES modules import the emitted file, e.g. `./util.mjs` for `util.mts`
+        let base_str = base_path.to_string_lossy();
+        let module_source =
+            [(".mjs", ".mts"), (".cjs", ".cts")]
+                .iter()
+                .find_map(|(emitted, source)| {
+                    base_str
+                        .strip_suffix(emitted)
+                        .map(|stem| PathBuf::from(format!("{}{}", stem, source)))
+                });
+        let file_candidates = extensions
+            .iter()
+            .map(|extension| format!(".{}", extension))
+            .chain([".json".to_string()])
+            .map(|ext| PathBuf::from(format!("{}{}", base_str, ext)))
+            .chain(module_source);
+        // A package directory resolves to the entry its package.json declares before
+        // falling back to its index file
+        let directory_candidates = self.package_entry(&base_path).into_iter().chain(
+            extensions
+                .iter()
+                .map(|extension| base_path.join(format!("index.{}", extension))),
+        );
+
+        for full_path in file_candidates.chain(directory_candidates) {
+            if self.file_system.exists(&full_path) {
+                return self
+                    .file_system
+                    .canonicalize(&full_path)
+                    .ok()
+                    .map(|path| normalize_path(&path));
+            }
+        }
+
+        if self.file_system.is_file(&base_path) {
+            return self
+                .file_system
+                .canonicalize(&base_path)
+                .ok()
+                .map(|path| normalize_path(&path));
+        }
+
+        // A missing file imported with its extension keeps it; otherwise the file is
+        // unknown and no extension is made up for it
+        let path_str = normalize_path(&base_path);
+        let has_extension = path_str.ends_with(".json")
+            || extensions
+                .iter()
+                .any(|extension| path_str.ends_with(&format!(".{}", extension)));
+        if has_extension {
+            Some(path_str)
+        } else {
+            Some(format!("{}{}", UNRESOLVED_PREFIX, path_str))
         }
     }
 
-    if base_path.exists() && base_path.is_file() {
-        return base_path
-            .canonicalize()
-            .ok()?
-            .to_str()
-            .map(|s| s.to_string());
+    /// Returns the entry file of the package in `dir` as declared by its
+    /// `package.json`: the `.` export, else `main`, else `module`. The TypeScript
+    /// source next to a JavaScript or declaration entry is preferred, e.g.
+    /// `src/index.ts` for `src/index.js`.
+    fn package_entry(&self, dir: &Path) -> Option<PathBuf> {
+        let content = self
+            .file_system
+            .read_to_string(&dir.join("package.json"))
+            .ok()?;
+        let manifest: serde_json::Value = serde_json::from_str(&content).ok()?;
+
+        [
+            manifest.get("exports").and_then(root_export),
+            manifest.get("main").and_then(serde_json::Value::as_str),
+            manifest.get("module").and_then(serde_json::Value::as_str),
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(|entry| self.entry_source(&dir.join(entry)))
     }
 
-    let path_str = base_path.to_string_lossy().to_string();
-    if path_str.ends_with(".ts") || path_str.ends_with(".tsx") {
-        Some(path_str)
-    } else {
-        Some(format!("{}.ts", path_str))
+    /// Finds the file a package entry refers to, trying the scanned extensions in
+    /// place of a JavaScript or `.d.ts` extension and the index file of a directory.
+    fn entry_source(&self, entry: &Path) -> Option<PathBuf> {
+        let entry_str = entry.to_string_lossy();
+        let stem = [".d.ts", ".js", ".mjs", ".cjs", ".jsx"]
+            .iter()
+            .find_map(|suffix| entry_str.strip_suffix(suffix))
+            .unwrap_or(&entry_str);
+        let extensions = self.extensions();
+
+        extensions
+            .iter()
+            .map(|extension| PathBuf::from(format!("{}.{}", stem, extension)))
+            .chain([entry.to_path_buf()])
+            .chain(
+                extensions
+                    .iter()
+                    .map(|extension| entry.join(format!("index.{}", extension))),
+            )
+            .find(|path| self.file_system.is_file(path))
     }
 }
 
-fn is_entity_used_locally(content: &str, entity_name: &str) -> bool {
-    let pattern = format!(r"\b{}\b", regex::escape(entity_name));
-    let re = match Regex::new(&pattern) {
-        Ok(r) => r,
-        Err(_) => return false,
-    };
+/// Picks the target of the package root (`.`) in a `package.json` `exports` field,
+/// which is a path, a map of subpaths or a map of conditions.
+fn root_export(exports: &serde_json::Value) -> Option<&str> {
+    match exports {
+        serde_json::Value::String(target) => Some(target),
+        serde_json::Value::Object(map) => {
+            if let Some(root) = map.get(".") {
+                return root_export(root);
+            }
+            if map.keys().any(|key| key.starts_with('.')) {
+                return None;
+            }
+            ["types", "import", "default", "require"]
+                .iter()
+                .find_map(|condition| map.get(*condition).and_then(root_export))
+        }
+        _ => None,
+    }
+}
 
-    let matches: Vec<_> = re.find_iter(content).collect();
-    matches.len() > 1
+/// Strips an alias prefix from an import source, but only at a path segment
+/// boundary, so `@awork` matches `@awork/models` and not `@awork-labs/models`.
+fn strip_alias_prefix<'s>(import_source: &'s str, prefix: &str) -> Option<&'s str> {
+    let rest = import_source.strip_prefix(prefix)?;
+    if prefix.ends_with('/') || rest.is_empty() {
+        Some(rest)
+    } else {
+        rest.strip_prefix('/')
+    }
+}
+
+/// Returns the scope of a scoped package import: `@angular/core` -> `@angular`.
+fn package_scope(import_source: &str) -> Option<&str> {
+    if !import_source.starts_with('@') {
+        return None;
+    }
+    import_source.split('/').next()
+}
+
+/// Returns the package an import source belongs to, dropping any subpath:
+/// `rxjs/operators` -> `rxjs`, `@angular/core/testing` -> `@angular/core`.
+fn package_name(import_source: &str) -> &str {
+    let segments = if import_source.starts_with('@') { 2 } else { 1 };
+    match import_source.match_indices('/').nth(segments - 1) {
+        Some((end, _)) => &import_source[..end],
+        None => import_source,
+    }
+}
+
+/// Tracks where the scanner is when inside template literals.
+enum TemplateContext {
+    /// Inside the literal text of a template string
+    Literal,
+    /// Inside a `${ }` interpolation, with the depth of nested `{` braces
+    Interpolation(usize),
+}
+
+/// Strips single-line (//) and multi-line (/* */) comments from content.
+/// Preserves strings so that comment-like patterns inside strings are not stripped.
+/// Template literal interpolations (`${ }`) are scanned as code, so strings and
+/// templates nested inside them are handled as well.
+pub fn strip_comments(content: &str) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string: Option<char> = None;
+    let mut templates: Vec<TemplateContext> = Vec::new();
+
+    while let Some(c) = chars.next() {
+        if let Some(quote) = in_string {
+            result.push(c);
+            if c == '\\' {
+                if let Some(&next) = chars.peek() {
+                    result.push(next);
+                    chars.next();
+                }
+            } else if c == quote {
+                in_string = None;
+            }
+            continue;
+        }
+
+        if let Some(TemplateContext::Literal) = templates.last() {
+            result.push(c);
+            if c == '\\' {
+                if let Some(&next) = chars.peek() {
+                    result.push(next);
+                    chars.next();
+                }
+            } else if c == '`' {
+                templates.pop();
+            } else if c == '$' && chars.peek() == Some(&'{') {
+                result.push('{');
+                chars.next();
+                templates.push(TemplateContext::Interpolation(0));
+            }
+            continue;
+        }
+
+        if c == '"' || c == '\'' {
+            in_string = Some(c);
+            result.push(c);
+            continue;
+        }
+
+        if c == '`' {
+            templates.push(TemplateContext::Literal);
+            result.push(c);
+            continue;
+        }
+
+        if let Some(TemplateContext::Interpolation(depth)) = templates.last_mut() {
+            if c == '{' {
+                *depth += 1;
+            } else if c == '}' {
+                if *depth == 0 {
+                    templates.pop();
+                } else {
+                    *depth -= 1;
+                }
+            }
+        }
+
+        if c == '/'
+            && let Some(&next) = chars.peek()
+        {
+            if next == '/' {
+                chars.next();
+                while let Some(&ch) = chars.peek() {
+                    if ch == '\n' {
+                        break;
+                    }
+                    chars.next();
+                }
+                continue;
+            } else if next == '*' {
+                chars.next();
+                while let Some(ch) = chars.next() {
+                    // Keep line breaks so line numbers stay aligned with the source
+                    if ch == '\n' {
+                        result.push(ch);
+                    }
+                    if ch == '*'
+                        && let Some(&peek) = chars.peek()
+                        && peek == '/'
+                    {
+                        chars.next();
+                        break;
+                    }
+                }
+                continue;
+            } else if regex_can_start(&result)
+                && let Some(body) = scan_regex_literal(&chars)
+            {
+                result.push(c);
+                for _ in 0..body.chars().count() {
+                    chars.next();
+                }
+                result.push_str(&body);
+                continue;
+            }
+        }
+
+        result.push(c);
+    }
+
+    result
+}
+
+/// Replaces the contents of string and template literals with spaces, keeping the
+/// quotes, line breaks and `${ }` interpolations, so that text inside literals is not
+/// mistaken for code. Expects content that has already been stripped of comments.
+pub(crate) fn mask_string_literals(code: &str) -> String {
+    let mask = |c: char| if c == '\n' { '\n' } else { ' ' };
+
+    let mut result = String::with_capacity(code.len());
+    let mut chars = code.chars().peekable();
+    let mut in_string: Option<char> = None;
+    let mut templates: Vec<TemplateContext> = Vec::new();
+
+    while let Some(c) = chars.next() {
+        if let Some(quote) = in_string {
+            if c == quote {
+                in_string = None;
+                result.push(c);
+            } else {
+                result.push(mask(c));
+                if c == '\\'
+                    && let Some(next) = chars.next()
+                {
+                    result.push(mask(next));
+                }
+            }
+            continue;
+        }
+
+        if let Some(TemplateContext::Literal) = templates.last() {
+            if c == '`' {
+                templates.pop();
+                result.push(c);
+            } else if c == '$' && chars.peek() == Some(&'{') {
+                chars.next();
+                result.push_str("${");
+                templates.push(TemplateContext::Interpolation(0));
+            } else {
+                result.push(mask(c));
+                if c == '\\'
+                    && let Some(next) = chars.next()
+                {
+                    result.push(mask(next));
+                }
+            }
+            continue;
+        }
+
+        if let Some(TemplateContext::Interpolation(depth)) = templates.last_mut() {
+            if c == '{' {
+                *depth += 1;
+            } else if c == '}' {
+                if *depth == 0 {
+                    templates.pop();
+                } else {
+                    *depth -= 1;
+                }
+            }
+        }
+
+        match c {
+            '"' | '\'' => in_string = Some(c),
+            '`' => templates.push(TemplateContext::Literal),
+            _ => {}
+        }
+        result.push(c);
+    }
+
+    result
+}
+
+/// Keywords after which a `/` starts a regex literal rather than a division.
+const REGEX_PRECEDING_KEYWORDS: &[&str] = &[
+    "return",
+    "typeof",
+    "instanceof",
+    "case",
+    "do",
+    "else",
+    "in",
+    "of",
+    "new",
+    "delete",
+    "void",
+    "throw",
+    "yield",
+    "await",
+];
+
+/// Decides from the code emitted so far whether a `/` at this position starts a
+/// regex literal. A regex can follow an operator, an opening bracket, a separator
+/// or one of a few keywords; after an identifier, a number or a closing bracket
+/// the `/` is a division.
+fn regex_can_start(preceding: &str) -> bool {
+    let preceding = preceding.trim_end();
+    let Some(last) = preceding.chars().last() else {
+        return true;
+    };
+
+    if "=(,:[!&|?{};+-*%~^".contains(last) {
+        return true;
+    }
+
+    if last.is_alphanumeric() || last == '_' || last == '$' {
+        let word: String = preceding
+            .chars()
+            .rev()
+            .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect();
+        return REGEX_PRECEDING_KEYWORDS.contains(&word.as_str());
+    }
+
+    false
+}
+
+/// Looks ahead from just after an opening `/` and returns the rest of the regex
+/// literal up to and including its closing `/`. Regex literals cannot span lines,
+/// so `None` is returned when no closing slash is found on the current line.
+fn scan_regex_literal(chars: &std::iter::Peekable<std::str::Chars>) -> Option<String> {
+    let mut lookahead = chars.clone();
+    let mut body = String::new();
+    let mut in_class = false;
+
+    while let Some(c) = lookahead.next() {
+        if c == '\n' {
+            return None;
+        }
+        body.push(c);
+
+        match c {
+            '\\' => {
+                let escaped = lookahead.next()?;
+                if escaped == '\n' {
+                    return None;
+                }
+                body.push(escaped);
+            }
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '/' if !in_class => return Some(body),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Modifiers that may appear between `export` and the declaration keyword.
+const DECLARATION_MODIFIERS: &[&str] = &["default", "declare", "abstract", "async"];
+
+/// Declaration keywords that introduce an exported entity.
+const DECLARATION_KEYWORDS: &[&str] = &[
+    "class",
+    "enum",
+    "type",
+    "interface",
+    "function",
+    "namespace",
+    "module",
+    "const",
+    "let",
+    "var",
+];
+
+/// Finds the declaration keyword following `export` (skipping modifiers such as
+/// `default` or `abstract`) and returns it with the rest of the line starting at it.
+/// `export const enum` is reported as an `enum` declaration.
+fn exported_declaration(line: &str) -> Option<(&'static str, &str)> {
+    let export_pos = line
+        .match_indices("export")
+        .map(|(pos, _)| pos)
+        .find(|&pos| {
+            let before_ok =
+                !line[..pos].ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == '$');
+            let after_ok = line[pos + "export".len()..].starts_with(char::is_whitespace);
+            before_ok && after_ok
+        })?;
+
+    declaration_keyword(&line[export_pos + "export".len()..])
+}
+
+/// Finds the declaration keyword of a top-level declaration that is not exported,
+/// i.e. one that starts at the beginning of the line.
+fn internal_declaration(line: &str) -> Option<(&'static str, &str)> {
+    if line.starts_with(char::is_whitespace) {
+        return None;
+    }
+    declaration_keyword(line)
+}
+
+/// Finds the declaration keyword at the start of `text`, after any modifiers and
+/// decorators, and returns it with the rest of the text starting at it.
+fn declaration_keyword(text: &str) -> Option<(&'static str, &str)> {
+    let mut rest = text.trim_start();
+    loop {
+        if let Some(after_decorator) = skip_decorator(rest) {
+            rest = after_decorator.trim_start();
+            continue;
+        }
+        let word = leading_word(rest);
+        if !DECLARATION_MODIFIERS.contains(&word) {
+            break;
+        }
+        rest = rest[word.len()..].trim_start();
+    }
+
+    let word = leading_word(rest);
+    let keyword = DECLARATION_KEYWORDS.iter().copied().find(|&k| k == word)?;
+
+    if keyword == "const" {
+        let after_const = rest["const".len()..].trim_start();
+        if leading_word(after_const) == "enum" {
+            return Some(("enum", after_const));
+        }
+    }
+
+    Some((keyword, rest))
+}
+
+/// Skips a decorator such as `@Injectable()` at the start of `text`, returning the
+/// text after it, or `None` when `text` does not start with a complete decorator.
+fn skip_decorator(text: &str) -> Option<&str> {
+    let after_at = text.strip_prefix('@')?;
+    let name = leading_word(after_at);
+    if name.is_empty() {
+        return None;
+    }
+    let rest = &after_at[name.len()..];
+    if rest.starts_with('(') {
+        matching_bracket(rest).map(|end| &rest[end + 1..])
+    } else {
+        Some(rest)
+    }
+}
+
+/// Splits the content into lines, joining an export header left dangling at the end
+/// of a line (`export`, `export default`, `export class`, ...) with the following
+/// non-empty lines, as some formatters produce. Each line keeps the index of the
+/// line it starts on.
+fn declaration_lines(content: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut pending: Option<(usize, String)> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let (start, joined) = match pending.take() {
+            Some((start, header)) if line.trim().is_empty() => {
+                pending = Some((start, header));
+                continue;
+            }
+            Some((start, header)) => (start, format!("{} {}", header, line.trim())),
+            None => (index, line.to_string()),
+        };
+
+        if is_export_header(&joined) {
+            pending = Some((start, joined));
+        } else {
+            lines.push((start, joined));
+        }
+    }
+
+    lines.extend(pending);
+    lines
+}
+
+/// Whether the line ends with `export` followed only by modifiers, declaration
+/// keywords and decorators, i.e. the declared name is still to come.
+fn is_export_header(line: &str) -> bool {
+    let mut words = line.split_whitespace().rev();
+    for word in words.by_ref() {
+        if word == "export" {
+            return true;
+        }
+        let is_decorator = skip_decorator(word).is_some_and(str::is_empty);
+        if !is_decorator
+            && !DECLARATION_MODIFIERS.contains(&word)
+            && !DECLARATION_KEYWORDS.contains(&word)
+        {
+            return false;
+        }
+    }
+    false
+}
+
+/// Returns the leading identifier-like word of `text`.
+fn leading_word(text: &str) -> &str {
+    let end = text
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+/// Returns every name bound by the declarators of a `const`/`let`/`var` statement,
+/// e.g. `a = 1, { b, c: d } = obj` binds `a`, `b` and `d`. A name is a `Function`
+/// when it is the sole target of a function expression, otherwise a `Const`.
+fn declared_variables(declarators: &str) -> Vec<(String, EntityType)> {
+    let mut variables = Vec::new();
+
+    for declarator in split_top_level(declarators) {
+        let declarator = declarator.trim();
+        let mut names = Vec::new();
+        collect_binding_names(declarator, &mut names);
+
+        let is_pattern = declarator.starts_with(['{', '[']);
+        for name in names {
+            let entity_type = if !is_pattern && is_function_initializer(declarator) {
+                EntityType::Function
+            } else {
+                EntityType::Const
+            };
+            variables.push((name, entity_type));
+        }
+    }
+
+    variables
+}
+
+/// Collects the names bound by a declaration target: a plain identifier or an
+/// object/array destructuring pattern (including renames, defaults and rest elements).
+fn collect_binding_names(target: &str, names: &mut Vec<String>) {
+    let target = target.trim_start();
+    let target = target.strip_prefix("...").unwrap_or(target).trim_start();
+
+    let Some(open @ ('{' | '[')) = target.chars().next() else {
+        let name = leading_word(target);
+        if !name.is_empty() {
+            names.push(name.to_string());
+        }
+        return;
+    };
+    let Some(close) = matching_bracket(target) else {
+        return;
+    };
+
+    for element in split_top_level(&target[1..close]) {
+        let element = element.trim();
+        if element.is_empty() {
+            continue;
+        }
+        match element.find(':') {
+            // `{ key: binding }` binds whatever follows the colon
+            Some(colon) if open == '{' && !element.starts_with("...") => {
+                collect_binding_names(&element[colon + 1..], names)
+            }
+            _ => collect_binding_names(element, names),
+        }
+    }
+}
+
+/// Returns the byte index of the bracket closing the one that `text` starts with.
+fn matching_bracket(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits `text` on commas that are not nested in brackets, generics or strings,
+/// ignoring anything after a top-level `;`.
+fn split_top_level(text: &str) -> Vec<&str> {
+    let bytes = text.as_bytes();
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quote: Option<u8> = None;
+    let mut start = 0;
+
+    for (i, &b) in bytes.iter().enumerate() {
+        if let Some(q) = quote {
+            if b == q && (i == 0 || bytes[i - 1] != b'\\') {
+                quote = None;
+            }
+            continue;
+        }
+        match b {
+            b'\'' | b'"' | b'`' => quote = Some(b),
+            b'(' | b'[' | b'{' | b'<' => depth += 1,
+            b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+            b'>' if i > 0 && bytes[i - 1] != b'=' => depth = depth.saturating_sub(1),
+            b',' if depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + 1;
+            }
+            b';' if depth == 0 => {
+                parts.push(&text[start..i]);
+                return parts;
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Joins the lines of the declaration starting on the 1-based `line` up to the one
+/// opening its body.
+fn declaration_header(lines: &[&str], line: usize) -> String {
+    let mut header = String::new();
+    for text in lines.iter().skip(line.saturating_sub(1)).take(10) {
+        header.push_str(text);
+        header.push(' ');
+        if text.contains('{') {
+            break;
+        }
+    }
+    header
+}
+
+/// Returns the index of the last line of the declaration starting at line index
+/// `start` of `lines`, whose string literals are masked: the first line where its
+/// brackets are balanced and that neither ends with an operator nor is continued
+/// by the next line (e.g. `| 'b'` in a union type or `.map(...)` in a chain).
+fn declaration_end(lines: &[&str], start: usize) -> usize {
+    const CONTINUING_ENDS: &[&str] = &["=", "=>", ",", "(", "[", "{", ":", "|", "&", "?", "+"];
+    const CONTINUING_STARTS: &[&str] = &["|", "&", ".", "?", ":"];
+
+    let mut depth = 0i32;
+    for (index, line) in lines.iter().enumerate().skip(start) {
+        for c in line.chars() {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        let line = line.trim_end();
+        let continued = line.is_empty()
+            || CONTINUING_ENDS.iter().any(|end| line.ends_with(end))
+            || lines.get(index + 1).is_some_and(|next| {
+                let next = next.trim_start();
+                CONTINUING_STARTS
+                    .iter()
+                    .any(|start| next.starts_with(start) && !next.starts_with("..."))
+            });
+        if depth <= 0 && !continued {
+            return index;
+        }
+    }
+    lines.len().saturating_sub(1)
+}
+
+/// Names in the `extends` and `implements` clauses of a class or interface
+/// declaration, e.g. `Bar` and `Baz` for
+/// `class Foo<T extends Base> extends Bar<T> implements Baz {`. Qualified names such
+/// as `ns.Base` are left out.
+fn heritage_names(header: &str) -> Vec<String> {
+    // Only the text outside type parameters and arguments, up to the body
+    let mut outer = String::new();
+    let mut depth = 0usize;
+    let mut previous = ' ';
+    for c in header.chars() {
+        match c {
+            '{' if depth == 0 => break,
+            '<' => depth += 1,
+            '>' if previous != '=' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => outer.push(c),
+            _ => {}
+        }
+        previous = c;
+    }
+
+    let mut names = Vec::new();
+    for clause in HERITAGE_RE.split(&outer).skip(1) {
+        for item in clause.split(',').map(str::trim) {
+            let Some(name) = IDENTIFIER_RE.find(item).filter(|name| name.start() == 0) else {
+                continue;
+            };
+            if !item[name.end()..].trim_start().starts_with('.') {
+                names.push(name.as_str().to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Returns the local name, exported name and 1-based line of each name exported
+/// with an export list without `from`, such as `export { internalFoo as Foo, bar }`.
+/// `export { foo as default }` exports `foo` under its own name, like `export default foo`.
+fn local_exports(content: &str) -> Vec<(String, String, usize)> {
+    let mut exports = Vec::new();
+    for caps in EXPORT_LIST_RE.captures_iter(content) {
+        if caps.get(2).is_some() {
+            continue;
+        }
+        let start = caps.get(0).map_or(0, |m| m.start());
+        let line = content[..start].matches('\n').count() + 1;
+
+        for specifier in caps[1].split(',') {
+            let specifier = specifier.trim();
+            let specifier = specifier.strip_prefix("type ").unwrap_or(specifier);
+            let parts: Vec<&str> = specifier.split_whitespace().collect();
+            let (local, exported) = match parts[..] {
+                [local] => (local, local),
+                [local, "as", "default"] => (local, local),
+                [local, "as", exported] => (local, exported),
+                _ => continue,
+            };
+            exports.push((local.to_string(), exported.to_string(), line));
+        }
+    }
+    exports
+}
+
+/// Finds the (not necessarily exported) declaration of `name` in the file, used for
+/// `export = name` and export lists. Returns its type, falling back to `Const` when no declaration is
+/// found, and its 1-based line.
+fn local_declaration(content: &str, name: &str) -> (EntityType, Option<usize>) {
+    let pattern = format!(
+        r"\b(class|enum|interface|type|function|namespace|const|let|var)\s+{}\b",
+        regex::escape(name)
+    );
+    let Some(caps) = Regex::new(&pattern)
+        .ok()
+        .and_then(|re| re.captures(content))
+    else {
+        return (EntityType::Const, None);
+    };
+
+    let entity_type = match &caps[1] {
+        "class" => EntityType::Class,
+        "enum" => EntityType::Enum,
+        "interface" => EntityType::Interface,
+        "type" => EntityType::Type,
+        "function" => EntityType::Function,
+        "namespace" => EntityType::Namespace,
+        _ => EntityType::Const,
+    };
+    let start = caps.get(0).map_or(0, |m| m.start());
+    let line = content[..start].matches('\n').count() + 1;
+
+    (entity_type, Some(line))
+}
+
+/// Returns the initializer of a variable declaration line: everything after the
+/// first top-level `=` that is not part of `=>`, `==` or a type annotation.
+fn declaration_initializer(line: &str) -> Option<&str> {
+    let bytes = line.as_bytes();
+    let mut depth = 0usize;
+
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            b'(' | b'[' | b'{' | b'<' => depth += 1,
+            b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+            b'>' if i > 0 && bytes[i - 1] != b'=' => depth = depth.saturating_sub(1),
+            b'=' if depth == 0 => {
+                let next = bytes.get(i + 1).copied();
+                let prev = if i > 0 { bytes[i - 1] } else { b' ' };
+                if next != Some(b'>') && next != Some(b'=') && !b"=!<>".contains(&prev) {
+                    return Some(line[i + 1..].trim());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Checks whether a `const`/`let`/`var` declaration is initialized with a function
+/// expression. Arrow functions nested inside object or array literals (or passed as
+/// call arguments) do not count, only an arrow or `function` that is the whole initializer.
+fn is_function_initializer(line: &str) -> bool {
+    let Some(initializer) = declaration_initializer(line) else {
+        return false;
+    };
+
+    let initializer = initializer
+        .strip_prefix("async")
+        .map_or(initializer, |rest| {
+            if rest.starts_with(|c: char| c.is_whitespace() || c == '(') {
+                rest.trim_start()
+            } else {
+                initializer
+            }
+        });
+
+    if initializer.starts_with("function") {
+        return true;
+    }
+
+    // Look for `=>` outside of any bracket: `(a, b) => ...`, `x => ...`, `<T>(x: T) => ...`
+    let bytes = initializer.as_bytes();
+    let mut depth = 0usize;
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => {
+                if depth == 0 {
+                    return false;
+                }
+                depth -= 1;
+            }
+            b'=' if depth == 0 && bytes.get(i + 1) == Some(&b'>') => return true,
+            _ => {}
+        }
+    }
+
+    false
+}
+
+/// Returns the identifier following `keyword` in a declaration line, e.g. `Foo`
+/// for `export class Foo {` and the keyword `class`.
+pub(crate) fn extract_export_name(line: &str, keyword: &str) -> Option<String> {
+    let mut search_start = 0;
+
+    while let Some(relative_pos) = line[search_start..].find(keyword) {
+        let pos = search_start + relative_pos;
+
+        // Check that keyword is not part of another word:
+        // - preceded by non-alphanumeric (or start of string)
+        // - followed by whitespace
+        let char_before_ok = pos == 0 || {
+            let prev_char = line[..pos].chars().last().unwrap();
+            !prev_char.is_alphanumeric() && prev_char != '_'
+        };
+
+        let after_keyword = &line[pos + keyword.len()..];
+        let char_after_ok = after_keyword.starts_with(|c: char| c.is_whitespace());
+
+        if char_before_ok && char_after_ok {
+            let identifier: String = after_keyword
+                .trim_start()
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .collect();
+
+            if !identifier.is_empty() {
+                return Some(identifier);
+            }
+        }
+
+        // Continue searching after this position
+        search_start = pos + keyword.len();
+    }
+
+    None
+}
+
+/// Returns the identifiers referenced inside decorator argument blocks, e.g.
+/// `FooService` and `BarPipe` in `@Component({ providers: [FooService], imports: [BarPipe] })`.
+/// Blocks may span several lines; object keys and string literals are ignored.
+fn decorator_references(content: &str) -> HashSet<String> {
+    let mut references = HashSet::new();
+
+    for decorator in DECORATOR_RE.find_iter(content) {
+        let block_start = decorator.end() - 1;
+        let Some(block_len) = matching_bracket(&content[block_start..]) else {
+            continue;
+        };
+        let block = &content[block_start + 1..block_start + block_len];
+        let block = STRING_LITERAL_RE.replace_all(block, "''");
+
+        for identifier in IDENTIFIER_RE.find_iter(&block) {
+            let is_key = block[identifier.end()..].trim_start().starts_with(':');
+            let is_member = block[..identifier.start()].ends_with('.');
+            if !is_key && !is_member {
+                references.insert(identifier.as_str().to_string());
+            }
+        }
+    }
+
+    references
+}
+
+/// Returns the element names rendered in the markup of a `.tsx` file, e.g. `Foo` for
+/// `<Foo />` and `<Foo.Item>`. Works on unmasked code, because apostrophes in JSX
+/// text such as `<p>Don't</p>` would otherwise be taken for string literals.
+fn jsx_element_names(content: &str, file_path: &str) -> HashSet<String> {
+    if !file_path.ends_with(".tsx") {
+        return HashSet::new();
+    }
+
+    JSX_ELEMENT_RE
+        .captures_iter(content)
+        .map(|caps| caps[1].to_string())
+        .collect()
+}
+
+/// Checks whether `code` references the entity anywhere other than its declarations.
+/// Occurrences on the lines declaring it are not references: the line holding the
+/// first occurrence of the name at or after each 1-based line of `declaration_lines`
+/// (the declaration itself and any overload signatures). Neither are member accesses
+/// such as `this.name` or occurrences where a parameter or block-scoped variable
+/// of the same name shadows the entity.
+/// Expects code with comments stripped and string literals masked.
+fn is_entity_used_locally(code: &str, entity_name: &str, declaration_lines: &[usize]) -> bool {
+    let pattern = format!(r"\b{}\b", regex::escape(entity_name));
+    let Ok(re) = Regex::new(&pattern) else {
+        return false;
+    };
+
+    let mut excluded: Vec<Range<usize>> = declaration_lines
+        .iter()
+        .filter_map(|&declaration_line| {
+            let line_start: usize = code
+                .split_inclusive('\n')
+                .take(declaration_line.saturating_sub(1))
+                .map(str::len)
+                .sum();
+            re.find_at(code, line_start)
+                .map(|m| line_bounds(code, m.start()))
+        })
+        .collect();
+    excluded.extend(
+        re.find_iter(code)
+            .filter_map(|m| binding_scope(code, m.start(), m.end())),
+    );
+
+    re.find_iter(code).any(|m| {
+        let before = &code[..m.start()];
+        let is_member = before.ends_with('.') && !before.ends_with("...");
+        !is_member && !excluded.iter().any(|range| range.contains(&m.start()))
+    })
+}
+
+/// Returns the byte range of the line containing `pos`.
+fn line_bounds(code: &str, pos: usize) -> Range<usize> {
+    let start = code[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let end = code[pos..].find('\n').map_or(code.len(), |i| pos + i);
+    start..end
+}
+
+/// When the name at `start..end` binds a local variable, returns the range where
+/// that binding shadows any outer declaration: the body of the function whose
+/// parameter it is (including `catch` clauses and `x => ...` arrows), or the rest of
+/// the block or `for` statement declaring it with `const`, `let` or `var`.
+fn binding_scope(code: &str, start: usize, end: usize) -> Option<Range<usize>> {
+    let before = code[..start].trim_end();
+    let after = code[end..].trim_start();
+
+    if let Some(body) = after.strip_prefix("=>") {
+        let body_start = code.len() - body.len();
+        return Some(start..body_end(code, body_start));
+    }
+
+    let keyword = trailing_word(before);
+    if matches!(keyword, "const" | "let" | "var") {
+        // `export const name` is a declaration of the module, not a local binding
+        let before_keyword = before[..before.len() - keyword.len()].trim_end();
+        if matches!(trailing_word(before_keyword), "export" | "declare") {
+            return None;
+        }
+        let open = enclosing_bracket(code, start)?;
+        return match code.as_bytes()[open] {
+            b'{' => Some(start..block_end(code, open)),
+            b'(' => {
+                let close = open + matching_bracket(&code[open..])?;
+                Some(open..body_end(code, close + 1))
+            }
+            _ => None,
+        };
+    }
+
+    let is_parameter_end = after.starts_with([':', ',', ')', '?'])
+        || (after.starts_with('=') && !after.starts_with("==") && !after.starts_with("=>"));
+    let is_parameter_start = before.ends_with(['(', ',']) || before.ends_with("...");
+    if !(is_parameter_end && is_parameter_start) {
+        return None;
+    }
+
+    let open = enclosing_bracket(code, start).filter(|&open| code.as_bytes()[open] == b'(')?;
+    if matches!(
+        trailing_word(code[..open].trim_end()),
+        "if" | "while" | "for" | "switch" | "with" | "return"
+    ) {
+        return None;
+    }
+    let close = open + matching_bracket(&code[open..])?;
+    let body_start = function_body_start(code, close + 1)?;
+    Some(open..body_end(code, body_start))
+}
+
+/// Returns the trailing identifier-like word of `text`.
+fn trailing_word(text: &str) -> &str {
+    let start = text
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .map_or(0, |i| i + 1);
+    &text[start..]
+}
+
+/// Returns the byte index of the innermost bracket that is still open at `pos`.
+fn enclosing_bracket(code: &str, pos: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, b) in code.as_bytes()[..pos].iter().enumerate().rev() {
+        match b {
+            b')' | b']' | b'}' => depth += 1,
+            b'(' | b'[' | b'{' if depth == 0 => return Some(i),
+            b'(' | b'[' | b'{' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns the byte index just past the `}` closing the block opened at `open`, or
+/// the end of the code when it is not closed.
+fn block_end(code: &str, open: usize) -> usize {
+    matching_bracket(&code[open..]).map_or(code.len(), |close| open + close + 1)
+}
+
+/// After the closing parenthesis of a parameter list, skips an optional return type
+/// annotation and returns the index where the function body starts: its `{`, or the
+/// expression after `=>`. Returns `None` when no body follows, as after the arguments
+/// of a call.
+fn function_body_start(code: &str, from: usize) -> Option<usize> {
+    let rest = code[from..].trim_start();
+    let offset = code.len() - rest.len();
+    if rest.starts_with('{') {
+        return Some(offset);
+    }
+    if let Some(body) = rest.strip_prefix("=>") {
+        return Some(code.len() - body.len());
+    }
+    let return_type = rest.strip_prefix(':')?;
+
+    // The return type ends at the body; anything ending the expression first means
+    // the `:` belonged to something else, such as a conditional
+    let type_start = code.len() - return_type.len();
+    let mut depth = 0usize;
+    let bytes = code.as_bytes();
+    for i in type_start..bytes.len() {
+        match bytes[i] {
+            b'=' if bytes.get(i + 1) == Some(&b'>') => return Some(i + 2),
+            b'{' if depth == 0 => return Some(i),
+            b'(' | b'[' | b'<' | b'{' => depth += 1,
+            b')' | b']' | b'>' | b'}' if depth > 0 => depth -= 1,
+            b';' | b',' | b')' | b'}' | b'\n' | b'?' | b'=' => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns the end of a function body starting at `start`: just past its closing `}`
+/// for a block, otherwise the end of the arrow function's expression.
+fn body_end(code: &str, start: usize) -> usize {
+    let rest = code[start..].trim_start();
+    let body_start = code.len() - rest.len();
+    if rest.starts_with('{') {
+        return block_end(code, body_start);
+    }
+
+    let mut depth = 0usize;
+    for (i, b) in rest.bytes().enumerate() {
+        match b {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' if depth == 0 => return body_start + i,
+            b')' | b']' | b'}' => depth -= 1,
+            b';' | b',' if depth == 0 => return body_start + i,
+            _ => {}
+        }
+    }
+    code.len()
+}
+
+/// Returns the distinct members accessed on `namespace` in `content`, through
+/// `namespace.Member` or `namespace['Member']`, and whether the namespace is also
+/// referenced on its own. The import statement at `statement` is skipped.
+fn namespace_members(
+    content: &str,
+    namespace: &str,
+    statement: Range<usize>,
+) -> (Vec<String>, bool) {
+    let pattern = format!(
+        r#"\b{}\b(?:\s*\.\s*([\w$]+)|\s*\[\s*['"]([\w$]+)['"]\s*\])?"#,
+        regex::escape(namespace)
+    );
+    let Ok(re) = Regex::new(&pattern) else {
+        return (Vec::new(), true);
+    };
+
+    let mut members: Vec<String> = Vec::new();
+    let mut used_whole = false;
+    for cap in re.captures_iter(content) {
+        let reference = cap.get(0).unwrap();
+        let before = &content[..reference.start()];
+        if statement.contains(&reference.start())
+            || (before.ends_with('.') && !before.ends_with("..."))
+        {
+            continue;
+        }
+
+        match cap.get(1).or(cap.get(2)) {
+            Some(member) => {
+                if !members.iter().any(|known| known == member.as_str()) {
+                    members.push(member.as_str().to_string());
+                }
+            }
+            None => used_whole = true,
+        }
+    }
+
+    (members, used_whole)
+}
+
+/// Removes a bundler query or fragment from an import source, e.g. `./icon.svg`
+/// for `./icon.svg?raw`. A leading `#` (package subpath imports) is kept.
+fn strip_resource_query(import_source: &str) -> &str {
+    match import_source
+        .char_indices()
+        .skip(1)
+        .find(|(_, c)| matches!(c, '?' | '#'))
+    {
+        Some((index, _)) => &import_source[..index],
+        None => import_source,
+    }
+}
+
+/// Counts whole-word occurrences of `word` in `content`.
+fn count_word_occurrences(content: &str, word: &str) -> usize {
+    let pattern = format!(r"\b{}\b", regex::escape(word));
+    match Regex::new(&pattern) {
+        Ok(re) => re.find_iter(content).count(),
+        Err(_) => 0,
+    }
 }
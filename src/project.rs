@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::filesystem::FileSystem;
+
+/// The part of a `project.json` that is read.
+#[derive(Debug, Default, Deserialize)]
+struct ProjectConfig {
+    name: Option<String>,
+}
+
+/// An entry of the `projects` of an older `nx.json`; only entries with a `root`
+/// define a project.
+#[derive(Debug, Deserialize)]
+struct NxProject {
+    root: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct NxConfig {
+    #[serde(default)]
+    projects: HashMap<String, NxProject>,
+}
+
+/// Finds the Nx project owning a file: the nearest directory at or above it, up to
+/// the workspace root, that has a `project.json` or is the `root` of a project
+/// listed in `nx.json`. A `project.json` without a `name` is named after its
+/// directory. Lookups are cached per directory.
+#[derive(Debug)]
+pub(crate) struct ProjectIndex<'a> {
+    file_system: &'a dyn FileSystem,
+    root: PathBuf,
+    /// Project names by root directory, from `nx.json`
+    nx_roots: HashMap<PathBuf, String>,
+    dirs: HashMap<PathBuf, Option<String>>,
+}
+
+impl<'a> ProjectIndex<'a> {
+    pub fn new(file_system: &'a dyn FileSystem, root_path: &Path) -> Self {
+        let nx_config: NxConfig = file_system
+            .read_to_string(&root_path.join("nx.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        let nx_roots = nx_config
+            .projects
+            .into_iter()
+            .filter_map(|(name, project)| Some((root_path.join(project.root?), name)))
+            .collect();
+
+        ProjectIndex {
+            file_system,
+            root: root_path.to_path_buf(),
+            nx_roots,
+            dirs: HashMap::new(),
+        }
+    }
+
+    /// The name of the project owning `file_path`, if any.
+    pub fn project_of(&mut self, file_path: &str) -> Option<String> {
+        self.project_of_dir(Path::new(file_path).parent()?)
+    }
+
+    fn project_of_dir(&mut self, dir: &Path) -> Option<String> {
+        if let Some(project) = self.dirs.get(dir) {
+            return project.clone();
+        }
+
+        let project = if !dir.starts_with(&self.root) {
+            None
+        } else if let Some(name) = self.read_project_name(dir) {
+            Some(name)
+        } else if let Some(name) = self.nx_roots.get(dir) {
+            Some(name.clone())
+        } else if dir == self.root {
+            None
+        } else {
+            dir.parent().and_then(|parent| self.project_of_dir(parent))
+        };
+
+        self.dirs.insert(dir.to_path_buf(), project.clone());
+        project
+    }
+
+    /// Reads the name of the project defined by `dir/project.json`, if there is one.
+    fn read_project_name(&self, dir: &Path) -> Option<String> {
+        let content = self
+            .file_system
+            .read_to_string(&dir.join("project.json"))
+            .ok()?;
+        let config: ProjectConfig = serde_json::from_str(&content).unwrap_or_default();
+        config.name.or_else(|| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::RealFileSystem;
+    use std::fs;
+
+    #[test]
+    fn test_project_of_finds_nearest_project() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        for dir in [
+            "libs/ui/src/button",
+            "libs/data/src",
+            "apps/web/src",
+            "tools",
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(
+            root.join("libs/ui/project.json"),
+            r#"{"name": "shared-ui"}"#,
+        )
+        .unwrap();
+        fs::write(root.join("libs/data/project.json"), "{}").unwrap();
+        fs::write(
+            root.join("nx.json"),
+            r#"{"projects": {"web": {"root": "apps/web", "tags": []}}}"#,
+        )
+        .unwrap();
+
+        let mut index = ProjectIndex::new(&RealFileSystem, root);
+        let project_of = |index: &mut ProjectIndex, file: &str| {
+            index.project_of(&root.join(file).to_string_lossy())
+        };
+
+        assert_eq!(
+            project_of(&mut index, "libs/ui/src/button/button.ts").as_deref(),
+            Some("shared-ui")
+        );
+        assert_eq!(
+            project_of(&mut index, "libs/data/src/store.ts").as_deref(),
+            Some("data")
+        );
+        assert_eq!(
+            project_of(&mut index, "apps/web/src/main.ts").as_deref(),
+            Some("web")
+        );
+        assert_eq!(project_of(&mut index, "tools/script.ts"), None);
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+
+/// How much informational output the commands print.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verbosity {
+    /// Only results and warnings
+    Quiet,
+    /// Results, warnings and progress messages such as the directories being scanned
+    #[default]
+    Normal,
+}
+
+/// Routes informational messages according to the verbosity. Warnings are always
+/// written to stderr so they never mix with results on stdout.
+pub(crate) struct Reporter<'w> {
+    verbosity: Verbosity,
+    out: Box<dyn Write + 'w>,
+}
+
+impl<'w> Reporter<'w> {
+    pub fn new(verbosity: Verbosity, out: impl Write + 'w) -> Self {
+        Reporter {
+            verbosity,
+            out: Box::new(out),
+        }
+    }
+
+    /// Reporter writing informational messages to stdout.
+    pub fn stdout(verbosity: Verbosity) -> Self {
+        Reporter::new(verbosity, io::stdout())
+    }
+
+    pub fn info(&mut self, message: impl Display) {
+        if self.verbosity != Verbosity::Quiet {
+            // Informational output is best effort, a closed stdout is not an error
+            let _ = writeln!(self.out, "{}", message);
+        }
+    }
+
+    pub fn warn(&self, message: impl Display) {
+        eprintln!("Warning: {}", message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quiet_reporter_drops_info() {
+        let mut buffer = Vec::new();
+        {
+            let mut reporter = Reporter::new(Verbosity::Quiet, &mut buffer);
+            reporter.info("Scanning directory");
+        }
+        assert!(buffer.is_empty());
+
+        {
+            let mut reporter = Reporter::new(Verbosity::Normal, &mut buffer);
+            reporter.info("Scanning directory");
+        }
+        assert_eq!(String::from_utf8(buffer).unwrap(), "Scanning directory\n");
+    }
+}
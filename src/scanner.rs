@@ -1,22 +1,25 @@
-use std::fs;
-use std::path::Path;
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::thread;
 
 use anyhow::Result;
+use serde::Serialize;
 
-const DEFAULT_SKIP_DIRECTORIES: &[&str] = &[
-    "mocks",
-    "__mocks__",
-    "mocks_stubs",
-    "tests",
-    "environments",
-    "i18n",
-    ".storybook",
-    "fakes",
-];
+use crate::analysis::wildcard_match;
+use crate::entity::normalize_path;
+use crate::filesystem::{FileSystem, RealFileSystem};
+
+const DEFAULT_SKIP_DIRECTORIES: &[&str] = &["environments", "i18n", ".storybook"];
+
+const DEFAULT_SKIP_FILE_SUFFIXES: &[&str] = &[".d.ts", ".d.mts", ".d.cts", ".stories.ts"];
 
-const DEFAULT_SKIP_FILE_SUFFIXES: &[&str] = &[
-    ".d.ts",
-    ".stories.ts",
+/// Directories holding test doubles and helpers, skipped unless tests are included.
+const TEST_SKIP_DIRECTORIES: &[&str] = &["mocks", "__mocks__", "mocks_stubs", "tests", "fakes"];
+
+/// File name suffixes of test doubles and helpers, skipped unless tests are included.
+const TEST_SKIP_FILE_SUFFIXES: &[&str] = &[
     "-stub.ts",
     "-test.ts",
     "-stub.service.ts",
@@ -24,72 +27,649 @@ const DEFAULT_SKIP_FILE_SUFFIXES: &[&str] = &[
     "mock.ts",
 ];
 
-pub(crate) struct Scanner {
-    skip_directories: Vec<&'static str>,
-    skip_file_suffixes: Vec<&'static str>,
+/// Upper bound of the default number of directory walking threads, which keeps the
+/// number of open directories low on machines with many cores.
+const MAX_DEFAULT_WALK_THREADS: usize = 8;
+
+/// Extensions (without the dot) of the files that are scanned by default.
+pub(crate) const DEFAULT_EXTENSIONS: &[&str] = &["ts", "tsx", "mts", "cts"];
+
+/// A directory or file that could not be read or parsed and was skipped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScanError {
+    pub path: String,
+    pub message: String,
 }
 
-impl Scanner {
+impl ScanError {
+    pub(crate) fn new(path: &Path, error: impl std::fmt::Display) -> Self {
+        ScanError {
+            path: path.to_string_lossy().to_string(),
+            message: error.to_string(),
+        }
+    }
+}
+
+pub(crate) struct Scanner<'a> {
+    file_system: &'a dyn FileSystem,
+    skip_directories: Vec<String>,
+    skip_file_suffixes: Vec<String>,
+    extensions: Vec<String>,
+    /// Glob patterns of excluded paths, matched relative to `exclude_root`
+    excludes: Vec<String>,
+    exclude_root: PathBuf,
+    max_depth: Option<usize>,
+    follow_symlinks: bool,
+    walk_threads: usize,
+    include_tests: bool,
+}
+
+impl<'a> Scanner<'a> {
     pub fn new() -> Self {
         Scanner {
-            skip_directories: DEFAULT_SKIP_DIRECTORIES.to_vec(),
-            skip_file_suffixes: DEFAULT_SKIP_FILE_SUFFIXES.to_vec(),
+            file_system: &RealFileSystem,
+            skip_directories: DEFAULT_SKIP_DIRECTORIES
+                .iter()
+                .map(|dir| dir.to_string())
+                .collect(),
+            skip_file_suffixes: DEFAULT_SKIP_FILE_SUFFIXES
+                .iter()
+                .map(|suffix| suffix.to_string())
+                .collect(),
+            extensions: DEFAULT_EXTENSIONS
+                .iter()
+                .map(|extension| extension.to_string())
+                .collect(),
+            excludes: Vec::new(),
+            exclude_root: PathBuf::new(),
+            max_depth: None,
+            follow_symlinks: false,
+            walk_threads: thread::available_parallelism()
+                .map_or(1, NonZeroUsize::get)
+                .min(MAX_DEFAULT_WALK_THREADS),
+            include_tests: false,
         }
     }
 
-    pub fn scan(&self, dir: &Path) -> Result<Vec<String>> {
-        let mut ts_files = Vec::new();
+    /// Reads the directories from `file_system` instead of the real file system.
+    pub fn with_file_system(mut self, file_system: &'a dyn FileSystem) -> Self {
+        self.file_system = file_system;
+        self
+    }
+
+    /// Skips directories with these names, in addition to the default ones.
+    pub fn with_skip_directories(mut self, skip_directories: &[String]) -> Self {
+        self.skip_directories.extend_from_slice(skip_directories);
+        self
+    }
+
+    /// Skips files whose name ends with one of these suffixes, in addition to the
+    /// default ones.
+    pub fn with_skip_file_suffixes(mut self, skip_file_suffixes: &[String]) -> Self {
+        self.skip_file_suffixes
+            .extend_from_slice(skip_file_suffixes);
+        self
+    }
+
+    /// Scans files with these extensions instead of `.ts`, `.tsx`, `.mts` and `.cts`. An empty
+    /// list keeps the defaults.
+    pub fn with_extensions(mut self, extensions: &[String]) -> Self {
+        if !extensions.is_empty() {
+            self.extensions = extensions.to_vec();
+        }
+        self
+    }
+
+    /// Skips files and directories matching one of the glob `patterns` (`*` and `?`).
+    /// Patterns containing `/` are matched against the path relative to `root`,
+    /// others against the file or directory name.
+    pub fn with_excludes(mut self, root: &Path, patterns: &[String]) -> Self {
+        self.exclude_root = root.to_path_buf();
+        self.excludes = patterns
+            .iter()
+            .map(|pattern| {
+                pattern
+                    .trim_start_matches("./")
+                    .trim_end_matches('/')
+                    .to_string()
+            })
+            .collect();
+        self
+    }
+
+    /// Scans test helpers and doubles (`mocks` directories, `mock.ts` or `-stub.ts`
+    /// files, ...) like any other source instead of skipping them.
+    pub fn with_tests(mut self, include_tests: bool) -> Self {
+        self.include_tests = include_tests;
+        self
+    }
+
+    /// Limits how many directory levels are scanned: 1 only lists the files directly
+    /// inside the scanned directory. `None` scans the whole tree.
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Descends into symlinked directories instead of skipping them. Each directory
+    /// is scanned once, so symlink loops terminate. Such scans walk on the calling
+    /// thread: a directory reachable through a link and through its real path is then
+    /// always listed under the path the sorted walk reaches first.
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Walks directories on up to `threads` threads, bounding how many directories
+    /// are open at once; 1 walks them on the calling thread. `None` uses one thread
+    /// per core, at most eight. Ignored when following symlinks.
+    pub fn with_walk_threads(mut self, threads: Option<usize>) -> Self {
+        if let Some(threads) = threads {
+            self.walk_threads = threads.max(1);
+        }
+        self
+    }
+
+    /// Lists the TypeScript files below `dir`, sorted. Directories that cannot be read
+    /// are skipped and recorded in `errors`.
+    pub fn scan(&self, dir: &Path, errors: &mut Vec<ScanError>) -> Vec<String> {
+        let mut files = if self.walk_threads > 1 && !self.follow_symlinks {
+            self.scan_parallel(dir, errors)
+        } else {
+            let mut files = Vec::new();
+            self.for_each_file(dir, errors, |file| files.push(file));
+            files
+        };
+        files.sort();
+        files
+    }
 
-        if dir.is_dir() {
-            for entry in fs::read_dir(dir)? {
-                let entry = entry?;
-                let path = entry.path();
-
-                if path.is_dir() {
-                    if let Some(dir_name) = path.file_name() {
-                        if let Some(name_str) = dir_name.to_str() {
-                            if self.should_skip_directory(name_str) {
-                                continue;
-                            }
-                        }
+    /// Hands the TypeScript files below `dir` to `visit` as each directory is read,
+    /// on the calling thread and without collecting them. Files come sorted within
+    /// their directory, before the files of its subdirectories. Directories that
+    /// cannot be read are skipped and recorded in `errors`.
+    pub fn for_each_file(
+        &self,
+        dir: &Path,
+        errors: &mut Vec<ScanError>,
+        mut visit: impl FnMut(String),
+    ) {
+        self.walk_with_depth(dir, self.max_depth, &mut HashSet::new(), errors, &mut visit);
+    }
+
+    /// Walks `dir` with `depth` levels left, `None` meaning unlimited. `visited` holds
+    /// the canonical paths of the directories walked so far when following symlinks.
+    fn walk_with_depth(
+        &self,
+        dir: &Path,
+        depth: Option<usize>,
+        visited: &mut HashSet<PathBuf>,
+        errors: &mut Vec<ScanError>,
+        visit: &mut dyn FnMut(String),
+    ) {
+        if !self.should_enter(dir, depth, visited) {
+            return;
+        }
+
+        match self.read_directory(dir) {
+            Ok((mut files, mut subdirs)) => {
+                files.sort();
+                subdirs.sort();
+                files.into_iter().for_each(&mut *visit);
+
+                let child_depth = depth.map(|depth| depth - 1);
+                for subdir in subdirs {
+                    self.walk_with_depth(&subdir, child_depth, visited, errors, visit);
+                }
+            }
+            Err(e) => errors.push(ScanError::new(dir, e)),
+        }
+    }
+
+    /// Scans `dir` with a pool of `walk_threads` workers that share a queue of
+    /// directories. Errors are recorded sorted by path, so the result does not depend
+    /// on which worker walked which directory.
+    fn scan_parallel(&self, dir: &Path, errors: &mut Vec<ScanError>) -> Vec<String> {
+        let queue = WalkQueue::new(dir.to_path_buf(), self.max_depth);
+
+        let results: Vec<(Vec<String>, Vec<ScanError>)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.walk_threads)
+                .map(|_| scope.spawn(|| self.walk_queue(&queue)))
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("directory walker panicked"))
+                .collect()
+        });
+
+        let mut files = Vec::new();
+        let mut walk_errors = Vec::new();
+        for (mut worker_files, mut worker_errors) in results {
+            files.append(&mut worker_files);
+            walk_errors.append(&mut worker_errors);
+        }
+        walk_errors.sort_by(|a, b| a.path.cmp(&b.path));
+        errors.append(&mut walk_errors);
+
+        files
+    }
+
+    /// Walks directories taken from `queue` until the whole tree is done, queueing
+    /// their subdirectories, and returns the files and errors this worker found.
+    fn walk_queue(&self, queue: &WalkQueue) -> (Vec<String>, Vec<ScanError>) {
+        let mut files = Vec::new();
+        let mut errors = Vec::new();
+
+        while let Some(task) = queue.next() {
+            let mut subdirs = Vec::new();
+            if task.depth != Some(0) {
+                match self.read_directory(&task.dir) {
+                    Ok((mut dir_files, dir_subdirs)) => {
+                        let child_depth = task.depth.map(|depth| depth - 1);
+                        files.append(&mut dir_files);
+                        subdirs = dir_subdirs
+                            .into_iter()
+                            .map(|subdir| (subdir, child_depth))
+                            .collect();
                     }
+                    Err(e) => errors.push(ScanError::new(&task.dir, e)),
+                }
+            }
 
-                    match self.scan(&path) {
-                        Ok(mut nested_files) => ts_files.append(&mut nested_files),
-                        Err(e) => eprintln!("Warning: Could not read directory {:?}: {}", path, e),
+            task.finish(subdirs);
+        }
+
+        (files, errors)
+    }
+
+    /// Whether `dir` is scanned: it is within the depth limit and, when following
+    /// symlinks, was not reached before through another path.
+    fn should_enter(
+        &self,
+        dir: &Path,
+        depth: Option<usize>,
+        visited: &mut HashSet<PathBuf>,
+    ) -> bool {
+        if depth == Some(0) {
+            return false;
+        }
+
+        if self.follow_symlinks
+            && let Ok(canonical) = self.file_system.canonicalize(dir)
+            && !visited.insert(canonical)
+        {
+            return false;
+        }
+
+        true
+    }
+
+    /// Lists the scannable files directly inside `dir` and the subdirectories to
+    /// descend into.
+    fn read_directory(&self, dir: &Path) -> Result<(Vec<String>, Vec<PathBuf>)> {
+        let mut ts_files = Vec::new();
+        let mut subdirs = Vec::new();
+
+        if self.file_system.is_dir(dir) {
+            for entry in self.file_system.read_dir(dir)? {
+                let path = entry.path;
+
+                if self.file_system.is_dir(&path) {
+                    if !self.follow_symlinks && entry.is_symlink {
+                        continue;
                     }
-                } else if path.is_file() {
-                    if self.should_skip_file(&path) {
+
+                    if let Some(dir_name) = path.file_name()
+                        && let Some(name_str) = dir_name.to_str()
+                        && self.should_skip_directory(name_str)
+                    {
                         continue;
                     }
 
-                    if let Some(extension) = path.extension() {
-                        if extension == "ts" || extension == "tsx" {
-                            if let Some(path_str) = path.to_str() {
-                                ts_files.push(path_str.to_string());
-                            }
-                        }
+                    if self.is_excluded(&path) {
+                        continue;
                     }
+
+                    subdirs.push(path);
+                } else if self.file_system.is_file(&path)
+                    && self.is_scannable(&path)
+                    && !self.is_excluded(&path)
+                {
+                    ts_files.push(normalize_path(&path));
                 }
             }
         }
 
-        Ok(ts_files)
+        Ok((ts_files, subdirs))
+    }
+
+    /// Whether the file has a scanned extension and is not excluded by its suffix.
+    pub fn is_scannable(&self, path: &Path) -> bool {
+        !self.should_skip_file(path)
+            && path.extension().is_some_and(|extension| {
+                self.extensions
+                    .iter()
+                    .any(|scanned| extension == scanned.as_str())
+            })
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        if self.excludes.is_empty() {
+            return false;
+        }
+
+        let relative = normalize_path(path.strip_prefix(&self.exclude_root).unwrap_or(path));
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        self.excludes.iter().any(|pattern| {
+            if pattern.contains('/') {
+                wildcard_match(pattern, &relative)
+            } else {
+                wildcard_match(pattern, name)
+            }
+        })
     }
 
     fn should_skip_directory(&self, dir_name: &str) -> bool {
-        self.skip_directories.contains(&dir_name)
+        self.skip_directories.iter().any(|dir| dir == dir_name)
+            || (!self.include_tests && TEST_SKIP_DIRECTORIES.contains(&dir_name))
     }
 
     fn should_skip_file(&self, path: &Path) -> bool {
-        if let Some(file_name) = path.file_name() {
-            if let Some(name_str) = file_name.to_str() {
-                return self
-                    .skip_file_suffixes
-                    .iter()
-                    .any(|suffix| name_str.ends_with(suffix));
-            }
+        if let Some(file_name) = path.file_name()
+            && let Some(name_str) = file_name.to_str()
+        {
+            return self
+                .skip_file_suffixes
+                .iter()
+                .any(|suffix| name_str.ends_with(suffix))
+                || (!self.include_tests
+                    && TEST_SKIP_FILE_SUFFIXES
+                        .iter()
+                        .any(|suffix| name_str.ends_with(suffix)));
         }
         false
     }
 }
+
+/// Directories waiting to be scanned by the workers of a parallel scan, each with
+/// the number of levels left below it.
+struct WalkQueue {
+    state: Mutex<WalkState>,
+    changed: Condvar,
+}
+
+struct WalkState {
+    pending: Vec<(PathBuf, Option<usize>)>,
+    /// Directories taken from the queue whose subdirectories are not queued yet
+    in_progress: usize,
+}
+
+impl WalkQueue {
+    fn new(dir: PathBuf, depth: Option<usize>) -> Self {
+        WalkQueue {
+            state: Mutex::new(WalkState {
+                pending: vec![(dir, depth)],
+                in_progress: 0,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Takes the next directory, waiting while other workers may still queue more.
+    /// Returns `None` once nothing is queued and no directory is being scanned.
+    fn next(&self) -> Option<WalkTask<'_>> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some((dir, depth)) = state.pending.pop() {
+                state.in_progress += 1;
+                return Some(WalkTask {
+                    queue: self,
+                    dir,
+                    depth,
+                    subdirs: Vec::new(),
+                });
+            }
+            if state.in_progress == 0 {
+                return None;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+}
+
+/// A directory taken from a `WalkQueue`. Dropping it marks the directory as done,
+/// so a worker that panics mid-scan does not leave the others waiting forever.
+struct WalkTask<'a> {
+    queue: &'a WalkQueue,
+    dir: PathBuf,
+    depth: Option<usize>,
+    subdirs: Vec<(PathBuf, Option<usize>)>,
+}
+
+impl WalkTask<'_> {
+    /// Marks the directory as scanned and queues its subdirectories.
+    fn finish(mut self, subdirs: Vec<(PathBuf, Option<usize>)>) {
+        self.subdirs = subdirs;
+    }
+}
+
+impl Drop for WalkTask<'_> {
+    fn drop(&mut self) {
+        let mut state = self
+            .queue
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.pending.append(&mut self.subdirs);
+        state.in_progress -= 1;
+        self.queue.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_max_depth_limits_recursion() {
+        let temp = tempdir().unwrap();
+        let nested = temp.path().join("one/two");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(temp.path().join("top.ts"), "").unwrap();
+        fs::write(temp.path().join("one/middle.ts"), "").unwrap();
+        fs::write(nested.join("deep.ts"), "").unwrap();
+
+        let file_names = |max_depth: Option<usize>| {
+            let mut errors = Vec::new();
+            let mut names: Vec<String> = Scanner::new()
+                .with_max_depth(max_depth)
+                .scan(temp.path(), &mut errors)
+                .iter()
+                .map(|file| {
+                    Path::new(file)
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .collect();
+            names.sort();
+            assert!(errors.is_empty());
+            names
+        };
+
+        assert_eq!(file_names(Some(1)), ["top.ts"]);
+        assert_eq!(file_names(Some(2)), ["middle.ts", "top.ts"]);
+        assert_eq!(file_names(None), ["deep.ts", "middle.ts", "top.ts"]);
+    }
+
+    #[test]
+    fn test_parallel_walk_matches_sequential_walk() {
+        let temp = tempdir().unwrap();
+        for dir in ["a/b/c", "a/d", "e/mocks", "e/f/g/h", "i"] {
+            fs::create_dir_all(temp.path().join(dir)).unwrap();
+        }
+        for file in [
+            "top.ts",
+            "a/one.ts",
+            "a/b/two.tsx",
+            "a/b/c/three.ts",
+            "a/b/c/notes.md",
+            "a/d/four.ts",
+            "a/d/four.stories.ts",
+            "e/mocks/skipped.ts",
+            "e/f/g/h/five.ts",
+            "i/six.ts",
+        ] {
+            fs::write(temp.path().join(file), "").unwrap();
+        }
+
+        let walk = |threads: usize, max_depth: Option<usize>| {
+            let mut errors = Vec::new();
+            let files = Scanner::new()
+                .with_walk_threads(Some(threads))
+                .with_max_depth(max_depth)
+                .scan(temp.path(), &mut errors);
+            assert!(errors.is_empty());
+            files
+        };
+
+        let sequential = walk(1, None);
+        assert_eq!(sequential.len(), 7);
+        assert!(sequential.is_sorted());
+        assert_eq!(walk(4, None), sequential);
+        assert_eq!(walk(4, Some(2)), walk(1, Some(2)));
+    }
+
+    #[test]
+    fn test_walk_queue_ends_when_a_worker_panics() {
+        let queue = WalkQueue::new(PathBuf::from("root"), None);
+        let task = queue.next().unwrap();
+
+        thread::scope(|scope| {
+            let waiting = scope.spawn(|| queue.next().is_none());
+            let panicking = scope.spawn(move || {
+                let _task = task;
+                panic!("directory walker failed");
+            });
+
+            assert!(panicking.join().is_err());
+            assert!(waiting.join().unwrap());
+        });
+    }
+
+    #[test]
+    fn test_excluded_globs_prune_matching_files_and_directories() {
+        let temp = tempdir().unwrap();
+        let src = temp.path().join("libs/src");
+        fs::create_dir_all(src.join("legacy/deep")).unwrap();
+        fs::create_dir_all(src.join("old")).unwrap();
+        fs::write(src.join("keep.ts"), "").unwrap();
+        fs::write(src.join("api.generated.ts"), "").unwrap();
+        fs::write(src.join("legacy/deep/ancient.ts"), "").unwrap();
+        fs::write(src.join("old/stale.ts"), "").unwrap();
+        fs::write(src.join("old/still-here.tsx"), "").unwrap();
+
+        let excludes = [
+            "*.generated.ts".to_string(),
+            "legacy".to_string(),
+            "libs/src/old/*.ts".to_string(),
+        ];
+        let mut errors = Vec::new();
+        let mut files: Vec<String> = Scanner::new()
+            .with_excludes(temp.path(), &excludes)
+            .scan(&temp.path().join("libs"), &mut errors)
+            .iter()
+            .map(|file| file.trim_start_matches(&*normalize_path(&src)).to_string())
+            .collect();
+        files.sort();
+
+        assert!(errors.is_empty());
+        assert_eq!(files, ["/keep.ts", "/old/still-here.tsx"]);
+    }
+
+    #[test]
+    fn test_module_typescript_files_are_scanned() {
+        let temp = tempdir().unwrap();
+        for file in ["index.mts", "legacy.cts", "types.d.mts", "notes.mjs"] {
+            fs::write(temp.path().join(file), "").unwrap();
+        }
+
+        let mut errors = Vec::new();
+        let mut names: Vec<String> = Scanner::new()
+            .scan(temp.path(), &mut errors)
+            .iter()
+            .map(|file| {
+                file.trim_start_matches(&*normalize_path(temp.path()))
+                    .to_string()
+            })
+            .collect();
+        names.sort();
+
+        assert!(errors.is_empty());
+        assert_eq!(names, ["/index.mts", "/legacy.cts"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_directory_is_followed_only_when_enabled() {
+        let temp = tempdir().unwrap();
+        let shared = temp.path().join("shared");
+        let app = temp.path().join("app");
+        fs::create_dir_all(&shared).unwrap();
+        fs::create_dir_all(&app).unwrap();
+        fs::write(shared.join("linked.ts"), "").unwrap();
+        fs::write(app.join("main.ts"), "").unwrap();
+        std::os::unix::fs::symlink(&shared, app.join("shared")).unwrap();
+        // A link back to the scanned directory must not make the scan loop
+        std::os::unix::fs::symlink(&app, app.join("loop")).unwrap();
+
+        let file_names = |follow_symlinks: bool| {
+            let mut errors = Vec::new();
+            let mut names: Vec<String> = Scanner::new()
+                .with_follow_symlinks(follow_symlinks)
+                .scan(&app, &mut errors)
+                .iter()
+                .map(|file| file.trim_start_matches(&*normalize_path(&app)).to_string())
+                .collect();
+            names.sort();
+            assert!(errors.is_empty());
+            names
+        };
+
+        assert_eq!(file_names(false), ["/main.ts"]);
+        assert_eq!(file_names(true), ["/main.ts", "/shared/linked.ts"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_directory_reached_through_symlink_is_listed_under_one_path() {
+        let temp = tempdir().unwrap();
+        let real = temp.path().join("b-real");
+        fs::create_dir_all(real.join("deep")).unwrap();
+        fs::write(real.join("deep/file.ts"), "").unwrap();
+        std::os::unix::fs::symlink(&real, temp.path().join("a-link")).unwrap();
+
+        for _ in 0..20 {
+            let mut errors = Vec::new();
+            let files: Vec<String> = Scanner::new()
+                .with_follow_symlinks(true)
+                .with_walk_threads(Some(8))
+                .scan(temp.path(), &mut errors)
+                .iter()
+                .map(|file| {
+                    file.trim_start_matches(&*normalize_path(temp.path()))
+                        .to_string()
+                })
+                .collect();
+
+            assert!(errors.is_empty());
+            assert_eq!(files, ["/a-link/deep/file.ts"]);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::env;
+use std::fmt::Display;
+use std::io::{self, IsTerminal};
+
+/// When the text output is colorized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Colorize when stdout is a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+/// Colors the parts of the text output: entity types, file paths and unused markers.
+/// A disabled palette returns the text unchanged.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Palette {
+    enabled: bool,
+}
+
+impl Palette {
+    pub fn new(color: ColorChoice) -> Self {
+        Palette {
+            enabled: color.enabled(),
+        }
+    }
+
+    pub fn entity_type(&self, text: impl Display) -> String {
+        self.paint("36", text)
+    }
+
+    pub fn path(&self, text: impl Display) -> String {
+        self.paint("2", text)
+    }
+
+    pub fn unused(&self, text: impl Display) -> String {
+        self.paint("31", text)
+    }
+
+    fn paint(&self, code: &str, text: impl Display) -> String {
+        if self.enabled {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_only_paints_when_enabled() {
+        assert_eq!(
+            Palette::new(ColorChoice::Always).unused("3 unused"),
+            "\x1b[31m3 unused\x1b[0m"
+        );
+        assert_eq!(
+            Palette::new(ColorChoice::Never).unused("3 unused"),
+            "3 unused"
+        );
+    }
+}
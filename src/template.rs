@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+// Start of an Angular component decorator
+static COMPONENT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"@Component\s*\(").unwrap());
+
+// Class declared after a decorator, capturing its name
+static CLASS_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bclass\s+([A-Za-z_$][\w$]*)").unwrap());
+
+static SELECTOR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\bselector\s*:\s*['"`]([^'"`]+)['"`]"#).unwrap());
+
+static TEMPLATE_URL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\btemplateUrl\s*:\s*['"`]([^'"`]+)['"`]"#).unwrap());
+
+static INLINE_TEMPLATE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\btemplate\s*:\s*(?:`([^`]*)`|'([^'\n]*)'|"([^"\n]*)")"#).unwrap()
+});
+
+/// How a component is referenced from a template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Selector {
+    /// `app-foo`, used as `<app-foo>`
+    Element(String),
+    /// `[appFoo]`, used as an attribute such as `<div appFoo>` or `<div [appFoo]="x">`
+    Attribute(String),
+}
+
+impl Selector {
+    /// Parses one selector of a (comma-separated) selector list. Compound selectors
+    /// such as `button[appFoo]` are matched by their attribute; class selectors are
+    /// not supported.
+    fn parse(selector: &str) -> Option<Selector> {
+        let selector = selector.trim();
+        if let Some(start) = selector.find('[') {
+            let name = selector[start + 1..]
+                .split([']', '='])
+                .next()
+                .unwrap_or("")
+                .trim();
+            return (!name.is_empty()).then(|| Selector::Attribute(name.to_string()));
+        }
+        if selector.is_empty() || selector.starts_with('.') || selector.starts_with(':') {
+            return None;
+        }
+        let name = selector.split(['.', ':']).next().unwrap_or(selector);
+        Some(Selector::Element(name.to_string()))
+    }
+
+    /// Whether `template` uses the selector.
+    pub fn is_used_in(&self, template: &str) -> bool {
+        let pattern = match self {
+            Selector::Element(name) => format!(r"<{}(?:[\s/>]|$)", regex::escape(name)),
+            Selector::Attribute(name) => {
+                format!(r"<[^>]*[\s\[(*]{}(?:[\s\])=/>]|$)", regex::escape(name))
+            }
+        };
+        Regex::new(&pattern).is_ok_and(|re| re.is_match(template))
+    }
+}
+
+/// An Angular component declared in a TypeScript file.
+#[derive(Debug)]
+pub(crate) struct Component {
+    pub class_name: String,
+    pub selectors: Vec<Selector>,
+    /// The file named by `templateUrl`, resolved against the component's directory
+    pub template_path: Option<PathBuf>,
+    /// The template given inline with `template: ...`
+    pub inline_template: Option<String>,
+}
+
+/// Finds the `@Component` decorated classes in `content`, read from `file_path`.
+pub(crate) fn components(content: &str, file_path: &str) -> Vec<Component> {
+    let dir = Path::new(file_path).parent().unwrap_or(Path::new(""));
+    let mut components = Vec::new();
+
+    for decorator in COMPONENT_RE.find_iter(content) {
+        let rest = &content[decorator.end()..];
+        let Some(class) = CLASS_RE.captures(rest) else {
+            continue;
+        };
+        // The metadata is everything between the decorator and the class keyword
+        let metadata = &rest[..class.get(0).map_or(0, |m| m.start())];
+
+        let selectors = SELECTOR_RE
+            .captures(metadata)
+            .map(|caps| caps[1].split(',').filter_map(Selector::parse).collect())
+            .unwrap_or_default();
+
+        components.push(Component {
+            class_name: class[1].to_string(),
+            selectors,
+            template_path: TEMPLATE_URL_RE
+                .captures(metadata)
+                .map(|caps| dir.join(&caps[1])),
+            inline_template: INLINE_TEMPLATE_RE.captures(metadata).and_then(|caps| {
+                (1..=3).find_map(|group| caps.get(group).map(|m| m.as_str().to_string()))
+            }),
+        });
+    }
+
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_components_read_selectors_and_templates() {
+        let content = r#"
+@Component({
+  selector: 'app-card, button[appCard]',
+  templateUrl: './card.component.html',
+})
+export class CardComponent {}
+
+@Component({ selector: 'app-badge', template: `<span>{{ label }}</span>` })
+export class BadgeComponent {}
+"#;
+        let found = components(content, "/project/src/card.component.ts");
+
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].class_name, "CardComponent");
+        assert_eq!(
+            found[0].selectors,
+            [
+                Selector::Element("app-card".to_string()),
+                Selector::Attribute("appCard".to_string())
+            ]
+        );
+        assert_eq!(
+            found[0].template_path,
+            Some(PathBuf::from("/project/src/./card.component.html"))
+        );
+        assert_eq!(found[1].class_name, "BadgeComponent");
+        assert_eq!(
+            found[1].inline_template.as_deref(),
+            Some("<span>{{ label }}</span>")
+        );
+    }
+
+    #[test]
+    fn test_selector_usage_in_template() {
+        let element = Selector::Element("app-card".to_string());
+        assert!(element.is_used_in("<div><app-card [title]=\"t\"></app-card></div>"));
+        assert!(!element.is_used_in("<app-card-list></app-card-list>"));
+
+        let attribute = Selector::Attribute("appCard".to_string());
+        assert!(attribute.is_used_in("<button appCard>Go</button>"));
+        assert!(attribute.is_used_in("<button [appCard]=\"x\">Go</button>"));
+        assert!(!attribute.is_used_in("<p>appCard</p>"));
+    }
+}